@@ -0,0 +1,238 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::common::client::client::BiliClient;
+use crate::common::utils::create_private_dir;
+use crate::config::config_dir;
+
+/// 账号索引文件名，位于配置目录
+pub const ACCOUNTS_FILE: &str = "accounts.toml";
+/// 账号登录信息目录名，每个账号一个以 mid 命名的子目录
+pub const ACCOUNTS_DIR: &str = "accounts";
+/// 账号目录中的 Cookie 文件名
+pub const COOKIES_FILE: &str = "cookies.jsonl";
+
+/// 一个已保存的账号
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Account {
+    pub mid: u64,
+    pub name: String,  // 自定义名称，用于 --account 选择
+    pub uname: String, // B站昵称
+    pub added_at: i64, // unix 时间戳（秒）
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AccountIndex {
+    default: Option<u64>,
+    #[serde(rename = "account", default)]
+    accounts: Vec<Account>,
+}
+
+/// 多账号存储
+///
+/// ```text
+/// ~/.config/bilidl/accounts.toml               # 账号索引与默认账号
+/// ~/.config/bilidl/accounts/<mid>/cookies.jsonl
+/// ```
+#[derive(Debug)]
+pub struct AccountStore {
+    root: PathBuf,
+    index: AccountIndex,
+}
+
+impl AccountStore {
+    /// 打开配置目录中的账号存储
+    pub fn open_default() -> Result<Self> {
+        let root = config_dir().ok_or_else(|| anyhow!("无法确定配置目录，请设置 HOME 环境变量"))?;
+        Self::open(&root)
+    }
+
+    /// 打开指定目录中的账号存储，索引文件不存在时为空
+    pub fn open(root: &Path) -> Result<Self> {
+        let path = root.join(ACCOUNTS_FILE);
+        let index = match std::fs::read_to_string(&path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| anyhow!("账号索引 {:?} 格式错误: {}", path, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AccountIndex::default(),
+            Err(e) => return Err(anyhow!("读取账号索引 {:?} 失败: {}", path, e)),
+        };
+        Ok(Self {
+            root: root.to_path_buf(),
+            index,
+        })
+    }
+
+    pub fn accounts(&self) -> &[Account] {
+        &self.index.accounts
+    }
+
+    pub fn default_account(&self) -> Option<&Account> {
+        let mid = self.index.default?;
+        self.index
+            .accounts
+            .iter()
+            .find(|account| account.mid == mid)
+    }
+
+    /// 按名称、mid 或昵称查找账号
+    pub fn find(&self, selector: &str) -> Option<&Account> {
+        let accounts = &self.index.accounts;
+        accounts
+            .iter()
+            .find(|account| account.name == selector)
+            .or_else(|| {
+                let mid = selector.parse::<u64>().ok()?;
+                accounts.iter().find(|account| account.mid == mid)
+            })
+            .or_else(|| accounts.iter().find(|account| account.uname == selector))
+    }
+
+    /// 按名称、mid 或昵称查找账号，找不到时返回包含已有账号的错误
+    pub fn resolve(&self, selector: &str) -> Result<&Account> {
+        self.find(selector).ok_or_else(|| {
+            let names: Vec<&str> = self
+                .index
+                .accounts
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect();
+            anyhow!(
+                "未找到账号 {:?}，已保存的账号: {}",
+                selector,
+                if names.is_empty() {
+                    "无".to_string()
+                } else {
+                    names.join(", ")
+                }
+            )
+        })
+    }
+
+    /// 账号登录信息所在目录
+    pub fn account_dir(&self, account: &Account) -> PathBuf {
+        self.root.join(ACCOUNTS_DIR).join(account.mid.to_string())
+    }
+
+    /// 账号的 Cookie 文件
+    pub fn cookie_path(&self, account: &Account) -> PathBuf {
+        self.account_dir(account).join(COOKIES_FILE)
+    }
+
+    /// 保存已登录客户端的 Cookie，同一 mid 重复添加时更新登录信息
+    ///
+    /// 第一个账号自动成为默认账号。
+    pub async fn add(
+        &mut self,
+        mid: u64,
+        name: Option<String>,
+        uname: &str,
+        client: &BiliClient,
+    ) -> Result<Account> {
+        let name = name.unwrap_or_else(|| uname.to_string());
+        if let Some(other) = self.find(&name).filter(|other| other.mid != mid) {
+            return Err(anyhow!(
+                "名称 {:?} 已被账号 {} ({}) 使用",
+                name,
+                other.uname,
+                other.mid
+            ));
+        }
+
+        let account = Account {
+            mid,
+            name,
+            uname: uname.to_string(),
+            added_at: chrono::Utc::now().timestamp(),
+        };
+        let dir = self.account_dir(&account);
+        create_private_dir(&dir).map_err(|e| anyhow!("创建目录 {:?} 失败: {}", dir, e))?;
+        client
+            .save_cookies_to_local(&self.cookie_path(&account).to_string_lossy())
+            .await?;
+
+        self.index.accounts.retain(|existing| existing.mid != mid);
+        self.index.accounts.push(account.clone());
+        if self.default_account().is_none() {
+            self.index.default = Some(mid);
+        }
+        self.save()?;
+        debug!("已保存账号 {} ({}) 到 {:?}", account.name, mid, dir);
+        Ok(account)
+    }
+
+    /// 删除账号及其登录信息；删除的是默认账号时，剩余的第一个账号成为默认账号
+    pub fn remove(&mut self, selector: &str) -> Result<Account> {
+        let account = self.resolve(selector)?.clone();
+        let dir = self.account_dir(&account);
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir).map_err(|e| anyhow!("删除目录 {:?} 失败: {}", dir, e))?;
+        }
+        self.index
+            .accounts
+            .retain(|existing| existing.mid != account.mid);
+        if self.index.default == Some(account.mid) {
+            self.index.default = self.index.accounts.first().map(|a| a.mid);
+        }
+        self.save()?;
+        Ok(account)
+    }
+
+    /// 设置默认账号
+    pub fn set_default(&mut self, selector: &str) -> Result<Account> {
+        let account = self.resolve(selector)?.clone();
+        self.index.default = Some(account.mid);
+        self.save()?;
+        Ok(account)
+    }
+
+    fn save(&self) -> Result<()> {
+        create_private_dir(&self.root)
+            .map_err(|e| anyhow!("创建目录 {:?} 失败: {}", self.root, e))?;
+        let path = self.root.join(ACCOUNTS_FILE);
+        let content = toml::to_string_pretty(&self.index)?;
+        std::fs::write(&path, content).map_err(|e| anyhow!("写入账号索引 {:?} 失败: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_account_store() {
+        let root = std::env::temp_dir().join(format!("bilidl-accounts-{}", uuid::Uuid::new_v4()));
+        let client = BiliClient::new();
+
+        let mut store = AccountStore::open(&root).unwrap();
+        store.add(1001, None, "主号", &client).await.unwrap();
+        store
+            .add(2002, Some("work".to_string()), "小号", &client)
+            .await
+            .unwrap();
+        assert!(store.cookie_path(&store.accounts()[0]).exists());
+        assert!(
+            store
+                .add(3003, Some("work".to_string()), "其他", &client)
+                .await
+                .is_err()
+        );
+
+        // 第一个账号为默认账号，可按名称、mid、昵称查找
+        let store = AccountStore::open(&root).unwrap();
+        assert_eq!(store.default_account().unwrap().mid, 1001);
+        assert_eq!(store.find("work").unwrap().mid, 2002);
+        assert_eq!(store.find("1001").unwrap().name, "主号");
+        assert_eq!(store.find("小号").unwrap().mid, 2002);
+
+        let mut store = store;
+        store.set_default("work").unwrap();
+        store.remove("work").unwrap();
+        assert_eq!(store.default_account().unwrap().mid, 1001);
+        assert!(store.resolve("work").is_err());
+
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,255 @@
+use std::fmt::Write;
+use std::path::Path;
+
+use anyhow::{Result, anyhow};
+use cookie::Cookie;
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore};
+use reqwest::Url;
+use time::OffsetDateTime;
+use tracing::debug;
+
+// 请求头中的 Cookie 不带域名，视为整个 B站 域名下的 Cookie
+const DEFAULT_DOMAIN: &str = "bilibili.com";
+
+/// Cookie 的文本格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CookieFormat {
+    Json,     // cookie_store 的 JSON Lines，账号登录信息的保存格式
+    Netscape, // cookies.txt，curl/wget/yt-dlp 等工具通用
+    Header,   // Cookie 请求头: SESSDATA=...; bili_jct=...
+}
+
+/// 识别 Cookie 文本的格式
+pub fn detect(content: &str) -> CookieFormat {
+    let content = content.trim_start();
+    if content.starts_with('{') {
+        CookieFormat::Json
+    } else if content.starts_with("# Netscape")
+        || content.starts_with("# HTTP Cookie File")
+        || content.lines().any(|line| line.split('\t').count() == 7)
+    {
+        CookieFormat::Netscape
+    } else {
+        CookieFormat::Header
+    }
+}
+
+/// 加载 `--cookie` 参数：已存在的文件按内容识别格式，否则视为 Cookie 请求头
+pub fn load(input: &str) -> Result<CookieStore> {
+    let path = Path::new(input);
+    if path.is_file() {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("读取 Cookie 文件 {:?} 失败: {}", path, e))?;
+        return parse(&content);
+    }
+    if !input.contains('=') {
+        return Err(anyhow!(
+            "{:?} 既不是 Cookie 文件，也不是 name=value 格式的 Cookie",
+            input
+        ));
+    }
+    parse_header(input)
+}
+
+/// 按识别出的格式解析 Cookie 文本
+pub fn parse(content: &str) -> Result<CookieStore> {
+    match detect(content) {
+        CookieFormat::Json => parse_json(content),
+        CookieFormat::Netscape => parse_netscape(content),
+        CookieFormat::Header => parse_header(content),
+    }
+}
+
+pub fn parse_json(content: &str) -> Result<CookieStore> {
+    CookieStore::load(content.as_bytes(), |s| serde_json::from_str(s))
+        .map_err(|e| anyhow!("解析 JSON Cookie 失败: {}", e))
+}
+
+pub fn parse_netscape(content: &str) -> Result<CookieStore> {
+    let mut store = CookieStore::default();
+    for line in content.lines() {
+        // #HttpOnly_ 前缀表示 HttpOnly Cookie，其余 # 开头的行为注释
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        let [
+            domain,
+            include_subdomains,
+            path,
+            secure,
+            expires,
+            name,
+            value,
+        ] = fields[..]
+        else {
+            debug!("跳过无法识别的 cookies.txt 行: {}", line);
+            continue;
+        };
+
+        let host = domain.trim_start_matches('.');
+        let mut cookie = Cookie::build((name.to_string(), value.to_string()))
+            .path(path.to_string())
+            .secure(secure.eq_ignore_ascii_case("TRUE"))
+            .http_only(http_only);
+        // 包含子域名时对应 Domain 属性，否则为仅限该主机的 Cookie
+        if include_subdomains.eq_ignore_ascii_case("TRUE") {
+            cookie = cookie.domain(host.to_string());
+        }
+        // 过期时间为 0 表示会话 Cookie
+        if let Ok(expires) = expires.parse::<i64>()
+            && expires > 0
+            && let Ok(expires) = OffsetDateTime::from_unix_timestamp(expires)
+        {
+            cookie = cookie.expires(expires);
+        }
+        insert(&mut store, &cookie.build(), host);
+    }
+    non_empty(store)
+}
+
+pub fn parse_header(header: &str) -> Result<CookieStore> {
+    // 允许直接粘贴带 "Cookie:" 前缀的整行请求头
+    let header = header.trim();
+    let header = header
+        .strip_prefix("Cookie:")
+        .or_else(|| header.strip_prefix("cookie:"))
+        .unwrap_or(header);
+
+    let mut store = CookieStore::default();
+    for pair in header.split(';') {
+        let Some((name, value)) = pair.trim().split_once('=') else {
+            continue;
+        };
+        if name.trim().is_empty() {
+            continue;
+        }
+        let cookie = Cookie::build((name.trim().to_string(), value.trim().to_string()))
+            .domain(DEFAULT_DOMAIN)
+            .path("/")
+            .build();
+        insert(&mut store, &cookie, &format!("www.{}", DEFAULT_DOMAIN));
+    }
+    non_empty(store)
+}
+
+/// 按指定格式导出未过期的 Cookie
+pub fn export(store: &CookieStore, format: CookieFormat) -> Result<String> {
+    let mut out = String::new();
+    match format {
+        CookieFormat::Json => {
+            let mut buf = Vec::new();
+            store
+                .save(&mut buf, serde_json::to_string)
+                .map_err(|e| anyhow!("导出 JSON Cookie 失败: {}", e))?;
+            out = String::from_utf8(buf)?;
+        }
+        CookieFormat::Netscape => {
+            out.push_str("# Netscape HTTP Cookie File\n");
+            for cookie in store.iter_unexpired() {
+                let (domain, include_subdomains) = match &cookie.domain {
+                    CookieDomain::Suffix(domain) => (format!(".{}", domain), "TRUE"),
+                    CookieDomain::HostOnly(domain) => (domain.clone(), "FALSE"),
+                    _ => continue,
+                };
+                let expires = match cookie.expires {
+                    CookieExpiration::AtUtc(expires) => expires.unix_timestamp(),
+                    CookieExpiration::SessionEnd => 0,
+                };
+                let _ = writeln!(
+                    out,
+                    "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    if cookie.http_only() == Some(true) {
+                        "#HttpOnly_"
+                    } else {
+                        ""
+                    },
+                    domain,
+                    include_subdomains,
+                    AsRef::<str>::as_ref(&cookie.path),
+                    if cookie.secure() == Some(true) {
+                        "TRUE"
+                    } else {
+                        "FALSE"
+                    },
+                    expires,
+                    cookie.name(),
+                    cookie.value()
+                );
+            }
+        }
+        CookieFormat::Header => {
+            let pairs: Vec<String> = store
+                .iter_unexpired()
+                .map(|cookie| format!("{}={}", cookie.name(), cookie.value()))
+                .collect();
+            out = pairs.join("; ");
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+fn insert(store: &mut CookieStore, cookie: &Cookie<'_>, host: &str) {
+    let url = match Url::parse(&format!("https://{}/", host)) {
+        Ok(url) => url,
+        Err(e) => {
+            debug!("忽略 Cookie {}: 无效的域名 {}: {}", cookie.name(), host, e);
+            return;
+        }
+    };
+    // 已过期或与域名不匹配的 Cookie 会被拒绝
+    if let Err(e) = store.insert_raw(cookie, &url) {
+        debug!("忽略 Cookie {}: {}", cookie.name(), e);
+    }
+}
+
+fn non_empty(store: CookieStore) -> Result<CookieStore> {
+    if store.iter_unexpired().next().is_none() {
+        return Err(anyhow!("没有找到有效的 Cookie"));
+    }
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cookie_formats() {
+        assert_eq!(detect("SESSDATA=abc; bili_jct=def"), CookieFormat::Header);
+
+        // 请求头 -> cookies.txt -> 请求头
+        let store = parse("Cookie: SESSDATA=abc%2C123; bili_jct=def").unwrap();
+        let netscape = export(&store, CookieFormat::Netscape).unwrap();
+        assert_eq!(detect(&netscape), CookieFormat::Netscape);
+        assert!(netscape.contains(".bilibili.com\tTRUE\t/\tFALSE\t0\tSESSDATA\tabc%2C123\n"));
+
+        let store = parse(&netscape).unwrap();
+        let header = export(&store, CookieFormat::Header).unwrap();
+        let mut pairs: Vec<&str> = header.trim().split("; ").collect();
+        pairs.sort();
+        assert_eq!(pairs, ["SESSDATA=abc%2C123", "bili_jct=def"]);
+
+        // HttpOnly 前缀与过期时间，已过期的 Cookie 被忽略
+        let store = parse(
+            "# Netscape HTTP Cookie File\n\
+             #HttpOnly_.bilibili.com\tTRUE\t/\tTRUE\t4102444800\tSESSDATA\txyz\n\
+             .bilibili.com\tTRUE\t/\tFALSE\t1\texpired\t1\n",
+        )
+        .unwrap();
+        let json = export(&store, CookieFormat::Json).unwrap();
+        assert_eq!(detect(&json), CookieFormat::Json);
+        let store = parse(&json).unwrap();
+        assert_eq!(
+            export(&store, CookieFormat::Header).unwrap(),
+            "SESSDATA=xyz\n"
+        );
+
+        assert!(load("not a cookie").is_err());
+    }
+}
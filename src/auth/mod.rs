@@ -1,3 +1,5 @@
+pub mod accounts;
+pub mod cookies;
 mod errors;
 mod qr_display;
 mod session;
@@ -9,11 +11,12 @@ use tokio::sync::Mutex;
 use std::thread::sleep;
 use std::time::Duration;
 
-use anyhow::Error;
+use anyhow::{Error, anyhow};
 use colored::Colorize;
 use tracing::{error, info};
 use uuid::Uuid;
 
+use accounts::{Account, AccountStore};
 use session::SessionManager;
 
 use crate::common::client::client::BiliClient;
@@ -75,21 +78,78 @@ impl AuthManager {
         // 通过读取文件来检查登录状态
         let mut new_client = BiliClient::new();
         new_client.load_cookies_from_local(path.as_str()).await;
-        // 检查登录状态
-        if let Err(_) = new_client.check_qr_login_status().await {
+        self.login_by_client(new_client, path).await
+    }
+
+    // 使用 --cookie 参数登录：可以是 Cookie 请求头，也可以是 cookies.txt 或 JSON 格式的 Cookie 文件
+    pub async fn login_by_cookie(&self, cookie: &str) -> Result<Option<Uuid>, Error> {
+        let mut new_client = BiliClient::new();
+        new_client.set_cookie_store(cookies::load(cookie)?)?;
+        // 不在日志中输出 Cookie 内容
+        let source = if std::path::Path::new(cookie).is_file() {
+            cookie
+        } else {
+            "--cookie"
+        };
+        self.login_by_client(new_client, source).await
+    }
+
+    // 检查客户端的登录状态，有效时创建会话
+    async fn login_by_client(
+        &self,
+        client: BiliClient,
+        source: &str,
+    ) -> Result<Option<Uuid>, Error> {
+        if client.check_qr_login_status().await.is_err() {
             // 登录状态无效
-            info!("{}: {}", "登录状态无效".red(), path);
+            info!("{}: {}", "登录状态无效".red(), source);
             return Ok(None);
-        } else {
-            // 登录状态有效
-            info!("{}: {}", "登录状态有效".green(), path);
-            // 创建新的会话
-            let session_manager = self.session_manager.lock().await;
-            let session_id = Uuid::new_v4();
-            session_manager
-                .create_session(session_id, &new_client)
-                .await?;
-            return Ok(Some(session_id));
+        }
+        // 登录状态有效
+        info!("{}: {}", "登录状态有效".green(), source);
+        // 创建新的会话
+        let session_manager = self.session_manager.lock().await;
+        let session_id = Uuid::new_v4();
+        session_manager
+            .create_session(session_id, &client)
+            .await?;
+        Ok(Some(session_id))
+    }
+
+    // 使用已保存的账号登录：指定名称或 mid 时使用该账号，否则使用默认账号；没有可用的账号时返回 None
+    pub async fn login_account(
+        &self,
+        selector: Option<&str>,
+    ) -> Result<Option<(Uuid, Account)>, Error> {
+        let store = match (AccountStore::open_default(), selector) {
+            (Ok(store), _) => store,
+            (Err(e), None) => {
+                info!("无法读取账号列表: {}", e);
+                return Ok(None);
+            }
+            (Err(e), Some(_)) => return Err(e),
+        };
+        let account = match selector {
+            Some(selector) => store.resolve(selector)?.clone(),
+            None => match store.default_account() {
+                Some(account) => account.clone(),
+                None => return Ok(None),
+            },
+        };
+
+        let path = store.cookie_path(&account);
+        if !path.is_file() {
+            return Err(anyhow!(
+                "账号 {} 的登录信息不存在，请使用 bilidl account add 重新登录",
+                account.name
+            ));
+        }
+        match self.login_by_cookies(&path.to_string_lossy().to_string()).await? {
+            Some(session_id) => Ok(Some((session_id, account))),
+            None => Err(anyhow!(
+                "账号 {} 的登录已失效，请使用 bilidl account add 重新登录",
+                account.name
+            )),
         }
     }
 
@@ -183,7 +243,9 @@ impl AuthManager {
                         let session_manager = self.session_manager.lock().await;
                         info!("login success, {:?}", data);
                         // 使用登录成功后的 cookies 创建会话
-                        session_manager.create_session(session_id, &client).await?;
+                        session_manager
+                .create_session(session_id, &client)
+                .await?;
                         return Ok(session_id);
                     }
                     86038 => {
@@ -22,7 +22,7 @@ impl SessionManager {
         }
     }
 
-    // 登录成功之后，创建新的会话
+    // 登录成功之后，创建新的会话（仅保存在内存中，账号的持久化见 accounts 模块）
     pub async fn create_session(
         &self,
         session_id: Uuid,
@@ -38,31 +38,9 @@ impl SessionManager {
         info!("{}: {}", "创建会话".green(), session_id);
         sessions.insert(session_id, client.clone());
 
-        // 保存会话到文件
-        self.save_session_to_file(session_id, client).await?;
-        info!("{}: {}", "会话已保存".green(), session_id);
-
         Ok(session_id)
     }
 
-    pub async fn save_session_to_file(
-        &self,
-        session_id: Uuid,
-        client: &BiliClient,
-    ) -> Result<(), ApiError> {
-        // 将会话信息保存到文件
-        let folder_path = format!("./sessions/{}", session_id);
-        std::fs::create_dir_all(&folder_path)
-            .map_err(|e| ApiError::InvalidResponse(format!("创建目录失败: {}", e)))?;
-        let file_path = format!("{}/cookies.jsonl", folder_path);
-        client
-            .save_cookies_to_local(file_path.as_str())
-            .await
-            .map_err(|e| ApiError::InvalidResponse(format!("保存会话失败: {}", e)))?;
-        info!("{}: {}", "会话已保存".green(), session_id);
-        Ok(())
-    }
-
     // 获取会话中的客户端（自动携带最新令牌）
     pub async fn get_authed_client(&self, session_id: Uuid) -> Result<BiliClient, ApiError> {
         let sessions = self.sessions.lock().await;
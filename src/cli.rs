@@ -1,6 +1,11 @@
-use clap::{Parser, ValueEnum};
+use clap::{Parser, Subcommand, ValueEnum};
+use std::net::{IpAddr, SocketAddr};
 use std::path::PathBuf;
 
+use crate::common::client::network::{ProxySetting, parse_header};
+use crate::downloader::rate_limit::{RateSchedule, parse_rate};
+use crate::parser::stream_selector::StreamSelector;
+
 /// 视频清晰度选项
 #[derive(Debug, Clone, ValueEnum)]
 pub enum QualityOption {
@@ -31,13 +36,64 @@ pub enum QualityOption {
     /// HDR 真彩色
     #[value(name = "hdr")]
     QHdr,
+    /// 杜比视界
+    #[value(name = "dolby")]
+    QDolby,
     /// 超高清 8K
     #[value(name = "8k")]
     Q8K,
 }
 
+/// 仅音频模式的输出格式
+#[derive(Debug, Clone, ValueEnum)]
+pub enum AudioFormatOption {
+    /// 直接封装为 m4a，不重新编码
+    #[value(name = "m4a")]
+    M4a,
+    /// 转码为 mp3
+    #[value(name = "mp3")]
+    Mp3,
+    /// 转码为 flac 无损格式
+    #[value(name = "flac")]
+    Flac,
+    /// 转码为 opus
+    #[value(name = "opus")]
+    Opus,
+}
+
+/// 首选流格式
+#[derive(Debug, Clone, ValueEnum)]
+pub enum StreamFormatOption {
+    /// 优先 DASH，不可用时自动回退到 MP4/FLV
+    #[value(name = "auto")]
+    Auto,
+    /// DASH 音视频分离流，支持 HDR/杜比/8K/AV1
+    #[value(name = "dash")]
+    Dash,
+    /// MP4 整合流
+    #[value(name = "mp4")]
+    Mp4,
+    /// FLV 整合流（旧格式）
+    #[value(name = "flv")]
+    Flv,
+}
+
+/// Cookie 导出格式
+#[derive(Debug, Clone, ValueEnum)]
+pub enum CookieFormatOption {
+    /// Netscape cookies.txt，可用于 curl/wget/yt-dlp 等工具
+    #[value(name = "netscape")]
+    Netscape,
+    /// Cookie 请求头: SESSDATA=...; bili_jct=...
+    #[value(name = "header")]
+    Header,
+    /// bilidl 保存登录信息使用的 JSON 格式
+    #[value(name = "json")]
+    Json,
+}
+
 /// B站视频下载器 - 支持下载B站视频、番剧、课程等内容
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(name = "bilidl")]
 #[command(version = "1.0")]
 #[command(author = "rpeng252@gmail.com")]
@@ -53,55 +109,313 @@ B站视频下载器 (bilidl) - 一个功能强大的B站内容下载工具
 • 支持弹幕和字幕下载
 
 使用示例:
-  # 下载单个视频
-  bilidl --url "https://www.bilibili.com/video/BV1xx411x7x1"
+  # 下载单个视频 (bilidl <URL> 是 bilidl download <URL> 的简写)
+  bilidl "https://www.bilibili.com/video/BV1xx411x7x1"
+
+  # 下载高清视频 (需要先登录，第一个添加的账号为默认账号)
+  bilidl account add --name main
+  bilidl download BV1xx411x7x1 --quality 1080p
 
-  # 下载高清视频 (需要先登录)
-  bilidl --login
-  bilidl --url "https://www.bilibili.com/video/BV1xx411x7x1" --quality 1080p
+  # 使用指定账号下载
+  bilidl --account work download BV1xx411x7x1
 
   # 下载番剧指定集数
-  bilidl --url "https://www.bilibili.com/bangumi/play/ss12345" --parts "1-3,5"
+  bilidl download "https://www.bilibili.com/bangumi/play/ss12345" --parts "1-3,5"
+
+  # 批量下载列表中的链接，每行可单独指定 --parts/--quality
+  bilidl download --batch-file urls.txt
+  cat urls.txt | bilidl download --batch-file -
+
+  # 仅提取音频并转为 mp3
+  bilidl download BV1xx411x7x1 --audio-only --audio-format mp3
+
+  # 按表达式选择视频流
+  bilidl download BV1xx411x7x1 --select "1080p60/1080p/best"
 
-  # 仅登录保存认证信息
-  bilidl --login
+  # 查看视频信息和可下载的流，不下载
+  bilidl info BV1xx411x7x1 --quality 4k
+
+  # 继续上次中断的下载
+  bilidl resume
+
+  # 订阅收藏夹/UP主/追番，定期下载新内容
+  bilidl watch sources.toml
+
+  # 将 XML 弹幕转换为 ASS 字幕
+  bilidl danmaku convert "视频标题.xml"
 
   # 启动MCP服务器 (stdio模式)
-  bilidl --mcp
+  bilidl mcp
 
-  # 启动HTTP MCP服务器
-  bilidl --http 127.0.0.1:3000
+  # 启动HTTP MCP服务器 (需要 --features http 编译)
+  bilidl serve --listen 127.0.0.1:3000
 
 注意事项:
-• 下载高清/付费内容需要先使用 --login 进行登录
+• 下载高清/付费内容需要先使用 bilidl account add 登录
+• 全局选项（登录、网络、超时等）可以写在子命令前后
 • 首次使用会自动下载FFmpeg用于音视频合并
 • 支持的URL格式: 视频/av/bv, 番剧/ss/ep, 课程/cheese
 • MCP服务器支持AI助手集成，提供视频下载API
 "#)]
 pub struct Cli {
-    /// 视频/番剧/课程链接 (支持多种B站URL格式)
-    #[arg(long, value_name = "URL")]
+    #[command(flatten)]
+    pub global: GlobalArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// 未指定子命令时的下载参数，`bilidl <URL>` 等同于 `bilidl download <URL>`
+    #[command(flatten)]
+    pub download: DownloadArgs,
+}
+
+impl Cli {
+    /// 要执行的子命令，未指定时为 download
+    pub fn subcommand(&self) -> Command {
+        self.command
+            .clone()
+            .unwrap_or_else(|| Command::Download(self.download.clone()))
+    }
+}
+
+/// 子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// 下载视频/番剧/课程
+    Download(DownloadArgs),
+    /// 解析并显示视频信息和将要下载的流，不下载
+    Info(InfoArgs),
+    /// 扫码登录B站账号并保存，等同于 account add
+    Login(AccountAddArgs),
+    /// 账号管理
+    Account {
+        #[command(subcommand)]
+        command: AccountCommand,
+    },
+    /// 继续上次未完成的下载
+    Resume,
+    /// 订阅模式：定期检查配置中的订阅源并下载新内容
+    Watch(WatchArgs),
+    /// 启动MCP服务器 (stdio模式)
+    Mcp,
+    /// 启动HTTP MCP服务器
+    Serve(ServeArgs),
+    /// 弹幕工具
+    Danmaku {
+        #[command(subcommand)]
+        command: DanmakuCommand,
+    },
+}
+
+/// 账号子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum AccountCommand {
+    /// 列出已保存的账号
+    List,
+    /// 扫码登录并保存账号
+    Add(AccountAddArgs),
+    /// 删除已保存的账号
+    Remove {
+        /// 账号名称或 mid
+        #[arg(value_name = "ACCOUNT")]
+        account: String,
+    },
+    /// 设置默认账号，未指定 --account 时自动使用
+    Default {
+        /// 账号名称或 mid
+        #[arg(value_name = "ACCOUNT")]
+        account: String,
+    },
+    /// 显示当前使用的账号
+    Whoami,
+    /// 导出账号的 Cookie，供其他工具使用
+    Export(AccountExportArgs),
+}
+
+/// 弹幕子命令
+#[derive(Subcommand, Debug, Clone)]
+pub enum DanmakuCommand {
+    /// 将 XML 弹幕转换为 ASS 字幕
+    Convert(DanmakuConvertArgs),
+}
+
+/// 导出 Cookie 参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct AccountExportArgs {
+    /// 账号名称或 mid，默认为 --account 指定的账号或默认账号
+    #[arg(value_name = "ACCOUNT")]
+    pub account: Option<String>,
+
+    /// 导出格式
+    #[arg(long, value_enum, default_value = "netscape")]
+    #[arg(help = "导出格式")]
+    pub format: CookieFormatOption,
+
+    /// 输出文件
+    #[arg(short, long, value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "写入文件，默认输出到标准输出")]
+    pub output: Option<PathBuf>,
+}
+
+/// 添加账号参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct AccountAddArgs {
+    /// 账号名称
+    #[arg(long, value_name = "NAME")]
+    #[arg(help = "账号名称，用于 --account 选择，默认使用B站昵称")]
+    pub name: Option<String>,
+
+    /// 设为默认账号
+    #[arg(long = "default")]
+    #[arg(help = "设为默认账号（第一个添加的账号自动成为默认账号）")]
+    pub make_default: bool,
+}
+
+/// 所有子命令共用的选项：登录、网络与超时
+#[derive(clap::Args, Debug, Clone)]
+#[command(next_help_heading = "全局选项")]
+pub struct GlobalArgs {
+    /// 配置文件
+    #[arg(long = "config", global = true, value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = r#"配置文件 (TOML)，默认为 ~/.config/bilidl/config.toml（存在时自动加载）
+配置文件中的设置作为默认值，命令行选项优先"#)]
+    pub config_file: Option<PathBuf>,
+
+    /// 配置方案
+    #[arg(long, global = true, value_name = "NAME")]
+    #[arg(help = "使用配置文件中 [profile.<NAME>] 的设置覆盖顶层设置")]
+    pub profile: Option<String>,
+
+    /// 旧版用户目录 (存放登录信息等)
+    #[arg(long, global = true, value_name = "DIR")]
+    #[arg(value_hint = clap::ValueHint::DirPath)]
+    #[arg(help = "从目录中的 cookies.jsonl 加载登录信息（旧版方式，推荐使用 --account）")]
+    pub user_dir: Option<PathBuf>,
+
+    /// 使用的账号
+    #[arg(long, global = true, value_name = "NAME|MID")]
+    #[arg(help = r#"使用已保存的账号（名称或 mid），未指定时使用默认账号
+账号通过 bilidl account add 添加"#)]
+    pub account: Option<String>,
+
+    /// Cookie字符串或文件 (高级用户选项)
+    #[arg(long, global = true, value_name = "COOKIE|FILE")]
+    #[arg(help = "手动指定B站Cookie：Cookie 请求头字符串，或 cookies.txt/JSON 格式的 Cookie 文件（自动识别）")]
+    pub cookie: Option<String>,
+
+    /// API 请求频率
+    #[arg(long, global = true, value_name = "N")]
+    #[arg(default_value_t = 4.0)]
+    #[arg(help = r#"每秒最多发起的 API 请求数（解析视频信息、获取播放地址等），0 表示不限制，默认4
+解析大量分集时触发 -412/-352 风控可适当调低"#)]
+    pub api_rate: f64,
+
+    /// API 请求重试次数
+    #[arg(long, global = true, value_name = "N")]
+    #[arg(default_value_t = 3)]
+    #[arg(help = "API 请求遇到服务端错误、超时或可重试的错误码时的最大重试次数，默认3")]
+    pub api_retries: u32,
+
+    /// 连接超时（秒）
+    #[arg(long, global = true, value_name = "SECS")]
+    #[arg(default_value_t = 10)]
+    #[arg(help = "建立 HTTP 连接的超时时间（秒），API 请求与下载共用，默认10")]
+    pub connect_timeout: u64,
+
+    /// API 请求超时（秒）
+    #[arg(long, global = true, value_name = "SECS")]
+    #[arg(default_value_t = 10)]
+    #[arg(help = "单次 API 请求的总超时时间（秒），默认10")]
+    pub api_timeout: u64,
+
+    /// 下载读取超时（秒）
+    #[arg(long, global = true, value_name = "SECS")]
+    #[arg(default_value_t = 60)]
+    #[arg(help = r#"下载时连续多久收不到数据即判定连接中断（秒），默认60
+下载本身不限制总时长，大文件不会因耗时过长而中断"#)]
+    pub read_timeout: u64,
+
+    /// 代理
+    #[arg(long, global = true, value_name = "URL")]
+    #[arg(help = r#"所有请求使用的代理，支持 http://、https://、socks5://、socks5h://
+填 direct 表示直连（忽略 HTTP_PROXY 等环境变量），未设置时使用系统代理"#)]
+    pub proxy: Option<ProxySetting>,
+
+    /// API 请求代理
+    #[arg(long, global = true, value_name = "URL")]
+    #[arg(help = "API 请求（解析视频信息等）使用的代理，覆盖 --proxy")]
+    pub api_proxy: Option<ProxySetting>,
+
+    /// CDN 下载代理
+    #[arg(long, global = true, value_name = "URL")]
+    #[arg(help = r#"音视频文件下载使用的代理，覆盖 --proxy
+例如只为解析走代理、媒体文件直连：--proxy socks5://127.0.0.1:1080 --cdn-proxy direct"#)]
+    pub cdn_proxy: Option<ProxySetting>,
+
+    /// 番剧播放地址代理
+    #[arg(long, global = true, value_name = "URL")]
+    #[arg(help = r#"仅用于获取番剧播放地址的代理，用于解除地区限制（6001）
+媒体文件仍按 --cdn-proxy 下载，未设置时与 API 请求相同"#)]
+    pub playurl_proxy: Option<ProxySetting>,
+
+    /// 自定义 User-Agent
+    #[arg(long, global = true, value_name = "UA")]
+    #[arg(help = "替换默认的浏览器 User-Agent")]
+    pub user_agent: Option<String>,
+
+    /// 附加请求头
+    #[arg(long = "header", global = true, value_name = "NAME: VALUE", value_parser = parse_header)]
+    #[arg(help = "附加到每个请求的请求头，可多次指定，例如 --header 'X-Forwarded-For: 1.1.1.1'")]
+    pub headers: Vec<(String, String)>,
+
+    /// 绑定出口地址
+    #[arg(long, global = true, value_name = "IP")]
+    #[arg(help = "发起连接时绑定的本地地址，用于多网卡或多出口 IP 的机器")]
+    pub bind_address: Option<IpAddr>,
+}
+
+/// 下载参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct DownloadArgs {
+    /// 视频/番剧/课程链接或裸ID (支持多种B站URL格式)
+    #[arg(value_name = "URL")]
     #[arg(value_parser = clap::value_parser!(String))]
     #[arg(value_hint = clap::ValueHint::Url)]
     #[arg(help = r#"B站内容链接，支持以下格式:
 • 普通视频: https://www.bilibili.com/video/BVxxx 或 https://www.bilibili.com/video/avxxx
 • 番剧: https://www.bilibili.com/bangumi/play/ssxxx 或 https://www.bilibili.com/bangumi/play/epxxx
-• 课程: https://www.bilibili.com/cheese/play/ssxxx"#)]
+• 课程: https://www.bilibili.com/cheese/play/ssxxx
+• 裸ID: BVxxx、avxxx、epxxx、ssxxx"#)]
     pub url: Option<String>,
 
-    /// 登录B站账号 (用于下载高清/付费内容)
-    #[arg(long)]
-    #[arg(help = r#"使用二维码扫描登录B站账号
-登录后可以下载更高清的视频和付费内容
-登录信息会保存到本地，下次使用时无需重新登录"#)]
-    pub login: bool,
+    /// 兼容旧版本的 --url 参数
+    #[arg(long = "url", value_name = "URL", hide = true, conflicts_with = "url")]
+    pub url_flag: Option<String>,
 
-    /// 用户配置目录 (存放登录信息等)
-    #[arg(long, value_name = "DIR")]
-    #[arg(value_hint = clap::ValueHint::DirPath)]
-    #[arg(help = "指定存放用户配置和登录信息的目录，默认使用系统配置目录")]
-    pub user_dir: Option<PathBuf>,
+    /// 批量下载列表
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["url", "url_flag"])]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = r#"从文件批量下载，- 表示从标准输入读取
+每行一个链接或裸ID（BV/av/ep/ss/cp/cs），可在行尾用 --parts、--quality 覆盖全局设置，# 开头为注释，例如：
+ss12345 --parts 1-3 --quality 4k"#)]
+    pub batch_file: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
 
+impl DownloadArgs {
+    /// 要下载的链接，位置参数优先于 --url
+    pub fn url(&self) -> Option<&str> {
+        self.url.as_deref().or(self.url_flag.as_deref())
+    }
+}
+
+/// 下载选项：清晰度、流选择、后处理、限速、存档等，download/info/watch 共用
+#[derive(clap::Args, Debug, Clone)]
+pub struct DownloadOptions {
     /// 视频保存目录
     #[arg(long, value_name = "DIR")]
     #[arg(default_value = ".")]
@@ -117,14 +431,10 @@ pub struct Cli {
 • 360p/480p/720p: 基础质量，无需登录
 • 720p60/1080p/1080p+/1080p60: 高清质量，建议登录后使用
 • 4k/8k: 超高清质量，需要登录且视频支持
-• hdr: HDR质量，特殊设备支持"#)]
+• hdr/dolby: HDR/杜比视界，特殊设备支持
+其它清晰度（如智能修复）可通过 --select 指定清晰度ID"#)]
     pub quality: QualityOption,
 
-    /// Cookie字符串 (高级用户选项)
-    #[arg(long, value_name = "COOKIE")]
-    #[arg(help = "手动指定B站Cookie字符串，通常不需要手动设置")]
-    pub cookie: Option<String>,
-
     /// 集数范围 (仅用于番剧/课程批量下载)
     #[arg(long, value_name = "RANGE")]
     #[arg(help = r#"指定下载的集数范围，格式示例:
@@ -162,14 +472,197 @@ pub struct Cli {
     #[arg(default_value = "true")]
     pub merge: bool,
 
+    /// 首选流格式
+    #[arg(long, value_enum)]
+    #[arg(default_value = "auto")]
+    #[arg(help = r#"首选流格式: auto/dash/mp4/flv，默认auto
+首选格式不可用时自动回退到其它格式，MP4/FLV 为音视频整合流，无需合并"#)]
+    pub format: StreamFormatOption,
+
+    /// 视频流选择表达式
+    #[arg(long, value_name = "EXPR")]
+    #[arg(help = r#"视频流选择表达式，指定后覆盖 --quality
+用 / 分隔备选项，依次尝试；用 , 组合多个条件:
+• best / worst  • 1080p60、4k、hdr、116  • height<=1080、fps>=60、filesize<2G、codec=hevc
+示例: "1080p60/1080p/best"、"height<=1080,codec=avc/best""#)]
+    pub select: Option<StreamSelector>,
+
+    /// 仅提取音频
+    #[arg(long)]
+    #[arg(help = r#"仅下载最佳音轨并提取为音频文件，写入标题、UP主、专辑、音轨号和封面标签
+适用于音乐投稿、讲座录音等场景"#)]
+    pub audio_only: bool,
+
+    /// 仅音频模式的输出格式
+    #[arg(long, value_enum)]
+    #[arg(default_value = "m4a")]
+    #[arg(help = "仅音频模式的输出格式: m4a 直接封装，mp3/flac/opus 需要转码，默认m4a")]
+    pub audio_format: AudioFormatOption,
+
+    /// 仅音频模式的文件名模板
+    #[arg(long, value_name = "TEMPLATE")]
+    #[arg(default_value = "{title}")]
+    #[arg(help = r#"仅音频模式的文件名模板，可用占位符:
+• {title}: 标题  • {artist}: UP主  • {album}: 合集/番剧/课程名  • {track}: 音轨号
+示例: "{album} - {track} - {title}""#)]
+    pub audio_template: String,
+
     /// 下载并发数
     #[arg(long, value_name = "NUM")]
     #[arg(default_value_t = 3)]
     #[arg(help = "同时下载的线程数，建议1-8之间，默认3")]
     pub concurrency: usize,
 
-    /// 启动MCP服务器模式 (开发者选项)
+    /// 全局限速
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    #[arg(help = r#"所有下载共享的带宽上限，如 500K、5M，0 表示不限速
+单位按 1024 进制，默认不限速"#)]
+    pub limit_rate: Option<u64>,
+
+    /// 单任务限速
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    #[arg(help = "单个文件的带宽上限，格式同 --limit-rate")]
+    pub limit_rate_per_task: Option<u64>,
+
+    /// 分时段限速
+    #[arg(long, value_name = "HH:MM-HH:MM=RATE")]
+    #[arg(help = r#"按时段覆盖全局限速，可多次指定，结束时间早于开始时间表示跨越午夜
+示例: --rate-schedule "01:00-07:00=0" --rate-schedule "09:00-18:00=2M""#)]
+    pub rate_schedule: Vec<RateSchedule>,
+
+    /// CDN 镜像
+    #[arg(long = "cdn", value_name = "HOST")]
+    #[arg(help = r#"将官方 CDN 节点改写为指定的 upos 镜像，原地址作为备用，例如：
+upos-sz-mirrorali.bilivideo.com、upos-sz-mirrorcos.bilivideo.com、upos-sz-mirrorhw.bilivideo.com
+未设置时优先使用官方 upos 节点，MCDN/PCDN 节点仅作为最后的备用"#)]
+    pub cdn_mirror: Option<String>,
+
+    /// CDN 测速
+    #[arg(long)]
+    #[arg(help = "开始下载前对主地址和备用地址测速，选择最快的节点")]
+    pub cdn_probe: bool,
+
+    /// 忽略磁盘空间检查
     #[arg(long)]
-    #[arg(help = "启动MCP (Model Context Protocol) 服务器模式，用于AI助手集成")]
-    pub mcp: bool,
+    #[arg(help = "可用空间不足以完成下载时仍然继续（默认会拒绝开始下载）")]
+    pub ignore_disk_space: bool,
+
+    /// 下载存档文件
+    #[arg(long, value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = r#"记录已下载内容的存档文件，重复运行时跳过存档中已有的视频/剧集
+未指定时使用配置目录下的 archive.jsonl（设置了 --user-dir 时使用其中的 archive.jsonl）"#)]
+    pub download_archive: Option<PathBuf>,
+
+    /// 升级已存档的内容
+    #[arg(long)]
+    #[arg(help = "存档中的内容现在有更高清晰度时重新下载，而不是跳过")]
+    pub upgrade: bool,
+}
+
+/// 信息查看参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct InfoArgs {
+    /// 视频/番剧/课程链接或裸ID
+    #[arg(value_name = "URL")]
+    #[arg(value_hint = clap::ValueHint::Url)]
+    pub url: String,
+
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
+
+/// 订阅模式参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct WatchArgs {
+    /// 订阅配置文件 (TOML)
+    #[arg(value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    pub config: PathBuf,
+
+    /// 只检查一轮
+    #[arg(long)]
+    #[arg(help = "每个订阅源只检查一次，下载完成后退出（适合配合 cron 使用）")]
+    pub once: bool,
+
+    #[command(flatten)]
+    pub options: DownloadOptions,
+}
+
+/// HTTP MCP服务器参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServeArgs {
+    /// 监听地址
+    #[arg(long, value_name = "ADDR")]
+    #[arg(default_value = "127.0.0.1:3000")]
+    #[arg(help = "HTTP 服务监听地址，MCP 请求发送到 POST /mcp，默认127.0.0.1:3000")]
+    pub listen: SocketAddr,
+
+    /// 允许跨域访问的来源
+    #[arg(long = "allow-origin", value_name = "ORIGIN")]
+    #[arg(help = "允许浏览器跨域调用的来源，如 http://localhost:5173，可重复指定；默认拒绝所有跨域请求")]
+    pub allow_origins: Vec<String>,
+}
+
+/// 弹幕转换参数
+#[derive(clap::Args, Debug, Clone)]
+pub struct DanmakuConvertArgs {
+    /// XML 弹幕文件
+    #[arg(value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    pub input: PathBuf,
+
+    /// 输出文件
+    #[arg(short, long, value_name = "FILE")]
+    #[arg(value_hint = clap::ValueHint::FilePath)]
+    #[arg(help = "输出的 ASS 文件，默认与输入文件同名")]
+    pub output: Option<PathBuf>,
+
+    /// 画面宽度
+    #[arg(long, value_name = "PX")]
+    #[arg(default_value_t = 1920)]
+    #[arg(help = "字幕画布宽度，建议与视频分辨率一致，默认1920")]
+    pub width: u32,
+
+    /// 画面高度
+    #[arg(long, value_name = "PX")]
+    #[arg(default_value_t = 1080)]
+    #[arg(help = "字幕画布高度，建议与视频分辨率一致，默认1080")]
+    pub height: u32,
+
+    /// 字体
+    #[arg(long, value_name = "NAME")]
+    #[arg(default_value = "Microsoft YaHei")]
+    #[arg(help = "弹幕字体，默认Microsoft YaHei")]
+    pub font: String,
+
+    /// 字号
+    #[arg(long, value_name = "PX")]
+    #[arg(default_value_t = 48)]
+    #[arg(help = "标准弹幕的字号，大/小弹幕按比例缩放，默认48")]
+    pub font_size: u32,
+
+    /// 滚动弹幕时长（秒）
+    #[arg(long, value_name = "SECS")]
+    #[arg(default_value_t = 10.0)]
+    #[arg(help = "滚动弹幕从右到左经过屏幕的时间（秒），默认10")]
+    pub scroll_duration: f64,
+
+    /// 固定弹幕时长（秒）
+    #[arg(long, value_name = "SECS")]
+    #[arg(default_value_t = 5.0)]
+    #[arg(help = "顶部/底部弹幕的显示时间（秒），默认5")]
+    pub fixed_duration: f64,
+
+    /// 不透明度
+    #[arg(long, value_name = "0-1")]
+    #[arg(default_value_t = 0.8)]
+    #[arg(help = "弹幕不透明度，0为全透明，1为不透明，默认0.8")]
+    pub opacity: f32,
+
+    /// 弹幕区域高度比例
+    #[arg(long, value_name = "0-1")]
+    #[arg(default_value_t = 1.0)]
+    #[arg(help = "滚动弹幕可使用的屏幕高度比例，如 0.5 只占上半屏，默认1（全屏）")]
+    pub display_area: f32,
 }
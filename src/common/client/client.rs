@@ -2,8 +2,13 @@ use std::collections::HashMap;
 use std::{fmt::Error, io::Read, sync::Arc, time::Duration};
 
 use crate::common::client::error::ApiError;
+use crate::common::client::governor::{ApiGovernor, ApiGovernorConfig};
 use crate::common::client::models::user_info::{CommonResponse, UserInfoResponse};
+use crate::common::client::network::{NetworkConfig, ProxySetting};
+use crate::common::client::wbi_keys::{WbiKeyCache, WbiKeys};
+use crate::common::utils::write_private;
 use crate::common::wbi_utils::WbiUtils;
+use crate::downloader::cdn::is_bilibili_cdn;
 use anyhow::{Result, anyhow};
 use cookie::Cookie;
 use cookie_store::CookieStore;
@@ -16,41 +21,163 @@ use reqwest_cookie_store::CookieStoreMutex;
 use serde::de::DeserializeOwned;
 use serde_json::Value;
 
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
+
+// 风控相关的B站业务错误码：-352 校验失败、-412 请求被拦截、-799 请求过于频繁
+const RISK_CONTROL_CODES: [i64; 3] = [-352, -412, -799];
+
+/// HTTP 超时配置
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClientTimeouts {
+    pub connect: Duration, // 建立连接超时，API 与下载共用
+    pub api: Duration,     // API 请求的总超时
+    pub read: Duration,    // 下载时两次读取之间的最长间隔，不限制总时长
+}
+
+impl Default for ClientTimeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            api: Duration::from_secs(10),
+            read: Duration::from_secs(60),
+        }
+    }
+}
 
 // 支持自动携带认证状态的客户端
 #[derive(Debug, Clone)]
 pub struct BiliClient {
-    pub inner: Client,
+    pub inner: Client,    // API 请求客户端，短超时
+    pub download: Client, // CDN 下载客户端，只限制连接与读取间隔
+    playurl: Client,      // 番剧播放地址请求客户端，可单独走地区代理
     pub cookie_store: Arc<CookieStoreMutex>,
+    timeouts: ClientTimeouts,
+    network: NetworkConfig,
+    governor: ApiGovernor, // API 请求限流与重试，克隆的客户端共享
+    wbi_keys: WbiKeyCache, // WBI 签名密钥缓存，克隆的客户端共享
 }
 
 impl BiliClient {
     // 创建基础客户端，未认证
     pub fn new() -> Self {
-        let headers = Self::get_default_headers();
         // 创建 CookieStore
         let cookie_store = CookieStore::default();
         let cookie_store = CookieStoreMutex::new(cookie_store);
         let cookie_store = Arc::new(cookie_store);
-
-        Self {
-            inner: match ClientBuilder::new()
-                .timeout(Duration::from_secs(10))
-                .cookie_provider(Arc::clone(&cookie_store))
-                .default_headers(headers)
-                .build()
-            {
-                Ok(client) => client,
+        let timeouts = ClientTimeouts::default();
+        let network = NetworkConfig::default();
+        let (inner, download, playurl) =
+            match Self::build_clients(&cookie_store, &timeouts, &network) {
+                Ok(clients) => clients,
                 Err(e) => {
                     error!("Error creating client: {}", e);
                     panic!("Failed to create client");
                 }
-            },
+            };
+
+        Self {
+            inner,
+            download,
+            playurl,
             cookie_store,
+            timeouts,
+            network,
+            governor: ApiGovernor::default(),
+            wbi_keys: WbiKeyCache::default(),
         }
     }
 
+    // 调整 API 请求的限流与重试策略，对共享同一调度器的所有客户端生效
+    pub fn set_api_governor(&self, config: ApiGovernorConfig) {
+        self.governor.set_config(config);
+    }
+
+    pub fn api_governor(&self) -> &ApiGovernor {
+        &self.governor
+    }
+
+    // 修改超时配置并重建 HTTP 客户端，Cookie 保持共享
+    pub fn set_timeouts(&mut self, timeouts: ClientTimeouts) -> Result<(), ApiError> {
+        self.rebuild(
+            Arc::clone(&self.cookie_store),
+            timeouts,
+            self.network.clone(),
+        )
+    }
+
+    pub fn timeouts(&self) -> ClientTimeouts {
+        self.timeouts
+    }
+
+    // 修改代理、请求头和出口地址并重建 HTTP 客户端
+    pub fn set_network(&mut self, network: NetworkConfig) -> Result<(), ApiError> {
+        self.rebuild(Arc::clone(&self.cookie_store), self.timeouts, network)
+    }
+
+    pub fn network(&self) -> &NetworkConfig {
+        &self.network
+    }
+
+    // 请求番剧播放地址用的客户端：API 请求改走 playurl 代理，其余状态共享
+    pub fn playurl_client(&self) -> Self {
+        Self {
+            inner: self.playurl.clone(),
+            ..self.clone()
+        }
+    }
+
+    fn rebuild(
+        &mut self,
+        cookie_store: Arc<CookieStoreMutex>,
+        timeouts: ClientTimeouts,
+        network: NetworkConfig,
+    ) -> Result<(), ApiError> {
+        let (inner, download, playurl) = Self::build_clients(&cookie_store, &timeouts, &network)?;
+        self.inner = inner;
+        self.download = download;
+        self.playurl = playurl;
+        self.cookie_store = cookie_store;
+        self.timeouts = timeouts;
+        self.network = network;
+        Ok(())
+    }
+
+    // 构建共享同一个 CookieStore 的 API、下载和播放地址客户端
+    fn build_clients(
+        cookie_store: &Arc<CookieStoreMutex>,
+        timeouts: &ClientTimeouts,
+        network: &NetworkConfig,
+    ) -> Result<(Client, Client, Client), ApiError> {
+        let mut headers = Self::get_default_headers();
+        network
+            .apply_headers(&mut headers)
+            .map_err(ApiError::Other)?;
+
+        let build = |builder: ClientBuilder, proxy: &ProxySetting| -> Result<Client, ApiError> {
+            let builder = builder
+                .connect_timeout(timeouts.connect)
+                .cookie_provider(Arc::clone(cookie_store))
+                .default_headers(headers.clone())
+                .local_address(network.local_address);
+            Ok(proxy.apply(builder)?.build()?)
+        };
+
+        let api = build(
+            ClientBuilder::new().timeout(timeouts.api),
+            &network.api_proxy,
+        )?;
+        // 下载大文件不能设置总超时，只在连接长时间没有数据时中断
+        let download = build(
+            ClientBuilder::new().read_timeout(timeouts.read),
+            &network.cdn_proxy,
+        )?;
+        let playurl = match &network.playurl_proxy {
+            Some(proxy) => build(ClientBuilder::new().timeout(timeouts.api), proxy)?,
+            None => api.clone(),
+        };
+        Ok((api, download, playurl))
+    }
+
     pub fn get_default_headers() -> reqwest::header::HeaderMap {
         // 创建默认请求头
         let mut headers = reqwest::header::HeaderMap::new();
@@ -80,12 +207,22 @@ impl BiliClient {
         // 返回自己
         Self {
             inner: self.inner.clone(),
+            download: self.download.clone(),
+            playurl: self.playurl.clone(),
             cookie_store: Arc::clone(&self.cookie_store),
+            timeouts: self.timeouts,
+            network: self.network.clone(),
+            governor: self.governor.clone(),
+            wbi_keys: self.wbi_keys.clone(),
         }
     }
 
-    // 通用请求
+    // 通用请求，经过限流与重试
     pub async fn get<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
+        self.governor.run(|| self.get_once::<T>(url)).await
+    }
+
+    async fn get_once<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
         let cookie = self.get_all_cookies().await;
         let cookie_str = cookie
             .iter()
@@ -105,12 +242,12 @@ impl BiliClient {
             .inner
             .get(url)
             .header(COOKIE, cookie_str)
-            .headers(Self::get_default_headers())
             .send()
             .await
             .map_err(|e| {
                 error!("请求失败: {}", e);
-                ApiError::InvalidResponse(format!("请求失败: {}", e))
+                // 保留原始错误，超时和连接失败可由调度器重试
+                ApiError::Reqwest(e)
             })?;
 
         debug!("Response Head: {:?}", resp);
@@ -121,8 +258,20 @@ impl BiliClient {
         Self::handle_response::<T>(resp).await
     }
 
-    // 处理响应
+    // 获取原始响应，经过限流，服务端错误时重试
     pub async fn get_raw_response(&self, url: &str) -> Result<Response, ApiError> {
+        self.governor
+            .run(|| async move {
+                let resp = self.get_raw_response_once(url).await?;
+                if resp.status().is_server_error() {
+                    return Err(ApiError::RetryLater);
+                }
+                Ok(resp)
+            })
+            .await
+    }
+
+    async fn get_raw_response_once(&self, url: &str) -> Result<Response, ApiError> {
         let cookie = self.get_all_cookies().await;
         let cookie_str = cookie
             .iter()
@@ -140,7 +289,6 @@ impl BiliClient {
             .inner
             .get(url)
             .header(COOKIE, cookie_str)
-            .headers(Self::get_default_headers())
             .send()
             .await?;
 
@@ -156,10 +304,14 @@ impl BiliClient {
 
     async fn handle_response<T: DeserializeOwned>(resp: Response) -> Result<T, ApiError> {
         let status = resp.status();
-        // 自动重试机制
+        // 服务端错误由 ApiGovernor 负责重试
         if status.is_server_error() {
             return Err(ApiError::RetryLater);
         }
+        // HTTP 412：请求被风控拦截
+        if status == reqwest::StatusCode::PRECONDITION_FAILED {
+            return Err(ApiError::RiskControl(-412, "请求被拦截".to_string()));
+        }
 
         let url = resp.url().to_string();
         let raw_body = resp.bytes().await?;
@@ -182,6 +334,9 @@ impl BiliClient {
                             .and_then(|v| v.as_str())
                             .unwrap_or("Unknown error")
                             .to_string();
+                        if RISK_CONTROL_CODES.contains(&code) {
+                            return Err(ApiError::RiskControl(code, message));
+                        }
                         return Err(ApiError::ApiError(code, message));
                     }
                 }
@@ -235,18 +390,27 @@ impl BiliClient {
     pub async fn save_cookies_to_local(&self, path: &str) -> Result<(), Error> {
         // 保存 Cookie 到本地
 
-        let store = self.cookie_store.lock().unwrap();
-        let mut writer = std::fs::File::create(path)
-            .map(std::io::BufWriter::new)
-            .unwrap();
-
-        if let Ok(_) = store.save(&mut writer, serde_json::to_string).map_err(|e| {
-            error!("Error saving cookies: {}", e);
-            format!("Cookies保存失败: {}", e)
-        }) {
-            info!("Cookies saved to {}", path);
-        } else {
-            error!("Error saving cookies");
+        // Cookie 包含登录凭据，文件只允许当前用户读写
+        let mut buf = Vec::new();
+        let saved = self
+            .cookie_store
+            .lock()
+            .unwrap()
+            .save(&mut buf, serde_json::to_string)
+            .map_err(|e| format!("Cookies保存失败: {}", e))
+            .and_then(|_| {
+                write_private(std::path::Path::new(path), &buf)
+                    .map_err(|e| format!("写入 {} 失败: {}", path, e))
+            });
+        match saved {
+            Ok(_) => info!("Cookies saved to {}", path),
+            Err(e) => error!("Error saving cookies: {}", e),
+        }
+        // WBI 密钥与会话一起保存
+        if let Some(dir) = std::path::Path::new(path).parent()
+            && let Err(e) = self.wbi_keys.persist(dir)
+        {
+            warn!("保存 WBI 密钥失败: {}", e);
         }
         Ok(())
     }
@@ -262,13 +426,18 @@ impl BiliClient {
 
         let store = CookieStoreMutex::new(store);
         let store = Arc::new(store);
-        self.inner = ClientBuilder::new()
-            .timeout(Duration::from_secs(10))
-            .cookie_provider(Arc::clone(&store))
-            .default_headers(Self::get_default_headers())
-            .build()
-            .unwrap();
-        self.cookie_store = store;
+        if let Err(e) = self.rebuild(store, self.timeouts, self.network.clone()) {
+            error!("Error creating client: {}", e);
+        }
+        if let Some(dir) = std::path::Path::new(path).parent() {
+            self.wbi_keys.attach(dir);
+        }
+    }
+
+    // 替换 Cookie 并重建 HTTP 客户端
+    pub fn set_cookie_store(&mut self, store: CookieStore) -> Result<(), ApiError> {
+        let store = Arc::new(CookieStoreMutex::new(store));
+        self.rebuild(store, self.timeouts, self.network.clone())
     }
 
     pub async fn check_qr_login_status(&self) -> Result<()> {
@@ -292,6 +461,18 @@ impl BiliClient {
         Ok(())
     }
 
+    /// 获取当前登录账号的信息，未登录时返回错误
+    pub async fn get_user_info(&self) -> Result<UserInfoResponse> {
+        let resp = self
+            .get::<CommonResponse<UserInfoResponse>>("https://api.bilibili.com/x/web-interface/nav")
+            .await
+            .map_err(|e| anyhow!("请求失败: {}", e))?;
+        match resp.data {
+            Some(user) if user.is_login => Ok(user),
+            _ => Err(anyhow!("未登录: {}", resp.message)),
+        }
+    }
+
     async fn get_cookies(&self, name: &str) -> Option<String> {
         // 获取 Cookie
         let cookie_store = self.cookie_store.lock().unwrap();
@@ -323,9 +504,14 @@ impl BiliClient {
         }
     }
 
+    // 写请求只限流不重试：请求可能已被服务器处理，重发并不安全
     pub async fn post<T: DeserializeOwned>(&self, url: &str, body: &str) -> Result<T, ApiError> {
-        let resp = self.inner.post(url).body(body.to_string()).send().await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self.inner.post(url).body(body.to_string()).send().await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
 
     pub async fn post_form<T: DeserializeOwned>(
@@ -333,112 +519,152 @@ impl BiliClient {
         url: &str,
         form: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .post(url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            .body(form.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .post(url)
+                    .header("Content-Type", "application/x-www-form-urlencoded")
+                    .body(form.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn post_json<T: DeserializeOwned>(
         &self,
         url: &str,
         json: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .post(url)
-            .header("Content-Type", "application/json")
-            .body(json.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    .body(json.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn post_json_with_auth<T: DeserializeOwned>(
         &self,
         url: &str,
         json: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .post(url)
-            .header("Content-Type", "application/json")
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .body(json.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .post(url)
+                    .header("Content-Type", "application/json")
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .body(json.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn post_form_with_auth<T: DeserializeOwned>(
         &self,
         url: &str,
         form: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .post(url)
-            .header("Content-Type", "application/x-www-form-urlencoded")
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .body(form.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .post(url)
+                    .header("Content-Type", "application/x-www-form-urlencoded")
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .body(form.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn post_with_auth<T: DeserializeOwned>(
         &self,
         url: &str,
         body: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .post(url)
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .body(body.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .post(url)
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .body(body.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn get_with_auth<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .get(url)
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run(|| async move {
+                let resp = self
+                    .inner
+                    .get(url)
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn delete<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
-        let resp = self.inner.delete(url).send().await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self.inner.delete(url).send().await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn delete_with_auth<T: DeserializeOwned>(&self, url: &str) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .delete(url)
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .delete(url)
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn put<T: DeserializeOwned>(&self, url: &str, body: &str) -> Result<T, ApiError> {
-        let resp = self.inner.put(url).body(body.to_string()).send().await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self.inner.put(url).body(body.to_string()).send().await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
     pub async fn put_with_auth<T: DeserializeOwned>(
         &self,
         url: &str,
         body: &str,
     ) -> Result<T, ApiError> {
-        let resp = self
-            .inner
-            .put(url)
-            // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
-            .body(body.to_string())
-            .send()
-            .await?;
-        Self::handle_response(resp).await
+        self.governor
+            .run_once(|| async move {
+                let resp = self
+                    .inner
+                    .put(url)
+                    // .header("Authorization", format!("Bearer {}", self.auth_cookie.as_ref().unwrap()))
+                    .body(body.to_string())
+                    .send()
+                    .await?;
+                Self::handle_response(resp).await
+            })
+            .await
     }
 
     // 发送带 WBI 签名的 GET 请求
@@ -447,18 +673,17 @@ impl BiliClient {
         url: &str,
         params: HashMap<String, String>,
     ) -> Result<T, ApiError> {
-        let mut url = url.to_string();
-
-        // 尝试获取 wbi keys
-        if let Ok((img_key, sub_key)) = self.get_wbi_keys().await {
-            url = format!("{}?{}", url, WbiUtils::enc_wbi(params, &img_key, &sub_key));
-        } else {
-            // fallback: 加上 params 但不加密
-            let query = serde_urlencoded::to_string(&params).unwrap_or_default();
-            url = format!("{}?{}", url, query);
+        let signed = self.sign_wbi(url, &params).await;
+        match self.get::<T>(&signed).await {
+            // 密钥可能已轮换，刷新后重试一次
+            Err(e) if e.is_wbi_rejected() => {
+                warn!("WBI 签名被拒绝 ({})，刷新密钥后重试", e);
+                self.wbi_keys.invalidate();
+                let signed = self.sign_wbi(url, &params).await;
+                self.get::<T>(&signed).await
+            }
+            result => result,
         }
-
-        self.get::<T>(url.clone().as_str()).await
     }
 
     // 发送带 WBI 签名的 POST 请求
@@ -467,22 +692,50 @@ impl BiliClient {
         url: &str,
         params: HashMap<String, String>,
     ) -> Result<T, ApiError> {
-        let mut url = url.to_string();
-
-        // 尝试获取 wbi keys
-        if let Ok((img_key, sub_key)) = self.get_wbi_keys().await {
-            url = format!("{}?{}", url, WbiUtils::enc_wbi(params, &img_key, &sub_key));
-        } else {
-            // fallback: 加上 params 但不加密
-            let query = serde_urlencoded::to_string(&params).unwrap_or_default();
-            url = format!("{}?{}", url, query);
+        let signed = self.sign_wbi(url, &params).await;
+        match self.post::<T>(&signed, "").await {
+            Err(e) if e.is_wbi_rejected() => {
+                warn!("WBI 签名被拒绝 ({})，刷新密钥后重试", e);
+                self.wbi_keys.invalidate();
+                let signed = self.sign_wbi(url, &params).await;
+                self.post::<T>(&signed, "").await
+            }
+            result => result,
         }
+    }
 
-        self.post::<T>(url.clone().as_str(), "").await
+    // 为请求参数加上 WBI 签名，获取密钥失败时退化为不签名的请求
+    async fn sign_wbi(&self, url: &str, params: &HashMap<String, String>) -> String {
+        match self.get_wbi_keys().await {
+            Ok((img_key, sub_key)) => {
+                let query = WbiUtils::enc_wbi(params.clone(), &img_key, &sub_key);
+                format!("{}?{}", url, query)
+            }
+            Err(e) => {
+                warn!("获取 WBI 密钥失败，将发送未签名的请求: {}", e);
+                let query = serde_urlencoded::to_string(params).unwrap_or_default();
+                format!("{}?{}", url, query)
+            }
+        }
     }
 
-    // 获取 wbi keys
+    // 获取 wbi keys，优先使用当天缓存的密钥
     pub async fn get_wbi_keys(&self) -> Result<(String, String), ApiError> {
+        if let Some(keys) = self.wbi_keys.get() {
+            return Ok((keys.img_key, keys.sub_key));
+        }
+        let (img_key, sub_key) = self.fetch_wbi_keys().await?;
+        self.wbi_keys
+            .set(WbiKeys::new(img_key.clone(), sub_key.clone()));
+        Ok((img_key, sub_key))
+    }
+
+    pub fn wbi_key_cache(&self) -> &WbiKeyCache {
+        &self.wbi_keys
+    }
+
+    // 从 nav 接口获取 wbi keys
+    async fn fetch_wbi_keys(&self) -> Result<(String, String), ApiError> {
         let url = "https://api.bilibili.com/x/web-interface/nav";
         let resp = self.get::<serde_json::Value>(url).await?;
         let data = resp["data"]
@@ -518,7 +771,8 @@ impl BiliClient {
 
     // 获取视频下载专用的请求头
     pub fn get_video_download_headers(video_url: &str) -> reqwest::header::HeaderMap {
-        let mut headers = Self::get_default_headers();
+        // 通用请求头（含自定义 User-Agent）已由客户端默认携带，这里只补充下载专用的
+        let mut headers = reqwest::header::HeaderMap::new();
         
         // 关键：添加视频下载必需的请求头
         headers.insert("Origin", reqwest::header::HeaderValue::from_static("https://www.bilibili.com"));
@@ -527,7 +781,7 @@ impl BiliClient {
         headers.insert("Sec-Fetch-Site", reqwest::header::HeaderValue::from_static("cross-site"));
         
         // 根据视频URL设置合适的Referer
-        if video_url.contains("bilivideo.com") {
+        if is_bilibili_cdn(video_url) {
             // 对于B站的CDN地址，使用播放页面作为Referer
             headers.insert(REFERER, reqwest::header::HeaderValue::from_static("https://www.bilibili.com/video/"));
         }
@@ -557,7 +811,7 @@ impl BiliClient {
         debug!("🍪 使用的 Cookie: {}", cookie_str);
 
         let resp = self
-            .inner
+            .download
             .get(url)
             .header(COOKIE, cookie_str)
             .headers(Self::get_video_download_headers(url))
@@ -566,4 +820,94 @@ impl BiliClient {
 
         Ok(resp)
     }
+
+    // CDN 上的弹幕、封面等小文件：走下载客户端，不经过 API 限流
+    pub async fn get_cdn_response(&self, url: &str) -> Result<Response, ApiError> {
+        let cookie = self.get_all_cookies().await;
+        let cookie_str = cookie
+            .iter()
+            .map(|c| {
+                format!(
+                    "{}={}",
+                    c["name"].as_str().unwrap(),
+                    c["value"].as_str().unwrap()
+                )
+            })
+            .collect::<Vec<String>>()
+            .join(";");
+
+        let resp = self
+            .download
+            .get(url)
+            .header(COOKIE, cookie_str)
+            .header(REFERER, "https://www.bilibili.com/")
+            .send()
+            .await?;
+
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use tokio::sync::mpsc;
+
+    // 模拟 HTTP 代理：响应中带上代理名称，并回传收到的请求
+    async fn fake_proxy(name: &'static str) -> (String, mpsc::UnboundedReceiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0; 8192];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                let _ = tx.send(String::from_utf8_lossy(&buf[..n]).into_owned());
+                let body = format!(r#"{{"code":0,"message":"0","data":"{}"}}"#, name);
+                let resp = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(resp.as_bytes()).await;
+            }
+        });
+        (addr, rx)
+    }
+
+    #[tokio::test]
+    async fn test_playurl_client_routing() {
+        let (api_proxy, mut api_requests) = fake_proxy("api").await;
+        let (playurl_proxy, _) = fake_proxy("playurl").await;
+        let mut client = BiliClient::new();
+        client
+            .set_network(NetworkConfig {
+                api_proxy: api_proxy.parse().unwrap(),
+                cdn_proxy: ProxySetting::Direct,
+                playurl_proxy: Some(playurl_proxy.parse().unwrap()),
+                user_agent: Some("bilidl-test".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        // 预置 WBI 密钥，不请求 nav 接口
+        client
+            .wbi_key_cache()
+            .set(WbiKeys::new("a".repeat(32), "b".repeat(32)));
+
+        let url = "http://api.bilibili.test/pgc/player/web/playurl";
+        let resp: CommonResponse<String> = client.get_auto(url, HashMap::new()).await.unwrap();
+        assert_eq!(resp.data.as_deref(), Some("api"));
+        let resp: CommonResponse<String> = client
+            .playurl_client()
+            .get_auto(url, HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(resp.data.as_deref(), Some("playurl"));
+
+        // 自定义 User-Agent 不会被默认请求头覆盖
+        let request = api_requests.recv().await.unwrap().to_ascii_lowercase();
+        assert!(request.contains("user-agent: bilidl-test"));
+    }
 }
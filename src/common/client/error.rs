@@ -32,6 +32,9 @@ pub enum ApiError {
     #[error("B站 API 错误: {1}")]
     ApiError(i64, String), // 添加这个类型来处理 B站 API 的错误
 
+    #[error("触发B站风控 ({0}): {1}，请降低请求频率、稍后重试或登录后再试")]
+    RiskControl(i64, String), // -352/-412/-799 等风控拦截
+
     #[error("显示错误: {0}")]
     DisplayError(String),
 
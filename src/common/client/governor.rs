@@ -0,0 +1,221 @@
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::common::client::error::ApiError;
+
+/// 可重试的B站业务错误码：服务器错误、调用过快、服务调用超时
+const RETRYABLE_CODES: [i64; 3] = [-500, -503, -504];
+
+/// API 请求调度配置
+#[derive(Debug, Clone)]
+pub struct ApiGovernorConfig {
+    pub requests_per_second: f64, // 每秒请求数上限，0 表示不限制
+    pub burst: u32,               // 允许的突发请求数
+    pub max_retries: u32,         // 最大重试次数
+    pub base_delay: Duration,     // 首次重试的基础等待时间，之后指数增长
+    pub max_delay: Duration,      // 单次重试的最长等待时间
+    pub timeout: Duration,        // 单次请求超时
+}
+
+impl Default for ApiGovernorConfig {
+    fn default() -> Self {
+        Self {
+            requests_per_second: 4.0,
+            burst: 4,
+            max_retries: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            timeout: Duration::from_secs(15),
+        }
+    }
+}
+
+/// API 请求调度器：令牌桶限流 + 抖动退避重试，克隆后共享同一个令牌桶
+#[derive(Debug, Clone)]
+pub struct ApiGovernor {
+    config: Arc<RwLock<ApiGovernorConfig>>,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl ApiGovernor {
+    pub fn new(config: ApiGovernorConfig) -> Self {
+        let tokens = config.burst as f64;
+        Self {
+            config: Arc::new(RwLock::new(config)),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn config(&self) -> ApiGovernorConfig {
+        self.config.read().map(|c| c.clone()).unwrap_or_default()
+    }
+
+    pub fn set_config(&self, config: ApiGovernorConfig) {
+        if let Ok(mut current) = self.config.write() {
+            *current = config;
+        }
+    }
+
+    /// 获取一个请求令牌，超出速率时等待
+    pub async fn acquire(&self) {
+        let config = self.config();
+        if config.requests_per_second <= 0.0 {
+            return;
+        }
+
+        let wait = {
+            let Ok(mut bucket) = self.bucket.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.last_refill = now;
+
+            let capacity = config.burst.max(1) as f64;
+            bucket.tokens =
+                (bucket.tokens + elapsed * config.requests_per_second).min(capacity) - 1.0;
+            (bucket.tokens < 0.0)
+                .then(|| Duration::from_secs_f64(-bucket.tokens / config.requests_per_second))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// 第 `attempt` 次重试前的等待时间：指数退避，并在 [50%, 100%] 之间随机抖动
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let config = self.config();
+        let delay = config
+            .base_delay
+            .saturating_mul(1 << attempt.min(16))
+            .min(config.max_delay);
+        delay.mul_f64(rand::random_range(0.5..=1.0))
+    }
+
+    /// 限流后发送请求，对可重试的错误按退避策略重试；只用于可以安全重发的 GET 请求
+    pub async fn run<T, F, Fut>(&self, mut send: F) -> Result<T, ApiError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        let mut attempt = 0;
+        loop {
+            self.acquire().await;
+            let config = self.config();
+            let result = match tokio::time::timeout(config.timeout, send()).await {
+                Ok(result) => result,
+                Err(_) => Err(ApiError::OperationTimeout),
+            };
+
+            match result {
+                Err(e) if e.is_retryable() && attempt < config.max_retries => {
+                    let delay = self.backoff(attempt);
+                    attempt += 1;
+                    warn!(
+                        "API 请求失败，{:.1} 秒后重试 ({}/{}): {}",
+                        delay.as_secs_f64(),
+                        attempt,
+                        config.max_retries,
+                        e
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// 限流后只发送一次请求，用于 POST/PUT/DELETE 等不能安全重发的请求
+    pub async fn run_once<T, Fut>(&self, send: impl FnOnce() -> Fut) -> Result<T, ApiError>
+    where
+        Fut: Future<Output = Result<T, ApiError>>,
+    {
+        self.acquire().await;
+        match tokio::time::timeout(self.config().timeout, send()).await {
+            Ok(result) => result,
+            Err(_) => Err(ApiError::OperationTimeout),
+        }
+    }
+}
+
+impl Default for ApiGovernor {
+    fn default() -> Self {
+        Self::new(ApiGovernorConfig::default())
+    }
+}
+
+impl ApiError {
+    /// 是否值得重试：服务端错误、超时、连接失败和部分B站业务错误码
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ApiError::RetryLater | ApiError::OperationTimeout => true,
+            ApiError::Reqwest(e) => e.is_timeout() || e.is_connect(),
+            ApiError::ApiError(code, _) => RETRYABLE_CODES.contains(code),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_retry_until_success() {
+        let governor = ApiGovernor::new(ApiGovernorConfig {
+            requests_per_second: 0.0,
+            base_delay: Duration::from_millis(1),
+            ..Default::default()
+        });
+
+        let mut calls = 0;
+        let result = governor
+            .run(|| {
+                calls += 1;
+                let attempt = calls;
+                async move {
+                    match attempt {
+                        1 => Err(ApiError::RetryLater),
+                        2 => Err(ApiError::ApiError(-503, "调用速度过快".to_string())),
+                        _ => Ok(attempt),
+                    }
+                }
+            })
+            .await;
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = governor
+            .run(|| {
+                calls += 1;
+                async { Err(ApiError::RiskControl(-412, "请求被拦截".to_string())) }
+            })
+            .await;
+        assert!(matches!(result, Err(ApiError::RiskControl(-412, _))));
+        assert_eq!(calls, 1);
+
+        // 写请求失败后不重发
+        let mut calls = 0;
+        let result: Result<(), _> = governor
+            .run_once(|| {
+                calls += 1;
+                async { Err(ApiError::RetryLater) }
+            })
+            .await;
+        assert!(matches!(result, Err(ApiError::RetryLater)));
+        assert_eq!(calls, 1);
+    }
+}
@@ -10,8 +10,9 @@ pub struct CommonResponse<T> {
     pub data: Option<T>,
 }
 
-/// 用户信息响应结构
-#[derive(Debug, Deserialize)]
+/// 用户信息响应结构，未登录时只有 isLogin 字段
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
 #[allow(dead_code)]
 pub struct UserInfoResponse {
     #[serde(rename = "isLogin")]
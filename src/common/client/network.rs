@@ -0,0 +1,132 @@
+use std::fmt;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, USER_AGENT};
+use reqwest::{ClientBuilder, Proxy};
+
+/// 单类流量的代理设置
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ProxySetting {
+    /// 使用系统代理（HTTP_PROXY / HTTPS_PROXY / ALL_PROXY 环境变量）
+    #[default]
+    System,
+    /// 直连，忽略系统代理
+    Direct,
+    /// http://、https://、socks5://、socks5h:// 代理
+    Url(String),
+}
+
+impl ProxySetting {
+    pub fn apply(&self, builder: ClientBuilder) -> Result<ClientBuilder, reqwest::Error> {
+        Ok(match self {
+            ProxySetting::System => builder,
+            ProxySetting::Direct => builder.no_proxy(),
+            ProxySetting::Url(url) => builder.proxy(Proxy::all(url)?),
+        })
+    }
+}
+
+impl FromStr for ProxySetting {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        match s.to_ascii_lowercase().as_str() {
+            "direct" | "none" => return Ok(ProxySetting::Direct),
+            "system" => return Ok(ProxySetting::System),
+            _ => {}
+        }
+
+        let scheme = s
+            .split_once("://")
+            .map(|(scheme, _)| scheme.to_ascii_lowercase());
+        match scheme.as_deref() {
+            Some("http" | "https" | "socks5" | "socks5h") => {}
+            _ => {
+                return Err(format!(
+                    "不支持的代理地址 '{}'，应为 http://、https://、socks5:// 或 socks5h:// 开头，或 direct / system",
+                    s
+                ));
+            }
+        }
+        Proxy::all(s).map_err(|e| format!("无效的代理地址 '{}': {}", s, e))?;
+        Ok(ProxySetting::Url(s.to_string()))
+    }
+}
+
+impl fmt::Display for ProxySetting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProxySetting::System => write!(f, "system"),
+            ProxySetting::Direct => write!(f, "direct"),
+            ProxySetting::Url(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// 网络配置：按流量类型分别设置代理，以及自定义请求头和出口地址
+#[derive(Debug, Clone, Default)]
+pub struct NetworkConfig {
+    pub api_proxy: ProxySetting,             // 解析视频信息等 API 请求
+    pub cdn_proxy: ProxySetting,             // 音视频等媒体文件下载
+    pub playurl_proxy: Option<ProxySetting>, // 番剧播放地址，未设置时与 API 相同
+    pub user_agent: Option<String>,
+    pub headers: Vec<(String, String)>, // 附加到每个请求的请求头
+    pub local_address: Option<IpAddr>,  // 绑定的本地出口地址
+}
+
+impl NetworkConfig {
+    /// 在默认请求头上应用自定义 User-Agent 与附加请求头
+    pub fn apply_headers(&self, headers: &mut HeaderMap) -> Result<(), String> {
+        if let Some(user_agent) = &self.user_agent {
+            let value = HeaderValue::from_str(user_agent)
+                .map_err(|e| format!("无效的 User-Agent '{}': {}", user_agent, e))?;
+            headers.insert(USER_AGENT, value);
+        }
+        for (name, value) in &self.headers {
+            let (name, value) = header_pair(name, value)?;
+            headers.insert(name, value);
+        }
+        Ok(())
+    }
+}
+
+/// 解析 `Name: Value` 格式的请求头
+pub fn parse_header(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("无效的请求头 '{}'，格式应为 'Name: Value'", s))?;
+    let (name, value) = (name.trim(), value.trim());
+    header_pair(name, value)?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn header_pair(name: &str, value: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let name =
+        HeaderName::from_str(name).map_err(|e| format!("无效的请求头名称 '{}': {}", name, e))?;
+    let value =
+        HeaderValue::from_str(value).map_err(|e| format!("无效的请求头内容 '{}': {}", value, e))?;
+    Ok((name, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proxy_and_header() {
+        assert_eq!("direct".parse(), Ok(ProxySetting::Direct));
+        assert_eq!(
+            "socks5h://127.0.0.1:1080".parse(),
+            Ok(ProxySetting::Url("socks5h://127.0.0.1:1080".to_string()))
+        );
+        assert!("ftp://127.0.0.1".parse::<ProxySetting>().is_err());
+
+        assert_eq!(
+            parse_header("X-Forwarded-For: 1.1.1.1"),
+            Ok(("X-Forwarded-For".to_string(), "1.1.1.1".to_string()))
+        );
+        assert!(parse_header("no-colon").is_err());
+    }
+}
@@ -0,0 +1,153 @@
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
+
+use crate::common::client::error::ApiError;
+
+/// 缓存文件名，与会话的 cookies.jsonl 放在同一目录
+pub const WBI_KEYS_FILE: &str = "wbi_keys.json";
+
+/// WBI 签名密钥，B站每天轮换一次
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WbiKeys {
+    pub img_key: String,
+    pub sub_key: String,
+    pub fetched_at: i64, // 获取时间，unix 秒
+}
+
+impl WbiKeys {
+    pub fn new(img_key: String, sub_key: String) -> Self {
+        Self {
+            img_key,
+            sub_key,
+            fetched_at: Local::now().timestamp(),
+        }
+    }
+
+    /// 不是今天（本地时间）获取的密钥视为过期
+    pub fn is_expired(&self) -> bool {
+        DateTime::from_timestamp(self.fetched_at, 0).is_none_or(|fetched| {
+            fetched.with_timezone(&Local).date_naive() != Local::now().date_naive()
+        })
+    }
+}
+
+/// WBI 密钥缓存，克隆的客户端共享；设置了持久化路径时每次更新都会写回磁盘
+#[derive(Debug, Clone, Default)]
+pub struct WbiKeyCache {
+    inner: Arc<RwLock<WbiKeyCacheState>>,
+}
+
+#[derive(Debug, Default)]
+struct WbiKeyCacheState {
+    keys: Option<WbiKeys>,
+    path: Option<PathBuf>,
+}
+
+impl WbiKeyCache {
+    /// 未过期的缓存密钥
+    pub fn get(&self) -> Option<WbiKeys> {
+        let state = self.inner.read().ok()?;
+        state.keys.clone().filter(|keys| !keys.is_expired())
+    }
+
+    pub fn set(&self, keys: WbiKeys) {
+        let Ok(mut state) = self.inner.write() else {
+            return;
+        };
+        if let Some(path) = &state.path
+            && let Err(e) = Self::save(path, &keys)
+        {
+            warn!("保存 WBI 密钥到 {} 失败: {}", path.display(), e);
+        }
+        state.keys = Some(keys);
+    }
+
+    /// 清除缓存，下次签名时重新获取
+    pub fn invalidate(&self) {
+        if let Ok(mut state) = self.inner.write() {
+            state.keys = None;
+        }
+    }
+
+    /// 绑定会话目录：读取其中未过期的密钥，之后的更新写入该目录
+    pub fn attach(&self, session_dir: &Path) {
+        let path = session_dir.join(WBI_KEYS_FILE);
+        let Ok(mut state) = self.inner.write() else {
+            return;
+        };
+        match Self::load(&path) {
+            Ok(keys) if !keys.is_expired() => {
+                debug!("从 {} 加载 WBI 密钥", path.display());
+                state.keys = Some(keys);
+            }
+            Ok(_) => debug!("{} 中的 WBI 密钥已过期", path.display()),
+            Err(_) => {}
+        }
+        state.path = Some(path);
+    }
+
+    /// 将当前密钥写入会话目录，并绑定该目录
+    pub fn persist(&self, session_dir: &Path) -> Result<(), ApiError> {
+        let path = session_dir.join(WBI_KEYS_FILE);
+        let Ok(mut state) = self.inner.write() else {
+            return Err(ApiError::LockError);
+        };
+        if let Some(keys) = &state.keys {
+            Self::save(&path, keys)?;
+        }
+        state.path = Some(path);
+        Ok(())
+    }
+
+    fn load(path: &Path) -> Result<WbiKeys, ApiError> {
+        let content = std::fs::read_to_string(path).map_err(|e| ApiError::Other(e.to_string()))?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(path: &Path, keys: &WbiKeys) -> Result<(), ApiError> {
+        let content = serde_json::to_string_pretty(keys)?;
+        std::fs::write(path, content).map_err(|e| ApiError::Other(e.to_string()))
+    }
+}
+
+impl ApiError {
+    /// 是否为 WBI 签名校验失败（密钥轮换后旧签名会被拒绝）
+    pub fn is_wbi_rejected(&self) -> bool {
+        matches!(
+            self,
+            ApiError::ApiError(-403, _) | ApiError::RiskControl(-352, _)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_persists_and_expires() {
+        let dir = std::env::temp_dir().join(format!("wbi_keys_{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let cache = WbiKeyCache::default();
+        cache.attach(&dir);
+        assert!(cache.get().is_none());
+        cache.set(WbiKeys::new("img".to_string(), "sub".to_string()));
+
+        let restored = WbiKeyCache::default();
+        restored.attach(&dir);
+        assert_eq!(restored.get().unwrap().img_key, "img");
+        restored.invalidate();
+        assert!(restored.get().is_none());
+
+        let mut stale = WbiKeys::new("img".to_string(), "sub".to_string());
+        stale.fetched_at -= 24 * 60 * 60;
+        assert!(stale.is_expired());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
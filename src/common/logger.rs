@@ -1,4 +1,21 @@
+use std::io::{self, Write};
+use std::sync::Mutex;
+
 use colored::*;
+use indicatif::MultiProgress;
+
+// 正在显示的下载面板；存在时日志输出到进度条上方，避免破坏进度条
+static PROGRESS: Mutex<Option<MultiProgress>> = Mutex::new(None);
+
+fn print_line(line: String) {
+    let progress = PROGRESS.lock().ok().and_then(|progress| progress.clone());
+    match progress {
+        Some(multi) if !multi.is_hidden() => {
+            let _ = multi.println(line);
+        }
+        _ => println!("{}", line),
+    }
+}
 
 /// 漂亮的日志输出工具
 pub struct PrettyLogger;
@@ -6,32 +23,32 @@ pub struct PrettyLogger;
 impl PrettyLogger {
     /// 显示成功消息
     pub fn success(message: impl AsRef<str>) {
-        println!("{} {}", "✓".green().bold(), message.as_ref());
+        print_line(format!("{} {}", "✓".green().bold(), message.as_ref()));
     }
 
     /// 显示信息消息
     pub fn info(message: impl AsRef<str>) {
-        println!("{} {}", "ℹ".blue().bold(), message.as_ref());
+        print_line(format!("{} {}", "ℹ".blue().bold(), message.as_ref()));
     }
 
     /// 显示警告消息
     pub fn warning(message: impl AsRef<str>) {
-        println!("{} {}", "⚠".yellow().bold(), message.as_ref());
+        print_line(format!("{} {}", "⚠".yellow().bold(), message.as_ref()));
     }
 
     /// 显示错误消息
     pub fn error(message: impl AsRef<str>) {
-        println!("{} {}", "✗".red().bold(), message.as_ref());
+        print_line(format!("{} {}", "✗".red().bold(), message.as_ref()));
     }
 
     /// 显示步骤开始
     pub fn step_start(step: impl AsRef<str>) {
-        println!("\n{} {}", "▶".cyan().bold(), step.as_ref().bold());
+        print_line(format!("\n{} {}", "▶".cyan().bold(), step.as_ref().bold()));
     }
 
     /// 显示步骤完成
     pub fn step_complete(step: impl AsRef<str>) {
-        println!("{} {}", "✓".green().bold(), step.as_ref().green());
+        print_line(format!("{} {}", "✓".green().bold(), step.as_ref().green()));
     }
 
     /// 显示下载进度
@@ -47,27 +64,27 @@ impl PrettyLogger {
             progress * 100.0
         );
 
-        println!("{} {} {}", "⬇".blue().bold(), filename.as_ref(), bar);
+        print_line(format!("{} {} {}", "⬇".blue().bold(), filename.as_ref(), bar));
     }
 
     /// 显示文件信息
     pub fn file_info(label: impl AsRef<str>, path: impl AsRef<str>) {
-        println!("{} {}: {}", "📁".blue().bold(), label.as_ref().bold(), path.as_ref());
+        print_line(format!("{} {}: {}", "📁".blue().bold(), label.as_ref().bold(), path.as_ref()));
     }
 
     /// 显示用户状态
     pub fn user_status(status: impl AsRef<str>, details: impl AsRef<str>) {
-        println!("{} {} - {}", "👤".green().bold(), status.as_ref().bold(), details.as_ref());
+        print_line(format!("{} {} - {}", "👤".green().bold(), status.as_ref().bold(), details.as_ref()));
     }
 
     /// 显示视频信息
     pub fn video_info(title: impl AsRef<str>, quality: impl AsRef<str>) {
-        println!("{} {} ({})", "🎬".magenta().bold(), title.as_ref().bold(), quality.as_ref().cyan());
+        print_line(format!("{} {} ({})", "🎬".magenta().bold(), title.as_ref().bold(), quality.as_ref().cyan()));
     }
 
     /// 显示分割线
     pub fn separator() {
-        println!("{}", "─".repeat(50).bright_black());
+        print_line(format!("{}", "─".repeat(50).bright_black()));
     }
 
     /// 显示标题
@@ -75,26 +92,69 @@ impl PrettyLogger {
         let text = text.as_ref();
         let padding = (48 - text.len()) / 2;
         let line = "─".repeat(padding);
-        println!("{} {} {}", line.bright_black(), text.bold(), "─".repeat(48 - padding - text.len()).bright_black());
+        print_line(format!("{} {} {}", line.bright_black(), text.bold(), "─".repeat(48 - padding - text.len()).bright_black()));
     }
 
     /// 显示完成总结
     pub fn completion_summary(items: Vec<impl AsRef<str>>) {
-        println!("\n{}", "🎉 下载完成！".green().bold());
+        print_line(format!("\n{}", "🎉 下载完成！".green().bold()));
         for item in items {
-            println!("  {}", item.as_ref());
+            print_line(format!("  {}", item.as_ref()));
         }
     }
 
     /// 显示登录提示
     pub fn login_prompt() {
-        println!("\n{}", "🔐 请使用手机扫描二维码登录".cyan().bold());
-        println!("{}", "   扫描完成后按 Enter 键继续...".bright_black());
+        print_line(format!("\n{}", "🔐 请使用手机扫描二维码登录".cyan().bold()));
+        print_line(format!("{}", "   扫描完成后按 Enter 键继续...".bright_black()));
+    }
+
+    /// 下载面板显示期间，日志改为输出到进度条上方
+    pub fn attach_progress(multi: MultiProgress) {
+        if let Ok(mut progress) = PROGRESS.lock() {
+            *progress = Some(multi);
+        }
+    }
+
+    /// 下载面板结束后恢复直接输出
+    pub fn detach_progress() {
+        if let Ok(mut progress) = PROGRESS.lock() {
+            *progress = None;
+        }
     }
 
     /// 显示等待消息
     pub fn waiting(message: impl AsRef<str>) {
-        println!("{} {}", "⏳".yellow().bold(), message.as_ref());
+        print_line(format!("{} {}", "⏳".yellow().bold(), message.as_ref()));
+    }
+}
+
+/// tracing 日志的输出目标，与 `PrettyLogger` 一样会避开下载面板
+///
+/// 用法：`tracing_subscriber::fmt().with_writer(LogWriter::default)`
+#[derive(Default)]
+pub struct LogWriter {
+    buf: Vec<u8>,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Drop for LogWriter {
+    fn drop(&mut self) {
+        if self.buf.is_empty() {
+            return;
+        }
+        let line = String::from_utf8_lossy(&self.buf);
+        print_line(line.trim_end_matches('\n').to_string());
     }
 }
 
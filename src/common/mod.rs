@@ -7,6 +7,9 @@ pub mod client {
     }
     pub mod client;
     pub mod error;
+    pub mod governor;
+    pub mod network;
+    pub mod wbi_keys;
 }
 pub mod download_type {
     pub mod common_video;
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use serde::Deserialize;
 
 use crate::{
-    downloader::models::DownloadTask,
+    downloader::{events::EventSender, models::DownloadTask, report::DownloadReport},
     parser::{detail_parser::parser_trait::ParserOptions, errors::ParseError},
     post_process::post_process,
 };
@@ -35,11 +35,13 @@ impl ParsedMeta {
         &self,
         task: &Vec<DownloadTask>,
         parser_options: &ParserOptions,
+        report: &DownloadReport,
+        events: &EventSender,
     ) -> Result<(), ParseError> {
         match &self.download_type {
-            DownloadType::CommonVideo => post_process(task, parser_options).await,
-            DownloadType::Bangumi => post_process(task, parser_options).await,
-            DownloadType::Course => post_process(task, parser_options).await,
+            DownloadType::CommonVideo => post_process(task, parser_options, report, events).await,
+            DownloadType::Bangumi => post_process(task, parser_options, report, events).await,
+            DownloadType::Course => post_process(task, parser_options, report, events).await,
             _ => Err(ParseError::ParseError("不支持的下载类型".to_string())),
         }
     }
@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use super::error::DownloadError;
+
+/// 用户目录下默认的下载存档文件
+pub const ARCHIVE_FILE: &str = "archive.jsonl";
+
+/// 存档条目的标识：同一内容的同一分P
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ArchiveKey {
+    pub kind: String, // video / bangumi / course，仅音频模式加 -audio 后缀
+    pub id: String,   // bvid 或 ep_id
+    pub cid: i64,
+}
+
+impl ArchiveKey {
+    pub fn new(kind: &str, id: impl ToString, cid: i64, audio_only: bool) -> Self {
+        let kind = if audio_only {
+            format!("{}-audio", kind)
+        } else {
+            kind.to_string()
+        };
+        Self {
+            kind,
+            id: id.to_string(),
+            cid,
+        }
+    }
+
+    /// 写入任务元数据，后处理成功后据此记录存档
+    pub fn insert_into(&self, metadata: &mut HashMap<String, String>) {
+        metadata.insert("archive_kind".to_string(), self.kind.clone());
+        metadata.insert("archive_id".to_string(), self.id.clone());
+        metadata.insert("archive_cid".to_string(), self.cid.to_string());
+    }
+
+    pub fn from_metadata(metadata: &HashMap<String, String>) -> Option<Self> {
+        Some(Self {
+            kind: metadata.get("archive_kind")?.clone(),
+            id: metadata.get("archive_id")?.clone(),
+            cid: metadata.get("archive_cid")?.parse().ok()?,
+        })
+    }
+}
+
+/// 一条下载存档记录
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+    #[serde(flatten)]
+    pub key: ArchiveKey,
+    pub quality: i32,
+    pub codec: String,
+    pub output: String,
+    pub archived_at: i64, // unix 时间戳（秒）
+}
+
+/// 下载存档：记录已完整下载并后处理成功的内容，重复运行时跳过
+///
+/// 文件为 JSON Lines 格式，只追加写入；同一标识出现多次时以最后一条为准。
+#[derive(Debug, Clone)]
+pub struct DownloadArchive {
+    path: PathBuf,
+    entries: Arc<RwLock<HashMap<ArchiveKey, ArchiveEntry>>>,
+}
+
+impl DownloadArchive {
+    /// 读取存档文件，文件不存在时为空存档
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, DownloadError> {
+        let path = path.as_ref().to_path_buf();
+        let mut entries = HashMap::new();
+        match std::fs::read_to_string(&path) {
+            Ok(content) => {
+                for (index, line) in content.lines().enumerate() {
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    match serde_json::from_str::<ArchiveEntry>(line) {
+                        Ok(entry) => {
+                            entries.insert(entry.key.clone(), entry);
+                        }
+                        Err(e) => warn!("忽略下载存档 {:?} 第 {} 行: {}", path, index + 1, e),
+                    }
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(DownloadError::IoError(format!(
+                    "读取下载存档 {:?} 失败: {}",
+                    path, e
+                )));
+            }
+        }
+        Ok(Self {
+            path,
+            entries: Arc::new(RwLock::new(entries)),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, key: &ArchiveKey) -> Option<ArchiveEntry> {
+        self.entries.read().unwrap().get(key).cloned()
+    }
+
+    /// 是否已存档该内容的任一分P
+    pub fn has_item(&self, kind: &str, id: &str) -> bool {
+        self.entries
+            .read()
+            .unwrap()
+            .keys()
+            .any(|key| key.kind == kind && key.id == id)
+    }
+
+    /// 追加一条记录并写入文件
+    pub fn record(&self, entry: ArchiveEntry) -> Result<(), DownloadError> {
+        let line =
+            serde_json::to_string(&entry).map_err(|e| DownloadError::IoError(e.to_string()))?;
+        if let Some(parent) = self.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", line)?;
+
+        self.entries
+            .write()
+            .unwrap()
+            .insert(entry.key.clone(), entry);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let path =
+            std::env::temp_dir().join(format!("bilidl-archive-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let key = ArchiveKey::new("bangumi", 12345, 678, false);
+        let archive = DownloadArchive::open(&path).unwrap();
+        assert!(archive.get(&key).is_none());
+
+        for quality in [80, 116] {
+            archive
+                .record(ArchiveEntry {
+                    key: key.clone(),
+                    quality,
+                    codec: "avc1.640032".to_string(),
+                    output: "番剧 - 第1话.mp4".to_string(),
+                    archived_at: 0,
+                })
+                .unwrap();
+        }
+
+        let reopened = DownloadArchive::open(&path).unwrap();
+        assert_eq!(reopened.len(), 1);
+        assert_eq!(reopened.get(&key).map(|e| e.quality), Some(116));
+        assert!(reopened.has_item("bangumi", "12345"));
+        assert!(
+            reopened
+                .get(&ArchiveKey::new("bangumi", 12345, 678, true))
+                .is_none()
+        );
+
+        let mut metadata = HashMap::new();
+        key.insert_into(&mut metadata);
+        assert_eq!(ArchiveKey::from_metadata(&metadata), Some(key));
+        let _ = std::fs::remove_file(&path);
+    }
+}
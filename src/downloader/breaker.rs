@@ -0,0 +1,184 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use tokio::sync::watch;
+
+/// 触发熔断所需的连续风控响应次数
+pub const RISK_CONTROL_THRESHOLD: u32 = 3;
+/// 首次熔断的冷却时间，之后每次探测失败翻倍
+const BASE_COOL_DOWN: Duration = Duration::from_secs(30);
+const MAX_COOL_DOWN: Duration = Duration::from_secs(30 * 60);
+
+/// 风控熔断器，由所有下载任务共享
+///
+/// 连续收到风控响应（403/429/401）达到阈值后打开，期间所有任务暂停；
+/// 冷却结束后由一次探测请求决定是恢复下载还是加倍退避。
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    state: Arc<Mutex<BreakerState>>,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    rejections: u32,                // 连续风控响应次数
+    trips: u32,                     // 连续熔断次数，决定退避时长
+    total_trips: u64,               // 累计熔断次数
+    open_until: Option<SystemTime>, // 冷却结束时间
+    recovering: bool,               // 是否已有恢复流程在运行
+    paused_tasks: Vec<String>,      // 因熔断而暂停的任务
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        let (closed, _) = watch::channel(true);
+        Self {
+            state: Arc::new(Mutex::new(BreakerState::default())),
+            closed: Arc::new(closed),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        !*self.closed.borrow()
+    }
+
+    /// 记录一次风控响应，熔断器因此（或此前已经）打开时返回 `true`
+    pub fn record_rejection(&self) -> bool {
+        let mut state = self.lock();
+        if self.is_open() {
+            return true;
+        }
+        state.rejections += 1;
+        if state.rejections < RISK_CONTROL_THRESHOLD {
+            return false;
+        }
+        self.trip(&mut state);
+        true
+    }
+
+    /// 请求成功，清零连续风控计数
+    pub fn record_success(&self) {
+        self.lock().rejections = 0;
+    }
+
+    /// 探测失败，加倍退避后继续熔断
+    pub fn reopen(&self) {
+        let mut state = self.lock();
+        self.trip(&mut state);
+    }
+
+    /// 探测成功后关闭熔断器，返回因熔断而暂停的任务
+    pub fn close(&self) -> Vec<String> {
+        let mut state = self.lock();
+        state.rejections = 0;
+        state.trips = 0;
+        state.open_until = None;
+        state.recovering = false;
+        self.closed.send_replace(true);
+        std::mem::take(&mut state.paused_tasks)
+    }
+
+    /// 从状态文件恢复上次未结束的冷却期
+    pub fn restore(&self, open_until: SystemTime, trips: u32) {
+        if open_until <= SystemTime::now() {
+            return;
+        }
+        let mut state = self.lock();
+        state.trips = trips.max(1);
+        state.open_until = Some(open_until);
+        self.closed.send_replace(false);
+    }
+
+    /// 抢占恢复流程，只有第一个调用者返回 `true`
+    pub fn try_begin_recovery(&self) -> bool {
+        let mut state = self.lock();
+        if !self.is_open() || state.recovering {
+            return false;
+        }
+        state.recovering = true;
+        true
+    }
+
+    pub fn track_paused(&self, task_id: String) {
+        self.lock().paused_tasks.push(task_id);
+    }
+
+    pub fn open_until(&self) -> Option<SystemTime> {
+        self.lock().open_until
+    }
+
+    pub fn trips(&self) -> u32 {
+        self.lock().trips
+    }
+
+    pub fn total_trips(&self) -> u64 {
+        self.lock().total_trips
+    }
+
+    /// 距冷却结束的剩余时间
+    pub fn remaining(&self) -> Duration {
+        self.open_until()
+            .and_then(|until| until.duration_since(SystemTime::now()).ok())
+            .unwrap_or_default()
+    }
+
+    /// 等待熔断器关闭
+    pub async fn wait_closed(&self) {
+        let mut closed = self.closed.subscribe();
+        let _ = closed.wait_for(|closed| *closed).await;
+    }
+
+    fn trip(&self, state: &mut BreakerState) {
+        state.rejections = 0;
+        state.trips += 1;
+        state.total_trips += 1;
+        state.open_until = Some(SystemTime::now() + cool_down(state.trips));
+        self.closed.send_replace(false);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BreakerState> {
+        self.state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn cool_down(trips: u32) -> Duration {
+    BASE_COOL_DOWN
+        .saturating_mul(1 << trips.saturating_sub(1).min(16))
+        .min(MAX_COOL_DOWN)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_breaker_trips_and_backs_off() {
+        let breaker = CircuitBreaker::new();
+        for _ in 1..RISK_CONTROL_THRESHOLD {
+            assert!(!breaker.record_rejection());
+        }
+        assert!(breaker.record_rejection());
+        assert!(breaker.is_open());
+        assert!(breaker.try_begin_recovery());
+        assert!(!breaker.try_begin_recovery());
+
+        let first = breaker.remaining();
+        breaker.reopen();
+        assert!(breaker.remaining() > first);
+        assert_eq!(breaker.trips(), 2);
+
+        breaker.track_paused("a".to_string());
+        assert_eq!(breaker.close(), vec!["a".to_string()]);
+        assert!(!breaker.is_open());
+        assert_eq!(breaker.total_trips(), 2);
+        assert_eq!(cool_down(20), MAX_COOL_DOWN);
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use futures::future::join_all;
+use reqwest::Url;
+use tracing::debug;
+use url::Host;
+
+use crate::common::client::client::BiliClient;
+
+/// 测速时下载的字节数
+const PROBE_BYTES: u64 = 256 * 1024;
+/// 单个候选地址的测速超时
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+/// 最多同时测速的候选地址数
+const MAX_PROBE_CANDIDATES: usize = 6;
+
+/// CDN 节点类型，按优先级从高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum CdnHostKind {
+    Upos,  // 官方 upos-* 节点
+    Other, // 其他官方边缘节点，如 cn-*.bilivideo.com
+    Mcdn,  // *.mcdn.bilivideo.cn 多媒体 CDN，经常很慢
+    Pcdn,  // 用户侧 PCDN：IP 直连、非标准端口、szbdyd.com 等，服务器上常常无法访问
+}
+
+impl CdnHostKind {
+    pub fn classify(url: &str) -> Self {
+        let Ok(url) = Url::parse(url) else {
+            return Self::Other;
+        };
+        let host = url.host_str().unwrap_or_default().to_ascii_lowercase();
+
+        if host.contains(".mcdn.bilivideo") {
+            Self::Mcdn
+        } else if matches!(url.host(), Some(Host::Ipv4(_) | Host::Ipv6(_)))
+            || host.ends_with(".szbdyd.com")
+            || url.port().is_some_and(|port| port != 80 && port != 443)
+        {
+            Self::Pcdn
+        } else if host.starts_with("upos-") {
+            Self::Upos
+        } else {
+            Self::Other
+        }
+    }
+}
+
+/// 是否为B站的音视频 CDN 地址，这类地址需要携带下载专用请求头
+pub fn is_bilibili_cdn(url: &str) -> bool {
+    let Some(host) = Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_ascii_lowercase))
+    else {
+        return false;
+    };
+    [
+        "bilivideo.com",
+        "bilivideo.cn",
+        "akamaized.net",
+        "szbdyd.com",
+    ]
+    .iter()
+    .any(|domain| host == *domain || host.ends_with(&format!(".{}", domain)))
+        || CdnHostKind::classify(url) == CdnHostKind::Pcdn
+}
+
+/// CDN 选择策略
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CdnPolicy {
+    pub mirror: Option<String>, // 将官方节点改写为指定的 upos 镜像，如 upos-sz-mirrorali.bilivideo.com
+    pub probe: bool,            // 开始下载前对候选地址测速，选择最快的
+}
+
+impl CdnPolicy {
+    /// 由主地址和备用地址得到按优先级排序、去重后的候选地址
+    ///
+    /// 官方节点排在 MCDN/PCDN 之前；设置了镜像时，改写后的地址排在最前，原地址作为后备。
+    pub fn candidates(&self, url: &str, backups: &[String]) -> Vec<String> {
+        let mut candidates: Vec<String> = Vec::new();
+        for url in std::iter::once(url).chain(backups.iter().map(String::as_str)) {
+            if !candidates.iter().any(|c| c == url) {
+                candidates.push(url.to_string());
+            }
+        }
+        // 稳定排序，同类节点保持接口返回的顺序
+        candidates.sort_by_key(|url| CdnHostKind::classify(url));
+
+        if let Some(mirror) = &self.mirror {
+            let rewritten: Vec<String> = candidates
+                .iter()
+                .filter_map(|url| rewrite_host(url, mirror))
+                .collect();
+            for url in rewritten.into_iter().rev() {
+                candidates.retain(|c| *c != url);
+                candidates.insert(0, url);
+            }
+        }
+        candidates
+    }
+}
+
+/// 将官方节点的地址改写到指定镜像，MCDN/PCDN 的路径格式不同，不做改写
+fn rewrite_host(url: &str, mirror: &str) -> Option<String> {
+    if !matches!(
+        CdnHostKind::classify(url),
+        CdnHostKind::Upos | CdnHostKind::Other
+    ) || !is_bilibili_cdn(url)
+    {
+        return None;
+    }
+    let mut url = Url::parse(url).ok()?;
+    url.set_host(Some(mirror)).ok()?;
+    url.set_port(None).ok()?;
+    Some(url.to_string())
+}
+
+/// 对候选地址测速，按速度从快到慢重新排序；测速失败的地址排在最后
+pub async fn rank_by_speed(client: &BiliClient, candidates: Vec<String>) -> Vec<String> {
+    if candidates.len() < 2 {
+        return candidates;
+    }
+    let split = candidates.len().min(MAX_PROBE_CANDIDATES);
+    let (probed, rest) = candidates.split_at(split);
+
+    let speeds = join_all(probed.iter().map(|url| measure(client, url))).await;
+    let mut ranked: Vec<(String, Option<f64>)> = probed.iter().cloned().zip(speeds).collect();
+    for (url, speed) in &ranked {
+        match speed {
+            Some(speed) => debug!("CDN 测速 {:.1} KB/s: {}", speed / 1024.0, url),
+            None => debug!("CDN 测速失败: {}", url),
+        }
+    }
+    ranked.sort_by(|(_, a), (_, b)| b.unwrap_or(-1.0).total_cmp(&a.unwrap_or(-1.0)));
+
+    ranked
+        .into_iter()
+        .map(|(url, _)| url)
+        .chain(rest.iter().cloned())
+        .collect()
+}
+
+// 下载前 PROBE_BYTES 字节，返回速度（字节/秒），失败返回 None
+async fn measure(client: &BiliClient, url: &str) -> Option<f64> {
+    let started = Instant::now();
+    let transfer = async {
+        let response = client
+            .download
+            .get(url)
+            .headers(BiliClient::get_video_download_headers(url))
+            .header(
+                reqwest::header::RANGE,
+                format!("bytes=0-{}", PROBE_BYTES - 1),
+            )
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let mut stream = response.bytes_stream();
+        let mut received = 0u64;
+        while let Some(chunk) = stream.next().await {
+            received += chunk.ok()?.len() as u64;
+            if received >= PROBE_BYTES {
+                break;
+            }
+        }
+        Some(received)
+    };
+
+    let received = tokio::time::timeout(PROBE_TIMEOUT, transfer)
+        .await
+        .ok()
+        .flatten()?;
+    (received > 0).then(|| received as f64 / started.elapsed().as_secs_f64().max(0.001))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_candidates_prefer_upos_and_rewrite() {
+        let pcdn = "https://112.1.2.3:4483/upgcxcode/1.m4s?e=1".to_string();
+        let mcdn = "https://xy1x2x3x4xy.mcdn.bilivideo.cn:8082/v1/resource/1.m4s".to_string();
+        let upos = "https://upos-sz-estgoss.bilivideo.com/upgcxcode/1.m4s?e=1".to_string();
+
+        assert_eq!(CdnHostKind::classify(&pcdn), CdnHostKind::Pcdn);
+        assert_eq!(CdnHostKind::classify(&mcdn), CdnHostKind::Mcdn);
+        assert!(is_bilibili_cdn(&upos));
+        assert!(!is_bilibili_cdn("https://i0.hdslb.com/bfs/cover.jpg"));
+
+        let policy = CdnPolicy::default();
+        assert_eq!(
+            policy.candidates(&mcdn, &[pcdn.clone(), upos.clone()]),
+            vec![upos.clone(), mcdn.clone(), pcdn.clone()]
+        );
+
+        let policy = CdnPolicy {
+            mirror: Some("upos-sz-mirrorali.bilivideo.com".to_string()),
+            probe: false,
+        };
+        assert_eq!(
+            policy.candidates(&mcdn, &[upos.clone()]),
+            vec![
+                "https://upos-sz-mirrorali.bilivideo.com/upgcxcode/1.m4s?e=1".to_string(),
+                upos,
+                mcdn
+            ]
+        );
+    }
+}
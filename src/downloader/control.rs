@@ -0,0 +1,138 @@
+use std::sync::Arc;
+
+use tokio::sync::watch;
+use tokio_util::sync::CancellationToken;
+
+use super::error::DownloadError;
+
+/// 下载任务的控制句柄：取消令牌 + 暂停闸门
+///
+/// 取消令牌由父令牌派生，取消父令牌（如整个下载作业）会同时取消所有子任务。
+/// 暂停没有时限，任务会一直等待恢复或取消，等待该任务的下载作业也不会结束。
+#[derive(Debug, Clone)]
+pub struct TaskControl {
+    cancel: CancellationToken,
+    paused: Arc<watch::Sender<bool>>,
+}
+
+/// 下载中断的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interrupt {
+    Paused,
+    Cancelled,
+}
+
+impl TaskControl {
+    pub fn new(parent: &CancellationToken) -> Self {
+        let (paused, _) = watch::channel(false);
+        Self {
+            cancel: parent.child_token(),
+            paused: Arc::new(paused),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+        // 唤醒等待恢复的任务，使其感知取消
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.is_cancelled()
+    }
+
+    pub fn pause(&self) {
+        self.paused.send_replace(true);
+    }
+
+    pub fn resume(&self) {
+        self.paused.send_replace(false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        *self.paused.borrow()
+    }
+
+    /// 等待直到任务被取消
+    pub async fn cancelled(&self) {
+        self.cancel.cancelled().await;
+    }
+
+    /// 等待直到任务被暂停或取消
+    pub async fn interrupted(&self) -> Interrupt {
+        let mut paused = self.paused.subscribe();
+        tokio::select! {
+            _ = self.cancel.cancelled() => Interrupt::Cancelled,
+            _ = paused.wait_for(|paused| *paused) => Interrupt::Paused,
+        }
+    }
+
+    /// 任务暂停时阻塞直到恢复；任务被取消时返回 `DownloadError::Cancelled`
+    pub async fn wait_if_paused(&self) -> Result<(), DownloadError> {
+        let mut paused = self.paused.subscribe();
+        tokio::select! {
+            _ = self.cancel.cancelled() => {}
+            _ = paused.wait_for(|paused| !*paused) => {}
+        }
+
+        if self.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+        Ok(())
+    }
+}
+
+impl From<Interrupt> for DownloadError {
+    fn from(interrupt: Interrupt) -> Self {
+        match interrupt {
+            Interrupt::Paused => DownloadError::Paused,
+            Interrupt::Cancelled => DownloadError::Cancelled,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_pause_resume_cancel() {
+        let parent = CancellationToken::new();
+        let control = TaskControl::new(&parent);
+
+        // 未暂停时立即通过
+        control.wait_if_paused().await.unwrap();
+
+        control.pause();
+        assert_eq!(control.interrupted().await, Interrupt::Paused);
+        let waiting = tokio::spawn({
+            let control = control.clone();
+            async move { control.wait_if_paused().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        control.resume();
+        waiting.await.unwrap().unwrap();
+
+        // 暂停中取消：等待恢复的任务被唤醒并返回 Cancelled
+        control.pause();
+        let waiting = tokio::spawn({
+            let control = control.clone();
+            async move { control.wait_if_paused().await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        control.cancel();
+        assert!(matches!(
+            waiting.await.unwrap(),
+            Err(DownloadError::Cancelled)
+        ));
+        assert!(!control.is_paused());
+        assert_eq!(control.interrupted().await, Interrupt::Cancelled);
+
+        // 取消父令牌同样会取消子任务
+        let child = TaskControl::new(&parent);
+        parent.cancel();
+        assert!(child.is_cancelled());
+    }
+}
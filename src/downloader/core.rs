@@ -1,22 +1,36 @@
 use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
 
 use crate::common::client::client::BiliClient;
 use crate::downloader::models::{DownloadProgress, FileType, TaskStatus};
 
+use super::breaker::CircuitBreaker;
+use super::cdn::{self, CdnPolicy};
+use super::control::TaskControl;
 use super::error::DownloadError;
+use super::events::{DownloadEvent, EventSender, ProgressMeter, emit, event_channel};
+use super::partial::{self, PartialMeta};
+use super::rate_limit::{RateLimitConfig, RateLimiter};
 use chardetng::EncodingDetector;
 use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
 use flate2::read::{DeflateDecoder, GzDecoder};
-use indicatif::ProgressBar;
-use tokio::sync::{Mutex, Semaphore};
+use tokio::sync::{Mutex, Semaphore, broadcast};
+use tokio_util::sync::CancellationToken;
 
 use tracing::{debug, error, info, warn};
 
 #[derive(Clone)]
 pub struct DownloadCore {
     tasks: Arc<Mutex<DashMap<String, Arc<Mutex<DownloadProgress>>>>>, // task_id -> Task
+    controls: Arc<DashMap<String, TaskControl>>,                       // task_id -> 控制句柄
+    events: EventSender,                                               // 下载事件通道
+    global_limit: RateLimiter,                                         // 全局带宽限制
+    task_limit: RateLimiter,                                           // 单任务带宽限制
+    breaker: CircuitBreaker,                                           // 风控熔断器
+    cdn: Arc<std::sync::RwLock<CdnPolicy>>,                            // CDN 选择策略
     state_file: PathBuf,
     semaphore: Arc<Semaphore>, // 控制并发数
     download_client: BiliClient,
@@ -29,21 +43,47 @@ impl DownloadCore {
         download_client: &BiliClient,
     ) -> Self {
         let state_file = state_file.as_ref().to_path_buf();
+        let breaker = CircuitBreaker::new();
+        // 恢复上次运行未结束的风控冷却期
+        let journal = std::fs::read(&state_file)
+            .ok()
+            .and_then(|content| serde_json::from_slice::<StateJournal>(&content).ok());
+        if let Some(StateJournal {
+            cool_down_until: Some(until),
+            breaker_trips,
+            ..
+        }) = journal
+        {
+            breaker.restore(UNIX_EPOCH + Duration::from_secs(until), breaker_trips);
+        }
+
         Self {
             tasks: Arc::new(Mutex::new(DashMap::new())),
+            controls: Arc::new(DashMap::new()),
+            events: event_channel(),
+            global_limit: RateLimiter::new(0),
+            task_limit: RateLimiter::new(0),
+            breaker,
+            cdn: Arc::new(std::sync::RwLock::new(CdnPolicy::default())),
             state_file: state_file,
             semaphore: Arc::new(Semaphore::new(max_concurrent)),
             download_client: download_client.clone(),
         }
     }
 
-    // 添加新的下载任务
+    // 添加新的下载任务，任务的取消令牌由 `parent` 派生；`mirrors` 为备用地址
     pub async fn add_task(
         &self,
         url: &str,
+        mirrors: &[String],
         output: &Path,
         file_type: &FileType,
+        parent: &CancellationToken,
     ) -> Result<String, DownloadError> {
+        if parent.is_cancelled() {
+            return Err(DownloadError::Cancelled);
+        }
+
         let task_id = uuid::Uuid::new_v4().to_string();
         let _permit = self
             .semaphore
@@ -56,9 +96,19 @@ impl DownloadCore {
         // 根据文件类型选择下载策略
         let strategy = DownloadStrategy::for_file_type(&file_type);
 
-        // 检查内容并获取大小（仅对需要的类型）
-        // 获取内容信息（统一调用，避免重复）
-        let content_info = get_content_info(&self.download_client, url).await?;
+        // 按 CDN 策略排序候选地址，使用第一个可用的地址
+        let candidates = self.cdn_candidates(url, mirrors).await;
+        // 风控熔断期间不发起新请求，等待恢复后再获取内容信息
+        self.wait_for_breaker(&candidates[0], parent).await?;
+        let (url, content_info, mirrors) = match self.resolve_candidates(candidates).await {
+            Ok(resolved) => resolved,
+            Err(DownloadError::RateLimited(msg)) => {
+                self.breaker.record_rejection();
+                return Err(DownloadError::RateLimited(msg));
+            }
+            Err(e) => return Err(e),
+        };
+        let url = url.as_str();
 
         // 根据策略处理文件大小
         let total_size = match strategy {
@@ -93,6 +143,7 @@ impl DownloadCore {
             total_size,
             downloaded: 0,
             status: TaskStatus::Queued,
+            mirrors,
         };
 
         let tasks = self.tasks.lock().await;
@@ -102,6 +153,28 @@ impl DownloadCore {
             return Err(DownloadError::TaskAlreadyExists(task_id_clone.clone()));
         }
         tasks.insert(task_id_clone.clone(), Arc::new(Mutex::new(task)));
+        let control = TaskControl::new(parent);
+        self.controls.insert(task_id_clone.clone(), control.clone());
+        emit(
+            &self.events,
+            DownloadEvent::TaskAdded {
+                task_id: task_id.clone(),
+                name: output
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_else(|| url.to_string()),
+                file_type: file_type.clone(),
+                total_size,
+            },
+        );
+        let context = TaskContext {
+            task_id: task_id.clone(),
+            control,
+            events: self.events.clone(),
+            global_limit: self.global_limit.clone(),
+            task_limit: self.task_limit.fork(),
+            core: self.clone(),
+        };
         let tasks = Arc::clone(&self.tasks);
         let download_client = self.download_client.clone();
         let file_type_owned = file_type.clone();
@@ -115,6 +188,7 @@ impl DownloadCore {
                 file_type_owned,
                 strategy,
                 content_info,
+                context,
             )
             .await;
         });
@@ -122,15 +196,282 @@ impl DownloadCore {
         Ok(task_id)
     }
 
+    // 订阅下载事件
+    pub fn subscribe(&self) -> broadcast::Receiver<DownloadEvent> {
+        self.events.subscribe()
+    }
+
+    // 事件发送端，供后处理等环节发布事件
+    pub fn events(&self) -> EventSender {
+        self.events.clone()
+    }
+
+    // 应用带宽限制配置，对正在下载的任务同样生效
+    pub fn apply_rate_limits(&self, config: &RateLimitConfig) {
+        self.global_limit.set_rate(config.global);
+        self.global_limit.set_schedules(config.schedules.clone());
+        self.task_limit.set_rate(config.per_task);
+    }
+
+    // 当前的带宽限制配置
+    pub fn rate_limits(&self) -> RateLimitConfig {
+        RateLimitConfig {
+            global: self.global_limit.rate(),
+            per_task: self.task_limit.rate(),
+            schedules: self.global_limit.schedules(),
+        }
+    }
+
+    // 当前生效的全局速率（考虑分时段规则），0 表示不限速
+    pub fn effective_rate_limit(&self) -> u64 {
+        self.global_limit.effective_rate()
+    }
+
+    // 设置 CDN 选择策略，对之后添加的任务生效
+    pub fn set_cdn_policy(&self, policy: CdnPolicy) {
+        if let Ok(mut current) = self.cdn.write() {
+            *current = policy;
+        }
+    }
+
+    pub fn cdn_policy(&self) -> CdnPolicy {
+        self.cdn.read().map(|p| p.clone()).unwrap_or_default()
+    }
+
+    // 按 CDN 策略得到排序后的候选地址，开启测速时按实测速度排序
+    async fn cdn_candidates(&self, url: &str, mirrors: &[String]) -> Vec<String> {
+        let policy = self.cdn_policy();
+        let candidates = policy.candidates(url, mirrors);
+        if policy.probe && candidates.len() > 1 {
+            cdn::rank_by_speed(&self.download_client, candidates).await
+        } else {
+            candidates
+        }
+    }
+
+    // 熔断器打开时等待其关闭；没有任务在负责恢复时，由当前任务发起冷却与探测
+    async fn wait_for_breaker(
+        &self,
+        probe_url: &str,
+        parent: &CancellationToken,
+    ) -> Result<(), DownloadError> {
+        if !self.breaker.is_open() {
+            return Ok(());
+        }
+        if self.breaker.try_begin_recovery() {
+            tokio::spawn(self.clone().recover_from_risk_control(probe_url.to_string()));
+        }
+        tokio::select! {
+            _ = parent.cancelled() => Err(DownloadError::Cancelled),
+            _ = self.breaker.wait_closed() => Ok(()),
+        }
+    }
+
+    // 依次尝试候选地址，返回第一个可用的地址、内容信息以及剩余的候选地址
+    async fn resolve_candidates(
+        &self,
+        candidates: Vec<String>,
+    ) -> Result<(String, DownloadContent, Vec<String>), DownloadError> {
+        let mut last_error = DownloadError::InvalidUrl("没有可用的下载地址".to_string());
+        for (index, url) in candidates.iter().enumerate() {
+            match get_content_info(&self.download_client, url).await {
+                Ok(info) => {
+                    if index > 0 {
+                        info!("🔀 主地址不可用，使用备用地址: {}", url);
+                    }
+                    return Ok((url.clone(), info, candidates[index + 1..].to_vec()));
+                }
+                // 风控由熔断器统一处理，不在这里换地址
+                Err(e @ DownloadError::RateLimited(_)) => return Err(e),
+                Err(e) => {
+                    debug!("候选地址不可用: {}, 错误: {}", url, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(last_error)
+    }
+
+    fn control(&self, task_id: &str) -> Result<TaskControl, DownloadError> {
+        self.controls
+            .get(task_id)
+            .map(|control| control.clone())
+            .ok_or_else(|| DownloadError::TaskNotFound(task_id.to_string()))
+    }
+
+    // 取消任务，已下载的部分文件会保留
+    pub async fn cancel(&self, task_id: &str) -> Result<(), DownloadError> {
+        self.control(task_id)?.cancel();
+        debug!("已请求取消任务: {}", task_id);
+        Ok(())
+    }
+
+    // 暂停任务：断开当前连接并保留部分文件，恢复后从断点继续
+    pub async fn pause(&self, task_id: &str) -> Result<(), DownloadError> {
+        self.control(task_id)?.pause();
+        debug!("已请求暂停任务: {}", task_id);
+        Ok(())
+    }
+
+    // 恢复已暂停的任务
+    pub async fn resume(&self, task_id: &str) -> Result<(), DownloadError> {
+        self.control(task_id)?.resume();
+        debug!("已请求恢复任务: {}", task_id);
+        Ok(())
+    }
+
+    // 暂停全部任务，并等待正在下载的任务停下（最多等待 `timeout`）
+    pub async fn pause_all(&self, timeout: std::time::Duration) {
+        for control in self.controls.iter() {
+            control.pause();
+        }
+
+        let deadline = tokio::time::Instant::now() + timeout;
+        while tokio::time::Instant::now() < deadline {
+            if !self.has_status(&TaskStatus::Downloading).await {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+    }
+
+    // 取消全部任务
+    pub fn cancel_all(&self) {
+        for control in self.controls.iter() {
+            control.cancel();
+        }
+    }
+
+    async fn has_status(&self, status: &TaskStatus) -> bool {
+        let task_locks: Vec<_> = {
+            let tasks = self.tasks.lock().await;
+            tasks.iter().map(|task| Arc::clone(task.value())).collect()
+        };
+        for task in task_locks {
+            if task.lock().await.status == *status {
+                return true;
+            }
+        }
+        false
+    }
+
+    // 将所有任务的状态和风控冷却期写入状态文件，供下次运行时查看和续传
+    pub async fn save_state(&self) -> Result<(), DownloadError> {
+        let task_locks: Vec<_> = {
+            let tasks = self.tasks.lock().await;
+            tasks.iter().map(|task| Arc::clone(task.value())).collect()
+        };
+        let mut snapshot = Vec::with_capacity(task_locks.len());
+        for task in task_locks {
+            snapshot.push(task.lock().await.clone());
+        }
+
+        let journal = StateJournal {
+            tasks: snapshot,
+            cool_down_until: self
+                .breaker
+                .open_until()
+                .and_then(|until| until.duration_since(UNIX_EPOCH).ok())
+                .map(|since_epoch| since_epoch.as_secs()),
+            breaker_trips: self.breaker.trips(),
+        };
+        let content = serde_json::to_vec_pretty(&journal)
+            .map_err(|e| DownloadError::IoError(e.to_string()))?;
+        tokio::fs::write(&self.state_file, content).await?;
+        debug!("已写入状态文件: {:?} ({} 个任务)", self.state_file, journal.tasks.len());
+        Ok(())
+    }
+
+    // 风控熔断器累计触发次数
+    pub fn breaker_trips(&self) -> u64 {
+        self.breaker.total_trips()
+    }
+
+    // 风控熔断期间暂停所有正在进行的任务，恢复时只恢复这些任务
+    //
+    // 控制句柄在任务结束时移除，这里只会涉及尚未结束的任务。
+    fn pause_for_breaker(&self) {
+        for control in self.controls.iter() {
+            if !control.is_paused() && !control.is_cancelled() {
+                control.pause();
+                self.breaker.track_paused(control.key().clone());
+            }
+        }
+    }
+
+    // 风控熔断后的恢复流程：冷却 -> 单次探测 -> 恢复全部任务，探测失败则加倍退避
+    async fn recover_from_risk_control(self, probe_url: String) {
+        self.pause_for_breaker();
+        loop {
+            let cool_down = self.breaker.remaining();
+            warn!(
+                "🧊 连续触发风控，暂停所有下载 {} 秒后探测",
+                cool_down.as_secs()
+            );
+            if let Err(e) = self.save_state().await {
+                warn!("保存风控冷却状态失败: {}", e);
+            }
+            tokio::time::sleep(cool_down).await;
+
+            if self.probe(&probe_url).await {
+                break;
+            }
+            self.breaker.reopen();
+        }
+
+        for task_id in self.breaker.close() {
+            if let Some(control) = self.controls.get(&task_id) {
+                control.resume();
+            }
+        }
+        if let Err(e) = self.save_state().await {
+            warn!("保存下载状态失败: {}", e);
+        }
+        info!("✅ 风控探测通过，恢复下载");
+    }
+
+    // 只请求一个字节，判断风控是否已解除
+    async fn probe(&self, url: &str) -> bool {
+        let mut builder = self
+            .download_client
+            .download
+            .get(url)
+            .header(reqwest::header::RANGE, "bytes=0-0");
+        for (key, value) in BiliClient::get_video_download_headers(url).iter() {
+            builder = builder.header(key, value);
+        }
+
+        match builder.send().await {
+            Ok(response) => match Self::check_response_status(&response, url) {
+                Ok(()) => true,
+                Err(e) => {
+                    debug!("风控探测失败: {}", e);
+                    false
+                }
+            },
+            Err(e) => {
+                debug!("风控探测请求失败: {}", e);
+                false
+            }
+        }
+    }
+
     // 获取任务状态
     pub async fn get_task_status(&self, task_id: &str) -> Option<TaskStatus> {
+        self.get_task_progress(task_id)
+            .await
+            .map(|progress| progress.status)
+    }
+
+    // 任务进度快照，任务不存在时返回 None
+    pub async fn get_task_progress(&self, task_id: &str) -> Option<DownloadProgress> {
         let task = {
             let tasks = self.tasks.lock().await;
-            let task = tasks.get(task_id).expect("任务不存在");
+            let task = tasks.get(task_id)?;
             Arc::clone(&task)
         };
 
-        Some(task.lock().await.status.clone())
+        Some(task.lock().await.clone())
     }
 
     async fn run(
@@ -140,6 +481,7 @@ impl DownloadCore {
         file_type: FileType,
         strategy: DownloadStrategy,
         content_info: DownloadContent,
+        ctx: TaskContext,
     ) {
         // 获取任务锁
         let task_lock = {
@@ -149,7 +491,7 @@ impl DownloadCore {
         };
 
         // 一次性获取所需数据
-        let (task_id, output_path, url) = {
+        let (task_id, output_path, mut url) = {
             let task_guard = task_lock.lock().await;
             (
                 task_guard.task_id.clone(),
@@ -160,46 +502,101 @@ impl DownloadCore {
 
         debug!("开始下载任务: {}, 类型: {:?}", task_id, file_type);
 
-        // 更新任务状态为下载中
-        {
-            let mut task_guard = task_lock.lock().await;
-            task_guard.status = TaskStatus::Downloading;
-        }
+        let mut started = false;
+        let result = loop {
+            // 风控熔断期间等待恢复，由第一个等待的任务负责探测
+            if ctx.core.breaker.is_open() {
+                task_lock.lock().await.status = TaskStatus::Paused;
+                ctx.emit(DownloadEvent::Paused {
+                    task_id: task_id.clone(),
+                });
+                if ctx.core.breaker.try_begin_recovery() {
+                    tokio::spawn(ctx.core.clone().recover_from_risk_control(url.clone()));
+                }
+                tokio::select! {
+                    _ = ctx.control.cancelled() => break Err(DownloadError::Cancelled),
+                    _ = ctx.core.breaker.wait_closed() => {}
+                }
+            }
 
-        // 根据下载策略执行不同的下载逻辑
-        let result = match strategy {
-            DownloadStrategy::BinaryStream {
-                show_progress,
-                chunk_size,
-            } => {
-                Self::download_binary_stream(
-                    &download_client,
-                    &url,
-                    &output_path,
-                    show_progress,
-                    chunk_size,
-                    &task_lock,
-                    &file_type,
-                )
-                .await
+            // 尚未开始前被暂停时，等待恢复
+            if ctx.control.is_paused() {
+                task_lock.lock().await.status = TaskStatus::Paused;
+                ctx.emit(DownloadEvent::Paused {
+                    task_id: task_id.clone(),
+                });
             }
-            DownloadStrategy::TextContent {
-                expected_content_type,
-            } => {
-                Self::download_text_content(
-                    &download_client,
-                    &url,
-                    &output_path,
-                    expected_content_type,
-                    &content_info,
-                )
-                .await
+            if let Err(e) = ctx.control.wait_if_paused().await {
+                break Err(e);
             }
-            DownloadStrategy::Image { validate_format } => {
-                Self::download_image(&download_client, &url, &output_path, validate_format).await
+
+            // 更新任务状态为下载中
+            task_lock.lock().await.status = TaskStatus::Downloading;
+            ctx.emit(if started {
+                DownloadEvent::Resumed {
+                    task_id: task_id.clone(),
+                }
+            } else {
+                DownloadEvent::Started {
+                    task_id: task_id.clone(),
+                }
+            });
+            started = true;
+
+            let result = Self::run_strategy(
+                &download_client,
+                &url,
+                &output_path,
+                &file_type,
+                strategy.clone(),
+                &content_info,
+                &task_lock,
+                &ctx,
+            )
+            .await;
+
+            match result {
+                Ok(()) => {
+                    ctx.core.breaker.record_success();
+                    break Ok(());
+                }
+                // 熔断器已打开：暂停等待恢复后重新排队，而不是直接跳过
+                Err(DownloadError::RateLimited(msg)) if ctx.core.breaker.record_rejection() => {
+                    warn!("⏸️ 风控熔断中，任务 {} 将在恢复后重试: {}", task_id, msg);
+                }
+                Err(
+                    e @ (DownloadError::Cancelled
+                    | DownloadError::Paused
+                    | DownloadError::RateLimited(_)),
+                ) => break Err(e),
+                // 当前地址失败，切换到下一个候选地址继续下载
+                Err(e) => {
+                    let next = {
+                        let mut task_guard = task_lock.lock().await;
+                        if task_guard.mirrors.is_empty() {
+                            None
+                        } else {
+                            let next = task_guard.mirrors.remove(0);
+                            task_guard.url = next.clone();
+                            Some(next)
+                        }
+                    };
+                    let Some(next) = next else {
+                        break Err(e);
+                    };
+                    warn!("🔀 下载地址失败 ({})，切换到备用地址: {}", e, next);
+                    ctx.emit(DownloadEvent::MirrorSwitch {
+                        task_id: task_id.clone(),
+                        from: std::mem::replace(&mut url, next.clone()),
+                        to: next,
+                    });
+                }
             }
         };
 
+        // 任务已结束，移除控制句柄，之后的暂停、恢复和熔断不再涉及该任务
+        ctx.core.controls.remove(&ctx.task_id);
+
         // 更新任务状态
         {
             let mut task_guard = task_lock.lock().await;
@@ -207,22 +604,93 @@ impl DownloadCore {
                 Ok(_) => {
                     task_guard.status = TaskStatus::Completed;
                     debug!("下载任务完成: {}", task_id);
+                    ctx.emit(DownloadEvent::Completed { task_id });
+                }
+                Err(DownloadError::Cancelled) => {
+                    task_guard.status = TaskStatus::Cancelled;
+                    info!("⏹️ 下载任务已取消: {}，已下载部分已保留", task_id);
+                    ctx.emit(DownloadEvent::Cancelled { task_id });
+                }
+                Err(DownloadError::Paused) => {
+                    task_guard.status = TaskStatus::Paused;
+                    info!("⏸️ 下载任务已暂停: {}", task_id);
+                    ctx.emit(DownloadEvent::Paused { task_id });
                 }
                 Err(DownloadError::RateLimited(msg)) => {
                     // 风控错误，跳过任务而不是失败
                     task_guard.status = TaskStatus::Skipped(msg.clone());
                     warn!("⏭️ 下载任务已跳过: {}, 原因: {}", task_id, msg);
                     info!("💡 提示: 这通常是临时的风控限制，建议稍后重试");
+                    ctx.emit(DownloadEvent::Skipped {
+                        task_id,
+                        reason: msg,
+                    });
                 }
                 Err(e) => {
                     task_guard.status = TaskStatus::Error(e.to_string());
                     error!("❌ 下载任务失败: {}, 错误: {}", task_id, e);
+                    ctx.emit(DownloadEvent::Failed {
+                        task_id,
+                        error: e.to_string(),
+                    });
                 }
             }
         }
     }
 
+    // 根据下载策略执行不同的下载逻辑
+    #[allow(clippy::too_many_arguments)]
+    async fn run_strategy(
+        download_client: &BiliClient,
+        url: &str,
+        output_path: &Path,
+        file_type: &FileType,
+        strategy: DownloadStrategy,
+        content_info: &DownloadContent,
+        task_lock: &Arc<Mutex<DownloadProgress>>,
+        ctx: &TaskContext,
+    ) -> Result<(), DownloadError> {
+        match strategy {
+            DownloadStrategy::BinaryStream {
+                show_progress,
+                chunk_size,
+            } => {
+                Self::download_binary_stream(
+                    download_client,
+                    url,
+                    output_path,
+                    show_progress,
+                    chunk_size,
+                    task_lock,
+                    file_type,
+                    content_info,
+                    ctx,
+                )
+                .await
+            }
+            DownloadStrategy::TextContent {
+                expected_content_type,
+            } => {
+                Self::download_text_content(
+                    download_client,
+                    url,
+                    output_path,
+                    expected_content_type,
+                    content_info,
+                )
+                .await
+            }
+            DownloadStrategy::Image { validate_format } => {
+                Self::download_image(download_client, url, output_path, validate_format).await
+            }
+        }
+    }
+
     // 二进制流下载方法（用于视频、音频等大文件）
+    //
+    // 数据先写入 `<输出文件>.part`，校验大小后再重命名为输出文件；
+    // 断点信息记录在 `.part.json` 中，资源变化时从头下载，完成后保留为已完成记录。
+    #[allow(clippy::too_many_arguments)]
     async fn download_binary_stream(
         download_client: &BiliClient,
         url: &str,
@@ -231,78 +699,97 @@ impl DownloadCore {
         _chunk_size: usize,
         task_lock: &Arc<Mutex<DownloadProgress>>,
         file_type: &FileType,
+        content_info: &DownloadContent,
+        ctx: &TaskContext,
     ) -> Result<(), DownloadError> {
         debug!("开始下载二进制文件: {}", url);
 
-        // 首先检查是否存在部分下载的文件
-        let mut start_pos = 0u64;
-        if output_path.exists() {
-            debug!("检测到部分下载文件: {:?}", output_path);
-            start_pos = tokio::fs::metadata(output_path)
-                .await
-                .map_err(|e| DownloadError::IoError(e.to_string()))?
-                .len();
-            if start_pos > 0 {
-                info!("检测到部分下载文件，从 {} 字节处继续下载", start_pos);
-            }
-        }
-
         // 获取文件总大小（从任务信息中获取，避免重复网络请求）
         let total_size = {
             let task_guard = task_lock.lock().await;
             task_guard.total_size
         };
 
-        // 如果文件已经完整下载，直接返回
-        if start_pos >= total_size && total_size > 0 {
-            info!("文件已完整下载，跳过: {}", output_path.display());
-            return Ok(());
-        }
+        let part_path = partial::part_path(output_path);
+        let meta = PartialMeta::new(
+            url,
+            total_size,
+            content_info.etag.clone(),
+            content_info.last_modified.clone(),
+        );
+        let saved = PartialMeta::load(output_path).await;
 
-        // 创建进度条
-        let pb = if show_progress && total_size > 0 {
-            let pb = ProgressBar::new(total_size);
-            pb.set_style(
-                indicatif::ProgressStyle::with_template(
-                    "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta}) {msg}",
-                )
-                .unwrap()
-                .progress_chars("#>-")
+        // 输出文件已存在时，只有已完成记录确认是同一资源才跳过，否则下载到 .part 后覆盖
+        if let Ok(metadata) = tokio::fs::metadata(output_path).await {
+            if total_size > 0
+                && metadata.len() == total_size
+                && saved.as_ref().is_some_and(|saved| saved.confirms(&meta))
+            {
+                info!("文件已完整下载，跳过: {}", output_path.display());
+                task_lock.lock().await.downloaded = total_size;
+                return Ok(());
+            }
+            warn!(
+                "输出文件已存在但无法确认与当前资源一致，将重新下载并覆盖: {}",
+                output_path.display()
             );
-            pb.set_position(start_pos);
-            Some(pb)
-        } else {
-            None
+        }
+
+        // 校验已有的部分文件，与当前资源一致时才继续下载
+        let mut start_pos = match (saved, tokio::fs::metadata(&part_path).await) {
+            (Some(saved), Ok(metadata))
+                if !saved.complete
+                    && saved.matches(&meta)
+                    && (total_size == 0 || metadata.len() <= total_size) =>
+            {
+                metadata.len()
+            }
+            (saved, part) => {
+                if saved.as_ref().is_some_and(|saved| !saved.complete) || part.is_ok() {
+                    warn!(
+                        "部分下载文件与当前资源不一致，从头下载: {}",
+                        part_path.display()
+                    );
+                }
+                partial::discard(output_path).await;
+                0
+            }
         };
+        if start_pos > 0 {
+            info!("检测到部分下载文件，从 {} 字节处继续下载", start_pos);
+        }
+        meta.save(output_path).await?;
+        let if_range = meta.if_range();
 
         // 使用重试机制下载
         const MAX_RETRIES: usize = 20; // 增加到20次重试
         const RETRY_DELAY_SECONDS: u64 = 2; // 减少延时到2秒
 
-        for attempt in 1..=MAX_RETRIES {
+        let mut attempt = 1;
+        while attempt <= MAX_RETRIES {
             match Self::download_with_resume(
                 download_client,
                 url,
-                output_path,
+                &part_path,
                 start_pos,
+                if_range,
                 total_size,
                 file_type,
-                &pb,
+                show_progress,
                 task_lock,
+                ctx,
             )
             .await
             {
                 Ok(_) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message("下载完成");
-                    }
+                    partial::finalize(output_path, &meta).await?;
                     info!("文件下载成功: {}", output_path.display());
                     return Ok(());
                 }
                 Err(DownloadError::StreamError(msg)) if attempt < MAX_RETRIES => {
                     // 计算下载进度
-                    let current_pos = if output_path.exists() {
-                        tokio::fs::metadata(output_path)
+                    let current_pos = if part_path.exists() {
+                        tokio::fs::metadata(&part_path)
                             .await
                             .map_err(|e| DownloadError::IoError(e.to_string()))?
                             .len()
@@ -330,16 +817,13 @@ impl DownloadCore {
                             start_pos as f64 / 1024.0 / 1024.0
                         );
 
-                        if let Some(pb) = &pb {
-                            pb.set_position(start_pos);
-                            pb.set_message(format!(
-                                "重试 {}/{} ({}%)",
-                                attempt + 1,
-                                MAX_RETRIES,
-                                progress_percent
-                            ));
-                        }
                     }
+                    ctx.emit(DownloadEvent::Retry {
+                        task_id: ctx.task_id.clone(),
+                        attempt: attempt + 1,
+                        max_retries: MAX_RETRIES,
+                        reason: msg,
+                    });
 
                     // 如果已经下载了相当一部分，减少延时
                     let delay = if progress_percent > 50 {
@@ -351,45 +835,60 @@ impl DownloadCore {
                     };
 
                     tokio::time::sleep(tokio::time::Duration::from_secs(delay)).await;
+                    attempt += 1;
+                }
+                Err(DownloadError::Paused) => {
+                    // 暂停：保留部分文件，恢复后从断点继续，不计入重试次数
+                    task_lock.lock().await.status = TaskStatus::Paused;
+                    ctx.emit(DownloadEvent::Paused {
+                        task_id: ctx.task_id.clone(),
+                    });
+                    info!("⏸️ 已暂停，部分文件已保留: {}", output_path.display());
+
+                    ctx.control.wait_if_paused().await?;
+
+                    task_lock.lock().await.status = TaskStatus::Downloading;
+                    ctx.emit(DownloadEvent::Resumed {
+                        task_id: ctx.task_id.clone(),
+                    });
+                    start_pos = match tokio::fs::metadata(&part_path).await {
+                        Ok(metadata) => metadata.len(),
+                        Err(_) => 0,
+                    };
+                    info!("▶️ 已恢复，从 {} 字节处继续下载", start_pos);
                 }
                 Err(DownloadError::RateLimited(_)) => {
                     // 风控错误，直接返回，不再重试
-                    if let Some(pb) = pb {
-                        pb.finish_with_message("访问受限");
-                    }
                     return Err(DownloadError::RateLimited(format!(
                         "下载过程中遇到访问限制，URL: {}",
                         url
                     )));
                 }
                 Err(e) => {
-                    if let Some(pb) = pb {
-                        pb.finish_with_message("下载失败");
-                    }
                     return Err(e);
                 }
             }
         }
 
-        if let Some(pb) = pb {
-            pb.finish_with_message("重试次数用尽");
-        }
         Err(DownloadError::StreamError(format!(
             "下载失败，已重试 {} 次",
             MAX_RETRIES
         )))
     }
 
-    // 带断点续传的下载方法
+    // 带断点续传的下载方法，`if_range` 为资源校验值，资源已变化时服务器返回完整内容
+    #[allow(clippy::too_many_arguments)]
     async fn download_with_resume(
         download_client: &BiliClient,
         url: &str,
         output_path: &Path,
-        start_pos: u64,
+        mut start_pos: u64,
+        if_range: Option<&str>,
         total_size: u64,
         file_type: &FileType,
-        pb: &Option<ProgressBar>,
+        show_progress: bool,
         task_lock: &Arc<Mutex<DownloadProgress>>,
+        ctx: &TaskContext,
     ) -> Result<(), DownloadError> {
         use futures::StreamExt;
         use tokio::io::AsyncWriteExt;
@@ -397,13 +896,13 @@ impl DownloadCore {
         // 对于音频和视频文件，构建带特殊请求头的请求
         let mut request_builder = match file_type {
             FileType::Video | FileType::Audio => {
-                let mut builder = download_client.inner.get(url);
+                let mut builder = download_client.download.get(url);
                 for (key, value) in BiliClient::get_video_download_headers(url).iter() {
                     builder = builder.header(key, value);
                 }
                 builder
             }
-            _ => download_client.inner.get(url),
+            _ => download_client.download.get(url),
         };
 
         // 对于B站的音频流，总是使用Range请求，因为某些音频流不支持完整GET请求
@@ -417,6 +916,11 @@ impl DownloadCore {
             };
 
             request_builder = request_builder.header(reqwest::header::RANGE, range_header.clone());
+            if start_pos > 0
+                && let Some(validator) = if_range
+            {
+                request_builder = request_builder.header(reqwest::header::IF_RANGE, validator);
+            }
 
             if start_pos > 0 {
                 debug!("使用断点续传，起始位置: {} 字节", start_pos);
@@ -428,10 +932,10 @@ impl DownloadCore {
             debug!("发送完整文件请求（无Range头）");
         }
 
-        let response = request_builder
-            .send()
-            .await
-            .map_err(DownloadError::HttpError)?;
+        let response = tokio::select! {
+            interrupt = ctx.control.interrupted() => return Err(interrupt.into()),
+            response = request_builder.send() => response.map_err(DownloadError::HttpError)?,
+        };
 
         // 记录详细的请求和响应信息
         debug!("下载请求详情:");
@@ -461,6 +965,15 @@ impl DownloadCore {
         // 检查响应状态
         Self::check_response_status(&response, url)?;
 
+        // 续传请求返回 200：服务器不支持 Range 或资源已变化，截断后从头写入
+        if start_pos > 0 && response.status() == reqwest::StatusCode::OK {
+            warn!(
+                "服务器未返回部分内容 (200)，丢弃已下载的 {:.1}MB 从头下载",
+                start_pos as f64 / 1024.0 / 1024.0
+            );
+            start_pos = 0;
+        }
+
         // 如果是206响应，解析Content-Range
         if response.status() == reqwest::StatusCode::PARTIAL_CONTENT {
             if let Some(content_range) = response.headers().get("content-range") {
@@ -497,30 +1010,43 @@ impl DownloadCore {
 
         let mut stream = response.bytes_stream();
         let mut downloaded = start_pos;
+        let mut meter = ProgressMeter::new(start_pos);
 
         // 设置下载超时和心跳检测
-        const CHUNK_TIMEOUT_SECONDS: u64 = 60; // 增加到60秒超时
+        // 与下载客户端的读取超时保持一致
+        let chunk_timeout = download_client.timeouts().read;
         const HEARTBEAT_INTERVAL: usize = 100; // 每100个chunk输出一次日志
         let mut chunk_count = 0;
 
         loop {
-            let chunk_result = tokio::time::timeout(
-                tokio::time::Duration::from_secs(CHUNK_TIMEOUT_SECONDS),
-                stream.next(),
-            )
-            .await;
+            let chunk_result = tokio::select! {
+                // 暂停或取消时断开连接，已写入的数据落盘后返回
+                interrupt = ctx.control.interrupted() => {
+                    file.flush()
+                        .await
+                        .map_err(|e| DownloadError::IoError(e.to_string()))?;
+                    task_lock.lock().await.downloaded = downloaded;
+                    debug!(
+                        "下载被中断 ({:?})，已下载 {:.1}MB",
+                        interrupt,
+                        downloaded as f64 / 1024.0 / 1024.0
+                    );
+                    return Err(interrupt.into());
+                }
+                result = tokio::time::timeout(chunk_timeout, stream.next()) => result,
+            };
 
             let chunk_option = match chunk_result {
                 Ok(opt) => opt,
                 Err(_) => {
                     warn!(
                         "下载超时 ({}秒)，已下载 {:.1}MB",
-                        CHUNK_TIMEOUT_SECONDS,
+                        chunk_timeout.as_secs(),
                         downloaded as f64 / 1024.0 / 1024.0
                     );
                     return Err(DownloadError::StreamError(format!(
                         "下载超时 ({}秒)，网络连接可能中断",
-                        CHUNK_TIMEOUT_SECONDS
+                        chunk_timeout.as_secs()
                     )));
                 }
             };
@@ -553,6 +1079,12 @@ impl DownloadCore {
             downloaded += chunk.len() as u64;
             chunk_count += 1;
 
+            // 限速：等待期间被暂停或取消时立即返回循环处理
+            tokio::select! {
+                _ = ctx.control.interrupted() => {}
+                _ = ctx.throttle(chunk.len() as u64) => {}
+            }
+
             // 定期输出下载状态（避免日志过多）
             if chunk_count % HEARTBEAT_INTERVAL == 0 {
                 debug!(
@@ -567,16 +1099,12 @@ impl DownloadCore {
                 );
             }
 
-            // 更新进度
-            if let Some(pb) = pb {
-                pb.set_position(downloaded);
-            }
-
-            // 更新任务进度（减少锁争用）
-            if chunk_count % 50 == 0 {
-                // 每50个chunk更新一次任务进度
-                let mut task_guard = task_lock.lock().await;
-                task_guard.downloaded = downloaded;
+            // 按固定间隔更新任务进度并发布进度事件（减少锁争用）
+            if let Some(event) = meter.tick(&ctx.task_id, downloaded, total_size) {
+                task_lock.lock().await.downloaded = downloaded;
+                if show_progress {
+                    ctx.emit(event);
+                }
             }
 
             // 检查是否下载完成
@@ -599,6 +1127,9 @@ impl DownloadCore {
             let mut task_guard = task_lock.lock().await;
             task_guard.downloaded = downloaded;
         }
+        if show_progress {
+            ctx.emit(meter.flush(&ctx.task_id, downloaded, total_size));
+        }
 
         // 验证下载完整性
         if total_size > 0 && downloaded < total_size {
@@ -643,7 +1174,7 @@ impl DownloadCore {
         debug!("开始下载文本内容: {}", url);
 
         let response = download_client
-            .get_raw_response(url)
+            .get_cdn_response(url)
             .await
             .map_err(|e| DownloadError::InvalidState(e.to_string()))?;
 
@@ -702,7 +1233,7 @@ impl DownloadCore {
         debug!("开始下载图片: {}", url);
 
         let response = download_client
-            .get_raw_response(url)
+            .get_cdn_response(url)
             .await
             .map_err(|e| DownloadError::InvalidState(e.to_string()))?;
 
@@ -895,6 +1426,38 @@ impl DownloadCore {
     }
 }
 
+// 运行中任务的上下文：控制句柄、事件通道、限速器与所属的下载核心
+struct TaskContext {
+    task_id: String,
+    control: TaskControl,
+    events: EventSender,
+    global_limit: RateLimiter,
+    task_limit: RateLimiter,
+    core: DownloadCore,
+}
+
+// 状态文件内容
+#[derive(Debug, Serialize, Deserialize)]
+struct StateJournal {
+    tasks: Vec<DownloadProgress>,
+    #[serde(default)]
+    cool_down_until: Option<u64>, // 风控冷却结束时间（Unix 秒）
+    #[serde(default)]
+    breaker_trips: u32,
+}
+
+impl TaskContext {
+    fn emit(&self, event: DownloadEvent) {
+        emit(&self.events, event);
+    }
+
+    // 同时受全局与单任务带宽限制
+    async fn throttle(&self, bytes: u64) {
+        self.global_limit.acquire(bytes).await;
+        self.task_limit.acquire(bytes).await;
+    }
+}
+
 // 下载策略，用于处理不同类型的下载内容
 #[derive(Debug, Clone)]
 pub enum DownloadStrategy {
@@ -944,18 +1507,28 @@ pub struct DownloadContent {
     pub content_type: String,
     pub content_length: Option<u64>,
     pub is_text: bool,
+    pub etag: Option<String>,          // 用于断点续传校验
+    pub last_modified: Option<String>, // 用于断点续传校验
+}
+
+// 读取字符串形式的响应头
+fn header_string(resp: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    resp.headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
 }
 
 // 获取内容信息（内容类型、大小等）
-async fn get_content_info(
+pub(crate) async fn get_content_info(
     client: &BiliClient,
     url: &str,
 ) -> Result<DownloadContent, DownloadError> {
     // 检查是否是B站的视频/音频流URL，如果是则使用专用请求头
-    let resp = if url.contains("bilivideo.com") || url.contains("bilivideo.cn") {
+    let resp = if cdn::is_bilibili_cdn(url) {
         debug!("检测到B站CDN地址，使用视频下载专用请求头");
         client
-            .inner
+            .download
             .head(url)
             .headers(BiliClient::get_video_download_headers(url))
             .send()
@@ -963,7 +1536,7 @@ async fn get_content_info(
             .map_err(DownloadError::HttpError)?
     } else {
         client
-            .inner
+            .download
             .head(url)
             .send()
             .await
@@ -1043,6 +1616,8 @@ async fn get_content_info(
         content_type,
         content_length,
         is_text,
+        etag: header_string(&resp, reqwest::header::ETAG),
+        last_modified: header_string(&resp, reqwest::header::LAST_MODIFIED),
     })
 }
 
@@ -1054,9 +1629,9 @@ async fn get_content_info_via_get(
     debug!("使用 GET 请求获取内容信息: {}", url);
 
     // 构建请求，使用Range头只请求前1024字节来获取头信息
-    let resp = if url.contains("bilivideo.com") || url.contains("bilivideo.cn") {
+    let resp = if cdn::is_bilibili_cdn(url) {
         client
-            .inner
+            .download
             .get(url)
             .headers(BiliClient::get_video_download_headers(url))
             .header(reqwest::header::RANGE, "bytes=0-1023") // 只请求前1KB
@@ -1065,7 +1640,7 @@ async fn get_content_info_via_get(
             .map_err(DownloadError::HttpError)?
     } else {
         client
-            .inner
+            .download
             .get(url)
             .header(reqwest::header::RANGE, "bytes=0-1023") // 只请求前1KB
             .send()
@@ -1138,5 +1713,135 @@ async fn get_content_info_via_get(
         content_type,
         content_length,
         is_text,
+        etag: header_string(&resp, reqwest::header::ETAG),
+        last_modified: header_string(&resp, reqwest::header::LAST_MODIFIED),
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::test_server::TestServer;
+
+    fn temp_output(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("bilidl-{}-{}.m4s", name, uuid::Uuid::new_v4()))
+    }
+
+    async fn wait_for_status(
+        core: &DownloadCore,
+        task_id: &str,
+        done: impl Fn(&TaskStatus) -> bool,
+    ) -> DownloadProgress {
+        tokio::time::timeout(Duration::from_secs(10), async {
+            loop {
+                let progress = core.get_task_progress(task_id).await.unwrap();
+                if done(&progress.status) {
+                    return progress;
+                }
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("等待任务状态超时")
+    }
+
+    #[tokio::test]
+    async fn test_controls_removed_after_task_ends() {
+        let server = TestServer::start(vec![7; 4096], 1024, Duration::ZERO).await;
+        let output = temp_output("controls");
+        let core = DownloadCore::new(2, output.with_extension("json"), &BiliClient::new());
+
+        let task_id = core
+            .add_task(
+                &server.url,
+                &[],
+                &output,
+                &FileType::Video,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        wait_for_status(&core, &task_id, |status| *status == TaskStatus::Completed).await;
+        assert!(core.controls.is_empty());
+        assert!(core.pause(&task_id).await.is_err());
+
+        partial::discard(&output).await;
+        let _ = tokio::fs::remove_file(&output).await;
+    }
+
+    async fn part_len(output: &Path) -> u64 {
+        tokio::fs::metadata(partial::part_path(output))
+            .await
+            .map(|metadata| metadata.len())
+            .unwrap_or(0)
+    }
+
+    // 开始一个慢速下载，等到 `.part` 中已有数据
+    async fn start_slow_download(server: &TestServer, output: &Path) -> (DownloadCore, String) {
+        let core = DownloadCore::new(2, output.with_extension("json"), &BiliClient::new());
+        let task_id = core
+            .add_task(
+                &server.url,
+                &[],
+                output,
+                &FileType::Video,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        tokio::time::timeout(Duration::from_secs(10), async {
+            while part_len(output).await < 8 * 1024 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("下载未开始");
+        (core, task_id)
+    }
+
+    #[tokio::test]
+    async fn test_pause_resume_continues_from_part() {
+        let body: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let server = TestServer::start(body.clone(), 1024, Duration::from_millis(5)).await;
+        let output = temp_output("pause");
+        let (core, task_id) = start_slow_download(&server, &output).await;
+
+        core.pause(&task_id).await.unwrap();
+        wait_for_status(&core, &task_id, |status| *status == TaskStatus::Paused).await;
+        let paused_at = part_len(&output).await;
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(part_len(&output).await, paused_at, "暂停后仍在写入");
+
+        // 恢复后用 Range 从 .part 的末尾继续下载
+        core.resume(&task_id).await.unwrap();
+        wait_for_status(&core, &task_id, |status| *status == TaskStatus::Completed).await;
+        assert_eq!(
+            server.ranges(),
+            [None, Some(format!("bytes={}-", paused_at))]
+        );
+        assert_eq!(tokio::fs::read(&output).await.unwrap(), body);
+
+        partial::discard(&output).await;
+        let _ = tokio::fs::remove_file(&output).await;
+    }
+
+    #[tokio::test]
+    async fn test_cancel_while_paused() {
+        let server = TestServer::start(vec![1; 64 * 1024], 1024, Duration::from_millis(5)).await;
+        let output = temp_output("cancel");
+        let (core, task_id) = start_slow_download(&server, &output).await;
+
+        core.pause(&task_id).await.unwrap();
+        wait_for_status(&core, &task_id, |status| *status == TaskStatus::Paused).await;
+        let paused_at = part_len(&output).await;
+
+        // 取消会唤醒等待恢复的任务，部分文件保留以便之后续传
+        core.cancel(&task_id).await.unwrap();
+        wait_for_status(&core, &task_id, |status| *status == TaskStatus::Cancelled).await;
+        assert_eq!(part_len(&output).await, paused_at);
+        assert!(PartialMeta::load(&output).await.is_some());
+        assert_eq!(server.ranges().len(), 1);
+
+        partial::discard(&output).await;
+    }
+}
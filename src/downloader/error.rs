@@ -28,6 +28,10 @@ pub enum DownloadError {
     SemaphoreError,
     /// 风控错误 (403 Forbidden)
     RateLimited(String),
+    /// 任务已暂停
+    Paused,
+    /// 任务已取消
+    Cancelled,
 }
 
 impl fmt::Display for DownloadError {
@@ -46,6 +50,8 @@ impl fmt::Display for DownloadError {
             DownloadError::MergeError(msg) => write!(f, "合并错误: {}", msg),
             DownloadError::StreamError(msg) => write!(f, "流错误: {}", msg),
             DownloadError::RateLimited(msg) => write!(f, "访问受限 (403 Forbidden): {}", msg),
+            DownloadError::Paused => write!(f, "任务已暂停"),
+            DownloadError::Cancelled => write!(f, "任务已取消"),
         }
     }
 }
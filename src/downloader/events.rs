@@ -0,0 +1,179 @@
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+use crate::downloader::models::FileType;
+
+/// 事件通道容量，订阅方处理过慢时会丢弃最旧的事件
+pub const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// 下载事件发送端
+pub type EventSender = broadcast::Sender<DownloadEvent>;
+
+/// 下载过程中发布的事件，CLI 进度条、MCP 服务等均基于该事件流渲染
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum DownloadEvent {
+    /// 任务已加入队列
+    TaskAdded {
+        task_id: String,
+        name: String,
+        file_type: FileType,
+        total_size: u64,
+    },
+    /// 任务开始下载
+    Started {
+        task_id: String,
+    },
+    /// 下载进度，速度单位为字节/秒
+    Progress {
+        task_id: String,
+        downloaded: u64,
+        total_size: u64,
+        speed: f64,
+        eta_secs: Option<u64>,
+    },
+    /// 下载中断后重试
+    Retry {
+        task_id: String,
+        attempt: usize,
+        max_retries: usize,
+        reason: String,
+    },
+    /// 切换到备用地址
+    MirrorSwitch {
+        task_id: String,
+        from: String,
+        to: String,
+    },
+    Paused {
+        task_id: String,
+    },
+    Resumed {
+        task_id: String,
+    },
+    Cancelled {
+        task_id: String,
+    },
+    Completed {
+        task_id: String,
+    },
+    Failed {
+        task_id: String,
+        error: String,
+    },
+    Skipped {
+        task_id: String,
+        reason: String,
+    },
+    /// 开始合并/封装一组任务
+    MergeStarted {
+        name: String,
+    },
+    /// 合并结束，失败时携带错误信息
+    MergeFinished {
+        name: String,
+        error: Option<String>,
+    },
+}
+
+impl DownloadEvent {
+    /// 事件所属的下载任务ID，合并事件返回 `None`
+    pub fn task_id(&self) -> Option<&str> {
+        match self {
+            Self::TaskAdded { task_id, .. }
+            | Self::Started { task_id }
+            | Self::Progress { task_id, .. }
+            | Self::Retry { task_id, .. }
+            | Self::MirrorSwitch { task_id, .. }
+            | Self::Paused { task_id }
+            | Self::Resumed { task_id }
+            | Self::Cancelled { task_id }
+            | Self::Completed { task_id }
+            | Self::Failed { task_id, .. }
+            | Self::Skipped { task_id, .. } => Some(task_id),
+            Self::MergeStarted { .. } | Self::MergeFinished { .. } => None,
+        }
+    }
+}
+
+/// 创建事件通道
+pub fn event_channel() -> EventSender {
+    broadcast::channel(EVENT_CHANNEL_CAPACITY).0
+}
+
+/// 发布事件；没有订阅方时直接丢弃
+pub fn emit(events: &EventSender, event: DownloadEvent) {
+    let _ = events.send(event);
+}
+
+/// 进度测速器：限制进度事件频率，并用指数滑动平均计算速度
+pub struct ProgressMeter {
+    interval: Duration,
+    last_emit: Instant,
+    last_bytes: u64,
+    speed: f64,
+}
+
+impl ProgressMeter {
+    pub fn new(start_pos: u64) -> Self {
+        Self {
+            interval: Duration::from_millis(200),
+            last_emit: Instant::now(),
+            last_bytes: start_pos,
+            speed: 0.0,
+        }
+    }
+
+    /// 距上次发布已超过间隔时返回进度事件
+    pub fn tick(
+        &mut self,
+        task_id: &str,
+        downloaded: u64,
+        total_size: u64,
+    ) -> Option<DownloadEvent> {
+        let elapsed = self.last_emit.elapsed();
+        if elapsed < self.interval {
+            return None;
+        }
+        Some(self.sample(task_id, downloaded, total_size, elapsed))
+    }
+
+    /// 立即生成进度事件（如下载结束时）
+    pub fn flush(&mut self, task_id: &str, downloaded: u64, total_size: u64) -> DownloadEvent {
+        let elapsed = self.last_emit.elapsed();
+        self.sample(task_id, downloaded, total_size, elapsed)
+    }
+
+    fn sample(
+        &mut self,
+        task_id: &str,
+        downloaded: u64,
+        total_size: u64,
+        elapsed: Duration,
+    ) -> DownloadEvent {
+        let secs = elapsed.as_secs_f64();
+        if secs > 0.0 {
+            let instant = downloaded.saturating_sub(self.last_bytes) as f64 / secs;
+            self.speed = if self.speed == 0.0 {
+                instant
+            } else {
+                self.speed * 0.7 + instant * 0.3
+            };
+        }
+        self.last_emit = Instant::now();
+        self.last_bytes = downloaded;
+
+        let eta_secs = (self.speed > 0.0 && total_size > downloaded)
+            .then(|| ((total_size - downloaded) as f64 / self.speed).ceil() as u64);
+
+        DownloadEvent::Progress {
+            task_id: task_id.to_string(),
+            downloaded,
+            total_size,
+            speed: self.speed,
+            eta_secs,
+        }
+    }
+}
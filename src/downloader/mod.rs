@@ -1,16 +1,30 @@
 use std::path::PathBuf;
 
 use core::DownloadCore;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
 use tracing::debug;
 
-use crate::Result;
 use crate::common::client::client::BiliClient;
-use crate::downloader::models::{DownloadTask, FileType, TaskStatus};
+use crate::downloader::error::DownloadError;
+use crate::downloader::models::DownloadTask;
+use crate::downloader::report::{DownloadReport, TaskOutcome, TaskReport};
 
+pub mod archive;
+pub mod breaker;
+pub mod cdn;
+pub mod control;
 pub mod core;
 pub mod error;
+pub mod events;
 pub mod models;
+pub mod partial;
+pub mod preflight;
+pub mod progress;
+pub mod rate_limit;
+pub mod report;
+#[cfg(test)]
+pub(crate) mod test_server;
 
 pub struct VideoDownloader {
     download_manager: DownloadCore,
@@ -23,57 +37,119 @@ impl VideoDownloader {
         }
     }
 
-    pub async fn download(&self, task: &mut Vec<DownloadTask>) -> Result<()> {
+    // 下载核心的句柄，可用于暂停、恢复、取消任务和保存状态
+    pub fn core(&self) -> DownloadCore {
+        self.download_manager.clone()
+    }
+
+    pub async fn download(&self, task: &mut Vec<DownloadTask>) -> DownloadReport {
+        self.download_with_cancel(task, &CancellationToken::new())
+            .await
+    }
+
+    // 下载一组任务并返回每个任务的结果，取消 `cancel` 会停止整个作业
+    //
+    // 被暂停的任务没有超时：作业会一直等到该任务恢复或被取消后才返回。
+    pub async fn download_with_cancel(
+        &self,
+        task: &mut Vec<DownloadTask>,
+        cancel: &CancellationToken,
+    ) -> DownloadReport {
         debug!("task: {:?}", task);
 
-        for t in task {
-            self.download_file(
-                t.url.clone(),
-                t.name.clone(),
-                t.output_path.clone(),
-                t.file_type.clone(),
-            )
-            .await?;
+        let started = Instant::now();
+        let trips_at_start = self.download_manager.breaker_trips();
+        let mut report = DownloadReport::default();
+        for t in task.iter() {
+            let task_report = if cancel.is_cancelled() {
+                TaskReport {
+                    name: t.name.clone(),
+                    output_path: t.output_path.clone(),
+                    file_type: t.file_type.clone(),
+                    outcome: TaskOutcome::Cancelled,
+                    bytes: 0,
+                    duration: Duration::ZERO,
+                }
+            } else {
+                self.download_file(t, cancel).await
+            };
+            report.tasks.push(task_report);
+        }
+
+        // 作业期间触发过风控熔断并已恢复时，重新下载之前被跳过的任务
+        if self.download_manager.breaker_trips() > trips_at_start {
+            let skipped: Vec<usize> = (0..report.tasks.len())
+                .filter(|&i| matches!(report.tasks[i].outcome, TaskOutcome::Skipped(_)))
+                .collect();
+            if !skipped.is_empty() {
+                crate::common::logger::PrettyLogger::info(format!(
+                    "风控已恢复，重新下载 {} 个被跳过的任务",
+                    skipped.len()
+                ));
+            }
+            for i in skipped {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                report.tasks[i] = self.download_file(&task[i], cancel).await;
+            }
         }
-        Ok(())
+        report.duration = started.elapsed();
+        report
     }
 
-    async fn download_file(
-        &self,
-        url: String,
-        name: String,
-        output_path: String,
-        file_type: FileType,
-    ) -> Result<()> {
+    async fn download_file(&self, task: &DownloadTask, cancel: &CancellationToken) -> TaskReport {
         crate::common::logger::PrettyLogger::separator();
-        crate::common::logger::PrettyLogger::info(format!("开始下载: {}", name));
+        crate::common::logger::PrettyLogger::info(format!("开始下载: {}", task.name));
 
-        let download_file_path = PathBuf::from(output_path);
+        let started = Instant::now();
+        let download_file_path = PathBuf::from(&task.output_path);
         // 确保输出目录存在
-        if let Some(parent) = download_file_path.parent() {
-            std::fs::create_dir_all(parent)?;
-        }
-        self.start_download(&url, &download_file_path, &file_type)
-            .await?;
+        let (outcome, bytes) = match download_file_path.parent().map(std::fs::create_dir_all) {
+            Some(Err(e)) => (TaskOutcome::Failed(format!("创建输出目录失败: {}", e)), 0),
+            _ => self.start_download(task, &download_file_path, cancel).await,
+        };
 
-        crate::common::logger::PrettyLogger::success(format!("下载完成: {}", download_file_path.display()));
-        Ok(())
+        if outcome == TaskOutcome::Completed {
+            crate::common::logger::PrettyLogger::success(format!("下载完成: {}", download_file_path.display()));
+        }
+        TaskReport {
+            name: task.name.clone(),
+            output_path: task.output_path.clone(),
+            file_type: task.file_type.clone(),
+            outcome,
+            bytes,
+            duration: started.elapsed(),
+        }
     }
 
-    async fn start_download(&self, url: &str, path: &PathBuf, file_type: &FileType) -> Result<()> {
-        let task_id = self.download_manager.add_task(url, path, file_type).await?;
+    // 等待任务结束，返回结果与已下载字节数；暂停中的任务会一直等待到恢复或取消
+    async fn start_download(
+        &self,
+        task: &DownloadTask,
+        path: &PathBuf,
+        cancel: &CancellationToken,
+    ) -> (TaskOutcome, u64) {
+        let task_id = match self
+            .download_manager
+            .add_task(&task.url, &task.mirrors, path, &task.file_type, cancel)
+            .await
+        {
+            Ok(task_id) => task_id,
+            Err(DownloadError::Cancelled) => return (TaskOutcome::Cancelled, 0),
+            // 获取内容信息时触发风控：记为跳过，熔断恢复后重新排队
+            Err(DownloadError::RateLimited(msg)) => return (TaskOutcome::Skipped(msg), 0),
+            Err(e) => return (TaskOutcome::Failed(e.to_string()), 0),
+        };
 
         loop {
-            let status = self.download_manager.get_task_status(&task_id).await;
-            if status == Some(TaskStatus::Completed) {
-                break;
+            let Some(progress) = self.download_manager.get_task_progress(&task_id).await else {
+                return (TaskOutcome::Failed("任务不存在".to_string()), 0);
+            };
+            if let Some(outcome) = TaskOutcome::from_status(&progress.status) {
+                return (outcome, progress.downloaded);
             }
             tokio::time::sleep(Duration::from_secs(1)).await;
         }
-
-        match self.download_manager.get_task_status(&task_id).await {
-            Some(TaskStatus::Completed) => Ok(()),
-            _ => Err("下载失败".into()),
-        }
     }
 }
@@ -14,6 +14,7 @@ pub struct DownloadTask {
     pub output_path: String,
     pub temp_path: String,
     pub metadata: HashMap<String, String>,
+    pub mirrors: Vec<String>, // 备用地址（接口返回的 backup_url）
 }
 
 impl DownloadTask {
@@ -32,9 +33,24 @@ impl DownloadTask {
             output_path,
             temp_path,
             metadata,
+            mirrors: Vec::new(),
         }
     }
 
+    pub fn with_mirrors(mut self, mirrors: Vec<String>) -> Self {
+        self.mirrors = mirrors;
+        self
+    }
+
+    /// 记录估算的文件大小，无法获取 Content-Length 时用于磁盘空间预检
+    pub fn with_estimated_size(mut self, size: u64) -> Self {
+        if size > 0 {
+            self.metadata
+                .insert("estimated_size".to_string(), size.to_string());
+        }
+        self
+    }
+
     pub fn get_output_path(&self) -> PathBuf {
         PathBuf::from(&self.output_path)
     }
@@ -52,8 +68,11 @@ pub enum TaskStatus {
     Failed,
     Error(String),
     Skipped(String), // 跳过任务，包含跳过原因
+    Paused,          // 已暂停，保留部分下载的文件
+    Cancelled,       // 已取消，保留部分下载的文件
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DownloadProgress {
     pub task_id: String,
     pub url: String,
@@ -61,6 +80,8 @@ pub struct DownloadProgress {
     pub total_size: u64,
     pub downloaded: u64,
     pub status: TaskStatus,
+    #[serde(default)]
+    pub mirrors: Vec<String>, // 当前地址失败时依次切换的候选地址
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Encode, Decode, PartialEq, Eq, Default)]
@@ -0,0 +1,185 @@
+use std::path::{Path, PathBuf};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+
+use super::error::DownloadError;
+
+/// 下载中的文件：`<输出文件>.part`
+pub fn part_path(output: &Path) -> PathBuf {
+    append_extension(output, "part")
+}
+
+/// 记录断点信息的附属文件：`<输出文件>.part.json`
+pub fn sidecar_path(output: &Path) -> PathBuf {
+    append_extension(output, "part.json")
+}
+
+fn append_extension(path: &Path, extension: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(".");
+    name.push(extension);
+    PathBuf::from(name)
+}
+
+/// 资源标识：去掉主机和签名参数后的路径
+///
+/// B站 CDN 地址的签名和过期参数每次解析都会变化，同一资源在不同镜像上路径相同。
+pub fn url_identity(url: &str) -> String {
+    Url::parse(url)
+        .map(|url| url.path().to_string())
+        .unwrap_or_else(|_| url.split('?').next().unwrap_or(url).to_string())
+}
+
+/// 部分下载文件的断点信息，用于判断能否从 `.part` 继续下载
+///
+/// 下载完成后保留为已完成记录，再次下载时据此确认已有的输出文件就是当前资源。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialMeta {
+    pub identity: String,
+    pub expected_size: u64,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub complete: bool, // 已下载完成并重命名为输出文件
+}
+
+impl PartialMeta {
+    pub fn new(
+        url: &str,
+        expected_size: u64,
+        etag: Option<String>,
+        last_modified: Option<String>,
+    ) -> Self {
+        Self {
+            identity: url_identity(url),
+            expected_size,
+            etag,
+            last_modified,
+            complete: false,
+        }
+    }
+
+    pub async fn load(output: &Path) -> Option<Self> {
+        let content = tokio::fs::read(sidecar_path(output)).await.ok()?;
+        serde_json::from_slice(&content).ok()
+    }
+
+    pub async fn save(&self, output: &Path) -> Result<(), DownloadError> {
+        let content =
+            serde_json::to_vec_pretty(self).map_err(|e| DownloadError::IoError(e.to_string()))?;
+        tokio::fs::write(sidecar_path(output), content)
+            .await
+            .map_err(|e| DownloadError::IoError(e.to_string()))
+    }
+
+    /// 已有的部分文件是否与当前要下载的资源一致
+    ///
+    /// 两边都有 ETag 或 Last-Modified 时必须相同；服务器未返回校验信息时只比较标识与大小。
+    pub fn matches(&self, current: &PartialMeta) -> bool {
+        fn same(a: &Option<String>, b: &Option<String>) -> bool {
+            match (a, b) {
+                (Some(a), Some(b)) => a == b,
+                _ => true,
+            }
+        }
+        self.identity == current.identity
+            && self.expected_size == current.expected_size
+            && same(&self.etag, &current.etag)
+            && same(&self.last_modified, &current.last_modified)
+    }
+
+    /// 已完成记录能否证明输出文件就是当前资源
+    ///
+    /// 与断点续传不同，缺少校验信息时无法确认，必须有相同的 ETag（都没有时比较 Last-Modified）。
+    pub fn confirms(&self, current: &PartialMeta) -> bool {
+        let same_version = match (&self.etag, &current.etag) {
+            (Some(a), Some(b)) => a == b,
+            (None, None) => {
+                self.last_modified.is_some() && self.last_modified == current.last_modified
+            }
+            _ => false,
+        };
+        self.complete
+            && self.identity == current.identity
+            && self.expected_size == current.expected_size
+            && same_version
+    }
+
+    /// `If-Range` 请求头：优先使用强 ETag，否则使用 Last-Modified
+    pub fn if_range(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// 删除部分文件及其断点信息
+pub async fn discard(output: &Path) {
+    let _ = tokio::fs::remove_file(part_path(output)).await;
+    let _ = tokio::fs::remove_file(sidecar_path(output)).await;
+}
+
+/// 下载完成后将 `.part` 重命名为最终文件（覆盖已有文件），并将断点信息改为已完成记录
+pub async fn finalize(output: &Path, meta: &PartialMeta) -> Result<(), DownloadError> {
+    tokio::fs::rename(part_path(output), output)
+        .await
+        .map_err(|e| DownloadError::IoError(format!("重命名下载文件失败: {}", e)))?;
+    let record = PartialMeta {
+        complete: true,
+        ..meta.clone()
+    };
+    record.save(output).await
+}
+
+/// 输出文件被移走或删除后，清理对应的已完成记录
+pub async fn forget(output: &Path) {
+    let _ = tokio::fs::remove_file(sidecar_path(output)).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partial_meta_matching() {
+        let output = Path::new("/tmp/video.mp4");
+        assert_eq!(part_path(output), PathBuf::from("/tmp/video.mp4.part"));
+
+        let saved = PartialMeta::new(
+            "https://upos-sz-mirrorali.bilivideo.com/upgcxcode/1-30080.m4s?deadline=1",
+            1024,
+            Some("\"abc\"".to_string()),
+            None,
+        );
+        let mirror = PartialMeta::new(
+            "https://cn-gddg-ct-01-01.bilivideo.com/upgcxcode/1-30080.m4s?deadline=2",
+            1024,
+            None,
+            Some("Mon, 01 Jan 2024 00:00:00 GMT".to_string()),
+        );
+        assert!(saved.matches(&mirror));
+        assert_eq!(saved.if_range(), Some("\"abc\""));
+
+        let changed = PartialMeta {
+            etag: Some("\"def\"".to_string()),
+            ..saved.clone()
+        };
+        assert!(!saved.matches(&changed));
+        assert!(!saved.matches(&PartialMeta {
+            expected_size: 2048,
+            ..saved.clone()
+        }));
+
+        // 已完成记录必须有相同的 ETag 才能确认输出文件
+        let finished = PartialMeta {
+            complete: true,
+            ..saved.clone()
+        };
+        assert!(!saved.confirms(&saved));
+        assert!(finished.confirms(&saved));
+        assert!(!finished.confirms(&mirror));
+        assert!(!finished.confirms(&changed));
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use futures::StreamExt;
+use tracing::debug;
+
+use super::core::get_content_info;
+use super::error::DownloadError;
+use super::models::{DownloadTask, FileType};
+use super::partial::{self, PartialMeta};
+use crate::common::client::client::BiliClient;
+
+/// 同时获取文件大小的请求数
+const MAX_CONCURRENT_HEADS: usize = 4;
+/// 弹幕、封面等小文件的预留空间
+const SMALL_FILE_RESERVE: u64 = 2 * 1024 * 1024;
+
+/// 整个作业需要的磁盘空间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpaceEstimate {
+    pub download: u64,    // 还需下载的字节数（已扣除已下载的部分）
+    pub headroom: u64,    // 合并/拼接/转码时输出文件额外占用的空间
+    pub estimated: usize, // 只能按码率估算大小的文件数
+    pub unknown: usize,   // 无法得知大小的文件数
+}
+
+/// 空间检查结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceVerdict {
+    Enough,
+    /// 足够下载，但合并时可能空间不足
+    TightForMerge,
+    /// 连下载都不够
+    Insufficient,
+}
+
+impl SpaceEstimate {
+    pub fn total(&self) -> u64 {
+        self.download.saturating_add(self.headroom)
+    }
+
+    pub fn verdict(&self, available: u64) -> SpaceVerdict {
+        if available < self.download {
+            SpaceVerdict::Insufficient
+        } else if available < self.total() {
+            SpaceVerdict::TightForMerge
+        } else {
+            SpaceVerdict::Enough
+        }
+    }
+}
+
+/// 估算一组任务需要的磁盘空间
+///
+/// 音视频优先使用 CDN 返回的 Content-Length，其次是接口给出的分段大小，最后按码率×时长估算。
+/// `post_process` 为真时（合并音视频、仅音频转码）额外预留与媒体文件等大的空间。
+pub async fn estimate_space(
+    client: &BiliClient,
+    tasks: &[DownloadTask],
+    post_process: bool,
+) -> SpaceEstimate {
+    let sizes: Vec<(&DownloadTask, Option<(u64, bool)>)> = futures::stream::iter(tasks)
+        .map(|task| async move { (task, task_size(client, task).await) })
+        .buffered(MAX_CONCURRENT_HEADS)
+        .collect()
+        .await;
+
+    let mut estimate = SpaceEstimate::default();
+    let mut media = 0u64;
+    let mut segmented = false;
+    for (task, size) in sizes {
+        let Some((size, exact)) = size else {
+            estimate.unknown += 1;
+            continue;
+        };
+        if !exact {
+            estimate.estimated += 1;
+        }
+        if matches!(task.file_type, FileType::Video | FileType::Audio) {
+            media = media.saturating_add(size);
+        }
+        segmented |= task
+            .metadata
+            .get("segment_count")
+            .is_some_and(|count| count != "1");
+        let present = present_bytes(Path::new(&task.output_path)).await;
+        estimate.download = estimate
+            .download
+            .saturating_add(size.saturating_sub(present));
+    }
+    if post_process || segmented {
+        estimate.headroom = media;
+    }
+    estimate
+}
+
+/// 目标目录所在文件系统的可用空间；目录尚未创建时检查最近的已存在上级目录
+pub fn available_space(dir: &Path) -> Result<u64, DownloadError> {
+    let existing = dir
+        .ancestors()
+        .find(|path| path.exists())
+        .unwrap_or(Path::new("."));
+    fs4::available_space(existing)
+        .map_err(|e| DownloadError::IoError(format!("获取磁盘可用空间失败: {}", e)))
+}
+
+// 单个任务的大小与是否为准确值，无法得知时返回 None
+async fn task_size(client: &BiliClient, task: &DownloadTask) -> Option<(u64, bool)> {
+    let metadata_number = |key: &str| -> Option<u64> {
+        task.metadata
+            .get(key)
+            .and_then(|v| v.parse().ok())
+            .filter(|&v| v > 0)
+    };
+
+    if let Some(size) = metadata_number("expected_size") {
+        return Some((size, true));
+    }
+    if !matches!(task.file_type, FileType::Video | FileType::Audio) {
+        return Some((SMALL_FILE_RESERVE, false));
+    }
+    for url in std::iter::once(&task.url)
+        .chain(task.mirrors.iter())
+        .take(2)
+    {
+        match get_content_info(client, url).await {
+            Ok(info) => {
+                if let Some(length) = info.content_length.filter(|&len| len > 0) {
+                    return Some((length, true));
+                }
+            }
+            Err(e) => debug!("获取文件大小失败: {} ({})", url, e),
+        }
+    }
+    metadata_number("estimated_size").map(|size| (size, false))
+}
+
+// 已下载到本地的字节数：有已完成记录的输出文件，否则为 `.part` 的长度
+//
+// 没有已完成记录的输出文件会被重新下载并覆盖，不计入已下载的部分。
+async fn present_bytes(output: &Path) -> u64 {
+    if PartialMeta::load(output).await.is_some_and(|meta| meta.complete)
+        && let Ok(metadata) = tokio::fs::metadata(output).await
+    {
+        return metadata.len();
+    }
+    tokio::fs::metadata(partial::part_path(output))
+        .await
+        .map(|metadata| metadata.len())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_space_verdict() {
+        let estimate = SpaceEstimate {
+            download: 800,
+            headroom: 700,
+            ..Default::default()
+        };
+        assert_eq!(estimate.total(), 1500);
+        assert_eq!(estimate.verdict(2000), SpaceVerdict::Enough);
+        assert_eq!(estimate.verdict(1000), SpaceVerdict::TightForMerge);
+        assert_eq!(estimate.verdict(500), SpaceVerdict::Insufficient);
+    }
+
+    #[tokio::test]
+    async fn test_present_bytes() {
+        let output =
+            std::env::temp_dir().join(format!("bilidl-preflight-{}.m4s", uuid::Uuid::new_v4()));
+        tokio::fs::write(&output, vec![0; 100]).await.unwrap();
+        tokio::fs::write(partial::part_path(&output), vec![0; 30])
+            .await
+            .unwrap();
+
+        // 没有已完成记录的输出文件会被重新下载，只计入 .part
+        assert_eq!(present_bytes(&output).await, 30);
+        let meta = PartialMeta::new("https://example.com/a.m4s", 100, None, None);
+        meta.save(&output).await.unwrap();
+        assert_eq!(present_bytes(&output).await, 30);
+
+        let finished = PartialMeta {
+            complete: true,
+            ..meta
+        };
+        finished.save(&output).await.unwrap();
+        assert_eq!(present_bytes(&output).await, 100);
+
+        partial::discard(&output).await;
+        let _ = tokio::fs::remove_file(&output).await;
+    }
+}
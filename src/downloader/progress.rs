@@ -1,25 +1,227 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::task::JoinHandle;
+
+use super::events::DownloadEvent;
+use super::models::FileType;
+use crate::common::logger::PrettyLogger;
+use crate::common::utils::FormatTool;
 
+// 文件名在进度行中的最大显示宽度（字符数）
+const NAME_WIDTH: usize = 24;
+
+/// 多任务下载面板：顶部为整体进度，下方每个下载中的文件占一行
+///
+/// 面板存在期间 `PrettyLogger` 与 tracing 日志会输出到进度条上方。
 pub struct DonwloadProgress {
     multi_pb: MultiProgress,
     main_pb: ProgressBar,
-    chunk_pbs: Vec<ProgressBar>,
+    tasks: HashMap<String, TaskLine>,
 }
 
+// 单个文件任务的进度
+struct TaskLine {
+    name: String,
+    total_size: u64,
+    downloaded: u64,
+    speed: f64,
+    pb: Option<ProgressBar>,
+}
 
 impl DonwloadProgress {
-    pub fn new(total_size: u64, chunks: usize) -> Self {
+    pub fn new() -> Self {
         let multi_pb = MultiProgress::new();
-        let main_pb = multi_pb.add(ProgressBar::new(total_size));
-        main_pb.set_style(ProgressStyle::default_bar()
-            .template("{msg} [{elapsed_precise}] {wide_bar} {bytes}/{total_bytes} ({eta})")
-            .unwrap());
-        
-        let chunk_pbs = (0..chunks)
-            .map(|_| multi_pb.add(ProgressBar::new(0)))
-            .collect();
-
-        Self { multi_pb, main_pb, chunk_pbs }
-        
-    }
-}
\ No newline at end of file
+        let main_pb = multi_pb.add(ProgressBar::new(0));
+        main_pb.set_style(
+            ProgressStyle::with_template(
+                "{prefix:.bold} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {msg}",
+            )
+            .unwrap()
+            .progress_chars("#>-"),
+        );
+        main_pb.set_prefix("总进度");
+        main_pb.enable_steady_tick(Duration::from_millis(500));
+        PrettyLogger::attach_progress(multi_pb.clone());
+
+        Self {
+            multi_pb,
+            main_pb,
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// 根据下载事件更新面板
+    pub fn handle(&mut self, event: DownloadEvent) {
+        match event {
+            DownloadEvent::TaskAdded {
+                task_id,
+                name,
+                file_type: FileType::Video | FileType::Audio,
+                total_size,
+            } if total_size > 0 => {
+                self.tasks.insert(
+                    task_id,
+                    TaskLine {
+                        name,
+                        total_size,
+                        downloaded: 0,
+                        speed: 0.0,
+                        pb: None,
+                    },
+                );
+            }
+            DownloadEvent::Started { task_id } => {
+                if let Some(task) = self.tasks.get_mut(&task_id) {
+                    let pb = self.multi_pb.add(ProgressBar::new(task.total_size));
+                    pb.set_style(
+                        ProgressStyle::with_template(
+                            "{spinner:.green} {prefix} [{bar:30.cyan/blue}] {bytes}/{total_bytes} {msg}",
+                        )
+                        .unwrap()
+                        .progress_chars("#>-"),
+                    );
+                    pb.set_prefix(truncate_name(&task.name));
+                    pb.set_position(task.downloaded);
+                    task.pb = Some(pb);
+                }
+            }
+            DownloadEvent::Progress {
+                task_id,
+                downloaded,
+                speed,
+                ..
+            } => {
+                if let Some(task) = self.tasks.get_mut(&task_id) {
+                    task.downloaded = downloaded;
+                    task.speed = speed;
+                    if let Some(pb) = &task.pb {
+                        pb.set_position(downloaded);
+                        pb.set_message(FormatTool::format_speed(speed as u64));
+                    }
+                }
+            }
+            DownloadEvent::Retry {
+                task_id,
+                attempt,
+                max_retries,
+                ..
+            } => self.set_message(&task_id, format!("重试 {}/{}", attempt, max_retries)),
+            DownloadEvent::Paused { task_id } => {
+                self.set_message(&task_id, "已暂停".to_string());
+                if let Some(task) = self.tasks.get_mut(&task_id) {
+                    task.speed = 0.0;
+                }
+            }
+            DownloadEvent::Resumed { task_id } => self.set_message(&task_id, String::new()),
+            DownloadEvent::Completed { task_id } => {
+                if let Some(task) = self.tasks.get_mut(&task_id) {
+                    task.downloaded = task.total_size;
+                }
+                self.finish_task(&task_id, None);
+            }
+            DownloadEvent::Cancelled { task_id } => self.finish_task(&task_id, Some("已取消")),
+            DownloadEvent::Skipped { task_id, .. } => self.finish_task(&task_id, Some("访问受限")),
+            DownloadEvent::Failed { task_id, .. } => self.finish_task(&task_id, Some("下载失败")),
+            DownloadEvent::MergeStarted { name } => {
+                self.main_pb.set_message(format!("正在合并: {}", name));
+                return;
+            }
+            _ => {}
+        }
+        self.refresh_overall();
+    }
+
+    /// 结束面板，恢复日志的直接输出
+    pub fn finish(self) {
+        self.refresh_overall();
+        self.main_pb.finish();
+        PrettyLogger::detach_progress();
+    }
+
+    fn set_message(&self, task_id: &str, message: String) {
+        if let Some(pb) = self.tasks.get(task_id).and_then(|task| task.pb.as_ref()) {
+            pb.set_message(message);
+        }
+    }
+
+    // 结束单个文件的进度行；失败等情况在日志区保留一行记录
+    fn finish_task(&mut self, task_id: &str, reason: Option<&str>) {
+        let Some(task) = self.tasks.get_mut(task_id) else {
+            return;
+        };
+        task.speed = 0.0;
+        if let Some(pb) = task.pb.take() {
+            pb.finish_and_clear();
+            self.multi_pb.remove(&pb);
+        }
+        if let Some(reason) = reason {
+            PrettyLogger::warning(format!("{}: {}", task.name, reason));
+        }
+    }
+
+    // 汇总所有文件的字节数、速度与剩余时间
+    fn refresh_overall(&self) {
+        let total: u64 = self.tasks.values().map(|task| task.total_size).sum();
+        let downloaded: u64 = self.tasks.values().map(|task| task.downloaded).sum();
+        let speed: f64 = self.tasks.values().map(|task| task.speed).sum();
+        let active = self.tasks.values().filter(|task| task.pb.is_some()).count();
+
+        self.main_pb.set_length(total);
+        self.main_pb.set_position(downloaded);
+
+        let eta = if speed > 0.0 && total > downloaded {
+            format_eta(((total - downloaded) as f64 / speed).ceil() as u64)
+        } else {
+            "--:--".to_string()
+        };
+        self.main_pb.set_message(format!(
+            "{}/{} {} 剩余 {} ({} 个文件下载中)",
+            FormatTool::format_size(downloaded),
+            FormatTool::format_size(total),
+            FormatTool::format_speed(speed as u64),
+            eta,
+            active
+        ));
+    }
+}
+
+impl Default for DonwloadProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn truncate_name(name: &str) -> String {
+    if name.chars().count() <= NAME_WIDTH {
+        return format!("{:<width$}", name, width = NAME_WIDTH);
+    }
+    let head: String = name.chars().take(NAME_WIDTH - 1).collect();
+    format!("{}…", head)
+}
+
+fn format_eta(secs: u64) -> String {
+    let (hours, mins, secs) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, mins, secs)
+    } else {
+        format!("{:02}:{:02}", mins, secs)
+    }
+}
+
+/// 基于下载事件渲染命令行下载面板，事件通道关闭后结束
+pub fn spawn_progress_renderer(mut events: broadcast::Receiver<DownloadEvent>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut dashboard = DonwloadProgress::new();
+        loop {
+            match events.recv().await {
+                Ok(event) => dashboard.handle(event),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => break,
+            }
+        }
+        dashboard.finish();
+    })
+}
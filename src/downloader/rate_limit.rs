@@ -0,0 +1,206 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::{Local, NaiveTime};
+
+/// 带宽限制配置，速率单位为字节/秒，`0` 表示不限速
+#[derive(Debug, Clone, Default)]
+pub struct RateLimitConfig {
+    pub global: u64,                  // 所有下载共享的上限
+    pub per_task: u64,                // 单个文件的上限
+    pub schedules: Vec<RateSchedule>, // 分时段的全局上限
+}
+
+/// 分时段限速规则，如 `01:00-07:00=0` 表示凌晨不限速
+///
+/// 结束时间早于开始时间时视为跨越午夜。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateSchedule {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+    pub rate: u64,
+}
+
+impl RateSchedule {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+impl FromStr for RateSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (range, rate) = s
+            .split_once('=')
+            .ok_or_else(|| format!("限速时段格式应为 HH:MM-HH:MM=速率: {}", s))?;
+        let (start, end) = range
+            .split_once('-')
+            .ok_or_else(|| format!("限速时段缺少结束时间: {}", s))?;
+        let parse_time = |text: &str| {
+            NaiveTime::parse_from_str(text.trim(), "%H:%M")
+                .map_err(|_| format!("无效的时间: {}", text))
+        };
+
+        Ok(Self {
+            start: parse_time(start)?,
+            end: parse_time(end)?,
+            rate: parse_rate(rate)?,
+        })
+    }
+}
+
+impl fmt::Display for RateSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}={}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M"),
+            self.rate
+        )
+    }
+}
+
+/// 解析速率，如 `500K`、`5M`、`1.5M`（按 1024 进制），`0`/`unlimited` 表示不限速
+pub fn parse_rate(text: &str) -> Result<u64, String> {
+    let value = text.trim();
+    if value.eq_ignore_ascii_case("unlimited") || value.eq_ignore_ascii_case("none") {
+        return Ok(0);
+    }
+
+    let value = value.trim_end_matches("/s").trim_end_matches(['B', 'b']);
+    let (number, unit) = match value.chars().last().map(|c| c.to_ascii_uppercase()) {
+        Some('K') => (&value[..value.len() - 1], 1024f64),
+        Some('M') => (&value[..value.len() - 1], 1024f64.powi(2)),
+        Some('G') => (&value[..value.len() - 1], 1024f64.powi(3)),
+        _ => (value, 1f64),
+    };
+    number
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|n| n.is_finite() && *n >= 0.0)
+        .map(|n| (n * unit) as u64)
+        .ok_or_else(|| format!("无效的速率: {}", text))
+}
+
+/// 令牌桶限速器，克隆后共享同一个令牌桶
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    rate: Arc<AtomicU64>,
+    schedules: Arc<RwLock<Vec<RateSchedule>>>,
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(rate: u64) -> Self {
+        Self {
+            rate: Arc::new(AtomicU64::new(rate)),
+            schedules: Arc::new(RwLock::new(Vec::new())),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: 0.0,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    /// 共享速率设置、但拥有独立令牌桶的限速器，用于单任务限速
+    pub fn fork(&self) -> Self {
+        Self {
+            rate: self.rate.clone(),
+            schedules: self.schedules.clone(),
+            bucket: Arc::new(Mutex::new(Bucket {
+                tokens: 0.0,
+                last_refill: Instant::now(),
+            })),
+        }
+    }
+
+    pub fn rate(&self) -> u64 {
+        self.rate.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rate(&self, rate: u64) {
+        self.rate.store(rate, Ordering::Relaxed);
+    }
+
+    pub fn schedules(&self) -> Vec<RateSchedule> {
+        self.schedules.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    pub fn set_schedules(&self, schedules: Vec<RateSchedule>) {
+        if let Ok(mut current) = self.schedules.write() {
+            *current = schedules;
+        }
+    }
+
+    /// 当前生效的速率：命中的时段规则优先，否则为基础速率
+    pub fn effective_rate(&self) -> u64 {
+        let now = Local::now().time();
+        self.schedules
+            .read()
+            .ok()
+            .and_then(|schedules| schedules.iter().find(|s| s.contains(now)).map(|s| s.rate))
+            .unwrap_or_else(|| self.rate())
+    }
+
+    /// 消耗 `bytes` 个令牌，令牌不足时等待
+    pub async fn acquire(&self, bytes: u64) {
+        let rate = self.effective_rate();
+        let wait = {
+            let Ok(mut bucket) = self.bucket.lock() else {
+                return;
+            };
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.last_refill = now;
+            if rate == 0 {
+                bucket.tokens = 0.0;
+                return;
+            }
+
+            // 最多积累 1 秒的突发流量；令牌可以透支，透支部分通过等待偿还
+            let rate = rate as f64;
+            bucket.tokens = (bucket.tokens + elapsed * rate).min(rate) - bytes as f64;
+            (bucket.tokens < 0.0).then(|| Duration::from_secs_f64(-bucket.tokens / rate))
+        };
+
+        if let Some(wait) = wait {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_rate_and_schedule() {
+        assert_eq!(parse_rate("5M"), Ok(5 * 1024 * 1024));
+        assert_eq!(parse_rate("500k"), Ok(500 * 1024));
+        assert_eq!(parse_rate("1.5MB/s"), Ok(1536 * 1024));
+        assert_eq!(parse_rate("unlimited"), Ok(0));
+        assert!(parse_rate("fast").is_err());
+
+        let night: RateSchedule = "23:00-07:00=0".parse().unwrap();
+        assert!(night.contains(NaiveTime::from_hms_opt(1, 0, 0).unwrap()));
+        assert!(!night.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+        assert_eq!(night.to_string(), "23:00-07:00=0");
+        assert!("07:00=1M".parse::<RateSchedule>().is_err());
+    }
+}
@@ -0,0 +1,191 @@
+use std::time::Duration;
+
+use serde::Serialize;
+
+use super::models::{FileType, TaskStatus};
+use crate::common::logger::PrettyLogger;
+use crate::common::utils::FormatTool;
+
+/// 单个下载任务的最终结果
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "outcome", content = "reason", rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Completed,
+    Failed(String),
+    Skipped(String), // 风控等原因跳过
+    Cancelled,
+}
+
+impl TaskOutcome {
+    /// 由任务的终止状态得到结果；任务仍在进行时返回 `None`
+    pub fn from_status(status: &TaskStatus) -> Option<Self> {
+        match status {
+            TaskStatus::Completed => Some(Self::Completed),
+            TaskStatus::Failed => Some(Self::Failed("下载失败".to_string())),
+            TaskStatus::Error(e) => Some(Self::Failed(e.clone())),
+            TaskStatus::Skipped(reason) => Some(Self::Skipped(reason.clone())),
+            TaskStatus::Cancelled => Some(Self::Cancelled),
+            TaskStatus::Queued | TaskStatus::Downloading | TaskStatus::Paused => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskReport {
+    pub name: String,
+    pub output_path: String,
+    pub file_type: FileType,
+    pub outcome: TaskOutcome,
+    pub bytes: u64,
+    pub duration: Duration,
+}
+
+/// 一次下载作业的报告
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DownloadReport {
+    pub tasks: Vec<TaskReport>,
+    pub duration: Duration,
+}
+
+impl DownloadReport {
+    pub fn completed(&self) -> impl Iterator<Item = &TaskReport> {
+        self.tasks
+            .iter()
+            .filter(|task| task.outcome == TaskOutcome::Completed)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &TaskReport> {
+        self.tasks
+            .iter()
+            .filter(|task| matches!(task.outcome, TaskOutcome::Failed(_)))
+    }
+
+    pub fn skipped(&self) -> impl Iterator<Item = &TaskReport> {
+        self.tasks
+            .iter()
+            .filter(|task| matches!(task.outcome, TaskOutcome::Skipped(_)))
+    }
+
+    pub fn cancelled(&self) -> impl Iterator<Item = &TaskReport> {
+        self.tasks
+            .iter()
+            .filter(|task| task.outcome == TaskOutcome::Cancelled)
+    }
+
+    /// 合并另一次作业的报告，用于批量下载的汇总
+    pub fn merge(&mut self, other: DownloadReport) {
+        self.tasks.extend(other.tasks);
+        self.duration += other.duration;
+    }
+
+    /// 指定输出路径的任务是否已下载完成
+    pub fn is_completed(&self, output_path: &str) -> bool {
+        self.completed().any(|task| task.output_path == output_path)
+    }
+
+    /// 所有任务均下载完成
+    pub fn is_success(&self) -> bool {
+        self.tasks
+            .iter()
+            .all(|task| task.outcome == TaskOutcome::Completed)
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.tasks.iter().map(|task| task.bytes).sum()
+    }
+
+    /// 在终端输出下载报告
+    pub fn print(&self) {
+        PrettyLogger::title("下载报告");
+        PrettyLogger::info(format!(
+            "完成 {} 个，失败 {} 个，跳过 {} 个，取消 {} 个，共 {}，耗时 {:.1} 秒",
+            self.completed().count(),
+            self.failed().count(),
+            self.skipped().count(),
+            self.cancelled().count(),
+            FormatTool::format_size(self.total_bytes()),
+            self.duration.as_secs_f64()
+        ));
+        for task in &self.tasks {
+            match &task.outcome {
+                TaskOutcome::Completed => {}
+                TaskOutcome::Failed(reason) => {
+                    PrettyLogger::error(format!("失败: {} - {}", task.name, reason))
+                }
+                TaskOutcome::Skipped(reason) => {
+                    PrettyLogger::warning(format!("风控跳过: {} - {}", task.name, reason))
+                }
+                TaskOutcome::Cancelled => PrettyLogger::warning(format!("已取消: {}", task.name)),
+            }
+        }
+        if self.skipped().next().is_some() {
+            PrettyLogger::info("被跳过的任务通常是临时风控限制，稍后重新运行即可继续下载");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(name: &str, outcome: TaskOutcome, bytes: u64) -> TaskReport {
+        TaskReport {
+            name: name.to_string(),
+            output_path: format!("{}.m4s", name),
+            file_type: FileType::Video,
+            outcome,
+            bytes,
+            duration: Duration::from_secs(1),
+        }
+    }
+
+    fn report(tasks: Vec<TaskReport>) -> DownloadReport {
+        DownloadReport {
+            tasks,
+            duration: Duration::from_secs(2),
+        }
+    }
+
+    #[test]
+    fn test_report_classification() {
+        // 没有任务时视为成功
+        assert!(DownloadReport::default().is_success());
+
+        let mut batch = report(vec![
+            task("a", TaskOutcome::Completed, 100),
+            task("b", TaskOutcome::Completed, 50),
+        ]);
+        assert!(batch.is_success());
+        assert!(batch.is_completed("a.m4s"));
+        assert_eq!(batch.failed().count(), 0);
+
+        // 跳过和取消不算失败，但作业不再成功
+        batch.merge(report(vec![
+            task("c", TaskOutcome::Skipped("风控".to_string()), 0),
+            task("d", TaskOutcome::Cancelled, 10),
+        ]));
+        assert!(!batch.is_success());
+        assert_eq!(batch.failed().count(), 0);
+        assert_eq!(batch.skipped().count(), 1);
+        assert_eq!(batch.cancelled().count(), 1);
+        assert!(!batch.is_completed("c.m4s"));
+
+        batch.merge(report(vec![task(
+            "e",
+            TaskOutcome::Failed("404".to_string()),
+            0,
+        )]));
+        let failed: Vec<&str> = batch.failed().map(|t| t.name.as_str()).collect();
+        assert_eq!(failed, ["e"]);
+        assert_eq!(batch.tasks.len(), 5);
+        assert_eq!(batch.total_bytes(), 160);
+        assert_eq!(batch.duration, Duration::from_secs(6));
+
+        // 任务的终止状态与结果的对应关系
+        assert_eq!(
+            TaskOutcome::from_status(&TaskStatus::Error("x".to_string())),
+            Some(TaskOutcome::Failed("x".to_string()))
+        );
+        assert_eq!(TaskOutcome::from_status(&TaskStatus::Paused), None);
+    }
+}
@@ -0,0 +1,89 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+/// 测试用的本地文件服务器：支持 HEAD 与 Range 请求，分块慢速发送内容，用于模拟进行中的下载
+pub struct TestServer {
+    pub url: String,
+    ranges: Arc<Mutex<Vec<Option<String>>>>,
+}
+
+impl TestServer {
+    /// 提供 `body` 的下载，每次发送 `chunk` 字节后等待 `delay`
+    pub async fn start(body: Vec<u8>, chunk: usize, delay: Duration) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/video.m4s", listener.local_addr().unwrap());
+        let ranges = Arc::new(Mutex::new(Vec::new()));
+        let body = Arc::new(body);
+        let recorded = ranges.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let body = body.clone();
+                let recorded = recorded.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0; 8192];
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let head = request.starts_with("HEAD ");
+                    let start = range_start(&request);
+                    if !head {
+                        recorded
+                            .lock()
+                            .unwrap()
+                            .push(start.map(|start| format!("bytes={}-", start)));
+                    }
+
+                    let start = start.unwrap_or(0).min(body.len());
+                    let status = if start > 0 {
+                        format!(
+                            "206 Partial Content\r\nContent-Range: bytes {}-{}/{}",
+                            start,
+                            body.len() - 1,
+                            body.len()
+                        )
+                    } else {
+                        "200 OK".to_string()
+                    };
+                    let header = format!(
+                        "HTTP/1.1 {}\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nAccept-Ranges: bytes\r\nETag: \"test\"\r\nConnection: close\r\n\r\n",
+                        status,
+                        body.len() - start
+                    );
+                    if stream.write_all(header.as_bytes()).await.is_err() || head {
+                        return;
+                    }
+                    for part in body[start..].chunks(chunk) {
+                        if stream.write_all(part).await.is_err() {
+                            return;
+                        }
+                        tokio::time::sleep(delay).await;
+                    }
+                });
+            }
+        });
+        Self { url, ranges }
+    }
+
+    /// 收到的 GET 请求的 Range 头（没有 Range 时为 None）
+    pub fn ranges(&self) -> Vec<Option<String>> {
+        self.ranges.lock().unwrap().clone()
+    }
+}
+
+fn range_start(request: &str) -> Option<usize> {
+    request.lines().find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        if !name.eq_ignore_ascii_case("range") {
+            return None;
+        }
+        value
+            .trim()
+            .strip_prefix("bytes=")?
+            .split('-')
+            .next()?
+            .parse()
+            .ok()
+    })
+}
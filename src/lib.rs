@@ -1,7 +1,12 @@
 pub mod auth;
 pub mod cli;
 pub mod common;
+pub mod config;
 pub mod downloader;
 pub mod mcp;
 pub mod parser;
-pub mod post_process;
\ No newline at end of file
+pub mod post_process;
+pub mod resume;
+pub mod watch;
+
+pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
@@ -1,67 +1,93 @@
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, ValueEnum};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::time::Instant;
 use tracing::{debug, error};
 use uuid::Uuid;
 
+use crate::auth::accounts::AccountStore;
+use crate::auth::cookies::CookieFormat;
+use crate::common::client::client::BiliClient;
 use crate::common::logger::PrettyLogger;
+use crate::common::utils::{FormatTool, write_private};
+use crate::downloader::archive::{ARCHIVE_FILE, ArchiveKey, DownloadArchive};
+use crate::downloader::cdn::CdnPolicy;
+use crate::downloader::models::DownloadTask;
+use crate::downloader::preflight::{self, SpaceVerdict};
+use crate::downloader::rate_limit::RateLimitConfig;
+use crate::downloader::report::DownloadReport;
 
 use crate::parser::{
-    detail_parser::{models::DownloadConfig, parser_trait::ParserOptions},
-    models::VideoQuality,
+    detail_parser::{
+        models::DownloadConfig,
+        parser_trait::{ParserOptions, StreamType},
+    },
+    models::{AudioFormat, VideoQuality},
 };
 
 mod auth;
 mod cli;
 mod common;
+mod config;
 mod downloader;
 mod parser;
 mod post_process;
+mod resume;
+mod watch;
 
 #[cfg(feature = "mcp")]
 mod mcp;
 
 type Result<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
 
-/// 处理用户认证
-async fn handle_auth(auth_manager: &auth::AuthManager, args: &cli::Cli) -> Result<Uuid> {
+/// 处理用户认证，依次尝试 --cookie、--user-dir、--account 和默认账号，均未提供时返回 None
+async fn handle_auth(
+    auth_manager: &auth::AuthManager,
+    args: &cli::GlobalArgs,
+) -> Result<Option<Uuid>> {
     // 如果提供了cookie，直接使用
     if let Some(cookie) = &args.cookie {
         log_info!("使用提供的Cookie进行登录");
-        let id_opt = auth_manager.login_by_cookies(cookie).await?;
+        let id_opt = auth_manager.login_by_cookie(cookie).await?;
         if let Some(id) = id_opt {
-            PrettyLogger::user_status("登录成功", &id.to_string());
-            return Ok(id);
+            PrettyLogger::user_status("登录成功", id.to_string());
+            return Ok(Some(id));
         } else {
             return Err("使用提供的cookie登录失败".into());
         }
     }
 
-    // 如果指定了用户目录，尝试从文件加载
+    // 如果指定了用户目录，尝试从文件加载：可以是 Cookie 文件，也可以是包含 cookies.jsonl 的目录
     if let Some(user_dir) = &args.user_dir {
         log_info!("尝试从用户目录加载登录状态");
-        if let Ok(Some(id)) = auth_manager
-            .login_by_cookies(&user_dir.to_str().unwrap().to_string())
-            .await
+        let cookie_file = if user_dir.is_dir() {
+            user_dir.join(auth::accounts::COOKIES_FILE)
+        } else {
+            user_dir.clone()
+        };
+        if cookie_file.is_file()
+            && let Ok(Some(id)) = auth_manager
+                .login_by_cookies(&cookie_file.to_string_lossy().to_string())
+                .await
         {
-            PrettyLogger::user_status("已登录", &id.to_string());
-            return Ok(id);
+            PrettyLogger::user_status("已登录", id.to_string());
+            return Ok(Some(id));
         }
         PrettyLogger::warning("用户目录中未找到有效的登录信息");
     }
 
-    // 如果需要登录，执行登录流程
-    if args.login {
-        log_step!("开始二维码登录流程");
-        let id = auth_manager.qr_login_flow().await?;
-        PrettyLogger::user_status("登录成功", &id.to_string());
-        return Ok(id);
+    // 已保存的账号：--account 指定的账号，否则为默认账号
+    match auth_manager.login_account(args.account.as_deref()).await? {
+        Some((id, account)) => {
+            PrettyLogger::user_status(&account.name, account.mid.to_string());
+            Ok(Some(id))
+        }
+        None => Ok(None),
     }
-
-    Err("需要登录信息".into())
 }
 
 /// 准备下载环境
-async fn prepare_download_env(args: &cli::Cli) -> Result<(PathBuf, PathBuf)> {
+async fn prepare_download_env(args: &cli::DownloadOptions) -> Result<(PathBuf, PathBuf)> {
     // 创建状态文件
     let state_file = PathBuf::from("state.json");
     if !state_file.exists() {
@@ -77,8 +103,37 @@ async fn prepare_download_env(args: &cli::Cli) -> Result<(PathBuf, PathBuf)> {
     Ok((state_file, output_dir))
 }
 
+/// 打开下载存档：优先使用 --download-archive，其次是旧版 --user-dir 中的存档，否则使用配置目录下的默认存档
+fn open_download_archive(
+    global: &cli::GlobalArgs,
+    args: &cli::DownloadOptions,
+) -> Result<Option<DownloadArchive>> {
+    let default_dir = global.user_dir.clone().or_else(config::config_dir);
+    let path = match (&args.download_archive, default_dir) {
+        (Some(path), _) => path.clone(),
+        (None, Some(dir)) => dir.join(ARCHIVE_FILE),
+        (None, None) => {
+            if args.upgrade {
+                log_warning!("无法确定配置目录，未使用下载存档，--upgrade 不会生效");
+            }
+            return Ok(None);
+        }
+    };
+    let archive = DownloadArchive::open(&path)?;
+    debug!(
+        "下载存档: {:?}，已有 {} 条记录",
+        archive.path(),
+        archive.len()
+    );
+    Ok(Some(archive))
+}
+
 /// 从命令行参数生成解析选项
-fn create_parser_options(args: &cli::Cli, url: &str) -> ParserOptions {
+fn create_parser_options(
+    args: &cli::DownloadOptions,
+    url: &str,
+    archive: Option<DownloadArchive>,
+) -> ParserOptions {
     // 将命令行的 quality 选项转换为 VideoQuality 枚举
     let quality = match args.quality {
         cli::QualityOption::Q360P => VideoQuality::Q360P, // 流畅 360P
@@ -90,6 +145,7 @@ fn create_parser_options(args: &cli::Cli, url: &str) -> ParserOptions {
         cli::QualityOption::Q1080P60 => VideoQuality::Q1080P60, // 高清 1080P60
         cli::QualityOption::Q4K => VideoQuality::Q4K,     // 超清 4K
         cli::QualityOption::QHdr => VideoQuality::QHdr,   // HDR 真彩色
+        cli::QualityOption::QDolby => VideoQuality::QDolby, // 杜比视界
         cli::QualityOption::Q8K => VideoQuality::Q8K,     // 超高清 8K
     };
 
@@ -98,165 +154,782 @@ fn create_parser_options(args: &cli::Cli, url: &str) -> ParserOptions {
         args.quality, quality, quality as i32
     );
 
+    let audio_format = match args.audio_format {
+        cli::AudioFormatOption::M4a => AudioFormat::M4a,
+        cli::AudioFormatOption::Mp3 => AudioFormat::Mp3,
+        cli::AudioFormatOption::Flac => AudioFormat::Flac,
+        cli::AudioFormatOption::Opus => AudioFormat::Opus,
+    };
+
+    if args.audio_only
+        && matches!(
+            args.format,
+            cli::StreamFormatOption::Mp4 | cli::StreamFormatOption::Flv
+        )
+    {
+        log_warning!("仅音频模式需要 DASH 音频流，忽略 --format 指定的格式");
+    }
+    let stream_type = match args.format {
+        cli::StreamFormatOption::Auto => StreamType::Auto,
+        cli::StreamFormatOption::Dash => StreamType::Dash,
+        cli::StreamFormatOption::Mp4 => StreamType::MP4,
+        cli::StreamFormatOption::Flv => StreamType::Flv,
+    };
+
+    let config = DownloadConfig {
+        resolution: quality,
+        need_audio: args.need_audio,
+        need_video: args.need_video,
+        need_subtitle: args.need_subtitle,
+        need_danmaku: args.need_danmaku,
+        concurrency: args.concurrency,
+        episode_range: args.parts.clone(),
+        merge: args.merge,
+        output_dir: args
+            .output_dir
+            .clone()
+            .to_str()
+            .unwrap_or("./downloads")
+            .to_string(),
+        output_format: "mp4".to_string(),
+        audio_only: args.audio_only,
+        audio_format,
+        audio_template: args.audio_template.clone(),
+        stream_type,
+        selector: args.select.clone(),
+        archive,
+        upgrade: args.upgrade,
+    };
+
     // 根据URL类型返回对应的选项
     if url.contains("/bangumi/play/") {
         // 后期可能需要更复杂的逻辑来区分番剧和课程
-        ParserOptions::Bangumi {
-            config: DownloadConfig {
-                resolution: quality,
-                need_audio: args.need_audio,
-                need_video: args.need_video,
-                need_subtitle: args.need_subtitle,
-                need_danmaku: args.need_danmaku,
-                concurrency: args.concurrency,
-                episode_range: args.parts.clone(),
-                merge: args.merge,
-                output_dir: args
-                    .output_dir
-                    .clone()
-                    .to_str()
-                    .unwrap_or("./downloads")
-                    .to_string(),
-                output_format: "mp4".to_string(),
-            },
-        }
+        ParserOptions::Bangumi { config }
     } else if url.contains("/cheese/play/") {
         // 课程解析选项
-        ParserOptions::Course {
-            config: DownloadConfig {
-                resolution: quality,
-                need_audio: args.need_audio,
-                need_video: args.need_video,
-                need_subtitle: args.need_subtitle,
-                need_danmaku: args.need_danmaku,
-                concurrency: args.concurrency,
-                episode_range: args.parts.clone(),
-                merge: args.merge,
-                output_dir: args
-                    .output_dir
-                    .clone()
-                    .to_str()
-                    .unwrap_or("./downloads")
-                    .to_string(),
-                output_format: "mp4".to_string(),
-            },
-        }
+        ParserOptions::Course { config }
     } else {
-        ParserOptions::CommonVideo {
-            config: DownloadConfig {
-                resolution: quality,
-                need_audio: args.need_audio,
-                need_video: args.need_video,
-                need_subtitle: args.need_subtitle,
-                need_danmaku: args.need_danmaku,
-                concurrency: args.concurrency,
-                episode_range: args.parts.clone(),
-                merge: args.merge,
-                output_dir: args
-                    .output_dir
-                    .clone()
-                    .to_str()
-                    .unwrap_or("./downloads")
-                    .to_string(),
-                output_format: "mp4".to_string(),
-            },
-        }
+        ParserOptions::CommonVideo { config }
     }
 }
 
-#[tokio::main]
-async fn main() -> Result<()> {
-    // 初始化日志
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .init();
-
-    // 解析命令行参数
-    let args = cli::Cli::parse();
-
-    // 检查是否启动MCP服务器模式
-    #[cfg(feature = "mcp")]
-    if args.mcp {
-        log_info!("启动MCP服务器模式");
-        let mcp_server = mcp::McpServer::new();
-        return mcp_server.run().await.map_err(|e| e.into());
-    }
-
-    #[cfg(not(feature = "mcp"))]
-    if args.mcp {
-        PrettyLogger::error("MCP功能未启用。请使用 --features mcp 重新编译");
-        return Err("MCP功能未启用".into());
-    }
-
-    // 检查是否仅执行登录
-    let is_login_only = args.url.is_none();
-
-    if is_login_only {
-        log_info!("仅执行登录操作");
-    } else {
-        PrettyLogger::video_info(args.url.as_ref().unwrap(), "准备下载");
+// 下载前检查输出目录所在磁盘的可用空间：不够下载时拒绝开始，不够合并时给出警告
+async fn check_disk_space(
+    client: &BiliClient,
+    tasks: &[DownloadTask],
+    options: &ParserOptions,
+    args: &cli::DownloadOptions,
+) -> Result<()> {
+    let config = options.config();
+    let estimate =
+        preflight::estimate_space(client, tasks, config.merge || config.audio_only).await;
+    let available = match preflight::available_space(Path::new(&config.output_dir)) {
+        Ok(available) => available,
+        Err(e) => {
+            log_warning!("{}，跳过磁盘空间检查", e);
+            return Ok(());
+        }
+    };
+    debug!("磁盘空间预检: {:?}，可用 {} 字节", estimate, available);
+    if estimate.estimated > 0 || estimate.unknown > 0 {
+        log_info!(
+            "{} 个文件按码率估算大小，{} 个文件大小未知，空间检查仅供参考",
+            estimate.estimated,
+            estimate.unknown
+        );
     }
 
-    // 认证处理
-    let auth_manager = auth::AuthManager::new();
-    let mut session_id = Uuid::new_v4(); // 默认会话ID
-
-    if args.login || args.cookie.is_some() || args.user_dir.is_some() {
-        session_id = handle_auth(&auth_manager, &args).await?;
-    } else if !is_login_only {
-        log_warning!("未提供登录信息，可能无法下载受限内容");
-    }
-
-    // 如果仅登录，完成登录后退出
-    if is_login_only {
-        let session_file = Path::new("./sessions")
-            .join(session_id.to_string())
-            .join("cookies.jsonl");
-        let abs_path = session_file.canonicalize().unwrap_or(session_file);
-        PrettyLogger::file_info("登录信息已保存到", &abs_path.display().to_string());
-        log_success!("登录完成！");
-        return Ok(());
+    let mib = |bytes: u64| bytes as f64 / 1024.0 / 1024.0;
+    match estimate.verdict(available) {
+        SpaceVerdict::Enough => Ok(()),
+        SpaceVerdict::TightForMerge => {
+            log_warning!(
+                "磁盘空间可能不足以完成合并: 需要约 {:.1} MiB（下载 {:.1} MiB + 合并 {:.1} MiB），可用 {:.1} MiB",
+                mib(estimate.total()),
+                mib(estimate.download),
+                mib(estimate.headroom),
+                mib(available)
+            );
+            Ok(())
+        }
+        SpaceVerdict::Insufficient if args.ignore_disk_space => {
+            log_warning!(
+                "磁盘空间不足: 需要约 {:.1} MiB，可用 {:.1} MiB，已按 --ignore-disk-space 继续",
+                mib(estimate.download),
+                mib(available)
+            );
+            Ok(())
+        }
+        SpaceVerdict::Insufficient => {
+            PrettyLogger::error(format!(
+                "磁盘空间不足: 下载需要约 {:.1} MiB，{} 所在磁盘仅剩 {:.1} MiB",
+                mib(estimate.download),
+                config.output_dir,
+                mib(available)
+            ));
+            Err("磁盘空间不足，可使用 --ignore-disk-space 忽略此检查".into())
+        }
     }
+}
 
-    let client = auth_manager.get_authed_client(session_id).await?;
-
-    // 创建解析选项
-    let options = create_parser_options(&args, args.url.as_ref().unwrap());
+/// 一个链接的下载结果
+struct UrlOutcome {
+    title: String,
+    report: DownloadReport, // 全部内容已在存档中时为空报告
+    complete: bool,         // 下载和后处理均已成功
+}
 
+/// 解析并下载一个链接
+async fn download_url(
+    client: &BiliClient,
+    url: &str,
+    options: &ParserOptions,
+    args: &cli::DownloadOptions,
+) -> Result<UrlOutcome> {
     // 解析视频信息
     log_step!("开始解析视频信息");
     let mut parser = parser::VideoParser::new(client.clone(), true);
-    let parsed_metas = parser
-        .parse(args.url.as_ref().unwrap(), &options)
-        .await
-        .map_err(|e| {
-            error!("解析失败: {}", e);
-            e
-        })?;
+    let parsed_metas = parser.parse(url, options).await.map_err(|e| {
+        error!("解析失败: {}", e);
+        e
+    })?;
 
     // 可能有多个视频需要下载
     PrettyLogger::video_info(&parsed_metas.title, "解析完成");
     debug!("解析结果: {:?}", parsed_metas);
+    if parsed_metas.download_items.is_empty() && options.config().archive.is_some() {
+        log_success!("所有内容均已在下载存档中，无需下载");
+        return Ok(UrlOutcome {
+            title: parsed_metas.title,
+            report: DownloadReport::default(),
+            complete: true,
+        });
+    }
 
     // 准备下载环境
-    let (state_file, _) = prepare_download_env(&args).await?;
+    let (state_file, _) = prepare_download_env(args).await?;
+
+    // 检查磁盘空间
+    check_disk_space(client, &parsed_metas.download_items, options, args).await?;
 
     // 开始下载
     log_step!("开始下载视频");
     let mut task = parsed_metas.download_items.clone();
-    let downloader = downloader::VideoDownloader::new(4, state_file, client.clone());
-    downloader.download(&mut task).await?;
+    let downloader = downloader::VideoDownloader::new(args.concurrency, state_file, client.clone());
+    downloader.core().set_cdn_policy(CdnPolicy {
+        mirror: args.cdn_mirror.clone(),
+        probe: args.cdn_probe,
+    });
+    downloader.core().apply_rate_limits(&RateLimitConfig {
+        global: args.limit_rate.unwrap_or(0),
+        per_task: args.limit_rate_per_task.unwrap_or(0),
+        schedules: args.rate_schedule.clone(),
+    });
+
+    // Ctrl-C：暂停全部任务，保留部分文件并写入状态文件后退出
+    let core = downloader.core();
+    let interrupt_handler = tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            log_warning!("收到中断信号，正在暂停所有下载任务...");
+            core.pause_all(std::time::Duration::from_secs(5)).await;
+            match core.save_state().await {
+                Ok(()) => log_info!("已保存下载状态，重新运行相同命令即可继续下载"),
+                Err(e) => error!("保存下载状态失败: {}", e),
+            }
+            std::process::exit(130);
+        }
+    });
+
+    let renderer = downloader::progress::spawn_progress_renderer(downloader.core().subscribe());
+
+    let report = downloader.download(&mut task).await;
+    interrupt_handler.abort();
 
-    // 后处理
-    if let Err(e) = parsed_metas.post_process(&task, &options).await {
+    // 后处理，仅处理完整下载的分组
+    let events = downloader.core().events();
+    let post_process_result = parsed_metas
+        .post_process(&task, options, &report, &events)
+        .await;
+    if let Err(e) = &post_process_result {
         error!("后处理失败: {}", e);
     } else {
         PrettyLogger::step_complete("后处理完成");
     }
 
-    PrettyLogger::completion_summary(vec![
-        &format!("📹 视频: {}", parsed_metas.title),
-        &format!("📂 保存位置: {}", args.output_dir.display()),
-    ]);
+    // 关闭事件通道，等待下载面板收尾
+    drop(events);
+    drop(downloader);
+    let _ = renderer.await;
+
+    report.print();
+    let complete = report.is_success() && post_process_result.is_ok();
+    if complete {
+        PrettyLogger::completion_summary(vec![
+            &format!("📹 视频: {}", parsed_metas.title),
+            &format!("📂 保存位置: {}", args.output_dir.display()),
+        ]);
+    }
+    Ok(UrlOutcome {
+        title: parsed_metas.title,
+        report,
+        complete,
+    })
+}
+
+/// 批量下载：逐行处理，共用同一个已登录的客户端，最后输出汇总报告
+///
+/// 单行失败不影响后续链接，返回是否全部完成。
+async fn run_batch(
+    client: &BiliClient,
+    global: &cli::GlobalArgs,
+    args: &cli::DownloadOptions,
+    batch_file: &Path,
+) -> Result<bool> {
+    let content = parser::batch::read_batch_input(batch_file)?;
+    let entries = parser::batch::parse_batch(&content)?;
+    if entries.is_empty() {
+        log_warning!("批量输入中没有需要下载的链接");
+        return Ok(true);
+    }
+    log_info!("批量下载: 共 {} 个链接", entries.len());
+
+    let archive = open_download_archive(global, args)?;
+    let mut summary = DownloadReport::default();
+    let mut failures = Vec::new();
+    for (index, entry) in entries.iter().enumerate() {
+        log_step!("[{}/{}] {}", index + 1, entries.len(), entry.input);
+        let mut entry_args = args.clone();
+        if let Some(parts) = &entry.parts {
+            entry_args.parts = Some(parts.clone());
+        }
+        if let Some(quality) = &entry.quality {
+            entry_args.quality = quality.clone();
+        }
+
+        let options = create_parser_options(&entry_args, &entry.url, archive.clone());
+        match download_url(client, &entry.url, &options, &entry_args).await {
+            Ok(outcome) => {
+                if !outcome.complete {
+                    failures.push(format!(
+                        "第 {} 行 {}: 部分任务未完成",
+                        entry.line, outcome.title
+                    ));
+                }
+                summary.merge(outcome.report);
+            }
+            Err(e) => failures.push(format!("第 {} 行 {}: {}", entry.line, entry.input, e)),
+        }
+    }
+
+    // 汇总报告
+    summary.print();
+    for failure in &failures {
+        PrettyLogger::error(failure);
+    }
+    log_info!(
+        "批量下载结束: 共 {} 个链接，{} 个完成，{} 个未完成",
+        entries.len(),
+        entries.len() - failures.len(),
+        failures.len()
+    );
+    Ok(failures.is_empty())
+}
+
+// 解析订阅配置中的清晰度名称，与 --quality 的取值相同
+fn parse_quality(value: &str) -> Result<cli::QualityOption> {
+    <cli::QualityOption as ValueEnum>::from_str(value, true)
+        .map_err(|_| format!("订阅配置中的清晰度 {:?} 无效", value).into())
+}
+
+/// 订阅模式：按各订阅源的间隔轮询，只下载下载存档中没有的新内容
+async fn run_watch(
+    client: &BiliClient,
+    global: &cli::GlobalArgs,
+    watch_args: &cli::WatchArgs,
+) -> Result<()> {
+    let args = &watch_args.options;
+    let config = watch::config::WatchConfig::load(&watch_args.config)?;
+    for source in &config.sources {
+        if let Some(quality) = source.quality(&config) {
+            parse_quality(quality)?;
+        }
+    }
+
+    // 订阅模式总是使用下载存档：--download-archive 优先于配置文件，都未设置时与 download 命令共用默认存档
+    let archive = match (&args.download_archive, &config.archive) {
+        (None, Some(path)) => DownloadArchive::open(path)?,
+        _ => open_download_archive(global, args)?
+            .ok_or("无法确定配置目录，请使用 --download-archive 指定下载存档")?,
+    };
+    PrettyLogger::file_info("下载存档", archive.path().display().to_string());
+    log_info!(
+        "共 {} 个订阅源，存档中已有 {} 条记录",
+        config.sources.len(),
+        archive.len()
+    );
+
+    let mut next_due = vec![Instant::now(); config.sources.len()];
+    loop {
+        for (index, source) in config.sources.iter().enumerate() {
+            if next_due[index] > Instant::now() {
+                continue;
+            }
+            log_step!("检查订阅源: {}", source.display_name());
+            if let Err(e) = poll_source(client, args, &config, source, &archive).await {
+                log_warning!("订阅源 {} 检查失败: {}", source.display_name(), e);
+            }
+            next_due[index] = Instant::now() + source.interval(&config);
+        }
+
+        if watch_args.once {
+            log_success!("所有订阅源检查完成");
+            return Ok(());
+        }
+        let wake_at = next_due.iter().min().copied().unwrap_or_else(Instant::now);
+        debug!(
+            "下次检查: {:?} 后",
+            wake_at.saturating_duration_since(Instant::now())
+        );
+        tokio::time::sleep_until(wake_at.into()).await;
+    }
+}
+
+// 检查一个订阅源并逐个下载新内容，单个内容失败不影响其它内容
+async fn poll_source(
+    client: &BiliClient,
+    args: &cli::DownloadOptions,
+    config: &watch::config::WatchConfig,
+    source: &watch::config::WatchSource,
+    archive: &DownloadArchive,
+) -> Result<()> {
+    let archive_kind = ArchiveKey::new("video", "", 0, args.audio_only).kind;
+    let items =
+        watch::sources::list_new_items(client, &source.kind, archive, &archive_kind).await?;
+    if items.is_empty() {
+        log_info!("{}: 没有新内容", source.display_name());
+        return Ok(());
+    }
+    log_info!(
+        "{}: 发现 {} 项待检查的内容",
+        source.display_name(),
+        items.len()
+    );
+
+    for item in items {
+        let mut item_args = args.clone();
+        item_args.parts = item.episode_range.clone();
+        if let Some(quality) = source.quality(config) {
+            item_args.quality = parse_quality(quality)?;
+        }
+        if let Some(template) = source.output_template(config) {
+            let vars = HashMap::from([
+                ("source".to_string(), source.display_name()),
+                ("owner".to_string(), item.owner.clone()),
+                ("title".to_string(), item.title.clone()),
+            ]);
+            item_args.output_dir = watch::config::render_output_dir(template, &vars);
+        }
+
+        PrettyLogger::video_info(&item.title, "准备下载");
+        let options = create_parser_options(&item_args, &item.url, Some(archive.clone()));
+        match download_url(client, &item.url, &options, &item_args).await {
+            Ok(outcome) if outcome.complete => {}
+            Ok(_) => log_warning!("{} 未完整下载，下次检查时重试", item.title),
+            Err(e) => log_warning!("{} 下载失败，下次检查时重试: {}", item.title, e),
+        }
+    }
     Ok(())
 }
+
+// 创建已登录（如果提供了登录信息）并应用网络设置的客户端
+async fn create_client(args: &cli::GlobalArgs) -> Result<BiliClient> {
+    let auth_manager = auth::AuthManager::new();
+    let session_id = match handle_auth(&auth_manager, args).await? {
+        Some(session_id) => session_id,
+        None => {
+            log_warning!("未登录，可能无法下载受限内容，可使用 bilidl account add 添加账号");
+            Uuid::new_v4() // 未登录的默认会话
+        }
+    };
+
+    let mut client = auth_manager.get_authed_client(session_id).await?;
+    config::configure_client(&mut client, args)?;
+    Ok(client)
+}
+
+// 裸ID展开为完整链接，解析选项按链接类型选择
+fn expand_url(input: &str) -> String {
+    parser::url_parser::UrlParser::new()
+        .handle_raw_id(input)
+        .unwrap_or_else(|_| input.to_string())
+}
+
+/// 下载一个链接或批量列表，未完成时以状态码 1 退出
+///
+/// `invocation` 为本次的命令行参数，中断后 `bilidl resume` 据此重新运行。
+async fn run_download(
+    global: &cli::GlobalArgs,
+    args: &cli::DownloadArgs,
+    invocation: &[String],
+) -> Result<()> {
+    let (url, batch_file) = match (args.url(), &args.batch_file) {
+        (Some(url), _) => (Some(expand_url(url)), None),
+        (None, Some(batch_file)) => (None, Some(batch_file)),
+        (None, None) => {
+            cli::Cli::command().print_help()?;
+            return Err("请指定要下载的链接，或使用 bilidl login 登录".into());
+        }
+    };
+    if let Some(url) = &url {
+        PrettyLogger::video_info(url, "准备下载");
+    }
+    let client = create_client(global).await?;
+
+    // 记录本次命令，中断后可用 bilidl resume 继续；标准输入无法重放
+    let resumable = batch_file.is_none_or(|path| path != Path::new("-"));
+    if resumable {
+        resume::save_resume_point(invocation)?;
+    }
+
+    let complete = match (url, batch_file) {
+        (_, Some(batch_file)) => run_batch(&client, global, &args.options, batch_file).await?,
+        (Some(url), None) => {
+            let archive = open_download_archive(global, &args.options)?;
+            let options = create_parser_options(&args.options, &url, archive);
+            download_url(&client, &url, &options, &args.options)
+                .await?
+                .complete
+        }
+        (None, None) => unreachable!(),
+    };
+
+    if !complete {
+        PrettyLogger::error("部分任务未完成，可使用 bilidl resume 或重新运行相同命令继续下载");
+        std::process::exit(1);
+    }
+    if resumable {
+        resume::clear_resume_point();
+    }
+    Ok(())
+}
+
+/// 解析命令行参数，并用配置文件中的设置填充命令行未指定的选项
+fn parse_args(matches: &clap::ArgMatches) -> Result<(cli::Cli, Option<config::LoadedConfig>)> {
+    let mut args = cli::Cli::from_arg_matches(matches)?;
+    let loaded = config::apply_config(&mut args, matches)?;
+    if let Some(loaded) = &loaded {
+        debug!(
+            "使用配置文件: {:?}，配置方案: {:?}",
+            loaded.path, loaded.profile
+        );
+    }
+    Ok((args, loaded))
+}
+
+/// 继续上次未完成的下载：使用上次命令的全部参数重新运行，已下载的部分会被复用
+///
+/// 上次命令中的 `--cookie`/`--header` 不会保存，可以在本次 resume 命令中重新指定。
+async fn run_resume(invocation: &[String]) -> Result<()> {
+    let Some(previous) = resume::load_resume_point()? else {
+        log_info!("没有未完成的下载");
+        return Ok(());
+    };
+    log_info!("继续上次的下载: bilidl {}", previous.args.join(" "));
+    let (_, secrets) = resume::split_secrets(invocation);
+    if previous.secrets_removed && secrets.is_empty() {
+        log_warning!("上次命令中的 --cookie/--header 未保存，需要时请在 resume 命令中重新指定");
+    }
+    let args: Vec<String> = previous.args.into_iter().chain(secrets).collect();
+    let matches = cli::Cli::command()
+        .try_get_matches_from(std::iter::once("bilidl".to_string()).chain(args.clone()))?;
+    let (parsed, _) = parse_args(&matches)?;
+    match parsed.subcommand() {
+        cli::Command::Download(download_args) => {
+            run_download(&parsed.global, &download_args, &args).await
+        }
+        _ => Err("上次记录的命令不是下载命令".into()),
+    }
+}
+
+/// 解析链接并列出将要下载的文件，不下载
+async fn run_info(global: &cli::GlobalArgs, args: &cli::InfoArgs) -> Result<()> {
+    let url = expand_url(&args.url);
+    let client = create_client(global).await?;
+    // 不使用下载存档，列出全部内容
+    let options = create_parser_options(&args.options, &url, None);
+
+    let mut parser = parser::VideoParser::new(client, true);
+    let parsed_metas = parser.parse(&url, &options).await?;
+
+    PrettyLogger::title(&parsed_metas.title);
+    PrettyLogger::info(format!("类型: {:?}", parsed_metas.download_type));
+    let mut total_size = 0;
+    for task in &parsed_metas.download_items {
+        let size = task
+            .metadata
+            .get("expected_size")
+            .or_else(|| task.metadata.get("estimated_size"))
+            .and_then(|size| size.parse::<u64>().ok());
+        total_size += size.unwrap_or(0);
+        let details: Vec<&str> = ["quality_desc", "codecs"]
+            .iter()
+            .filter_map(|key| task.metadata.get(*key).map(String::as_str))
+            .filter(|value| !value.is_empty())
+            .collect();
+        PrettyLogger::info(format!(
+            "{:?}: {} {} {}",
+            task.file_type,
+            task.name,
+            details.join(" "),
+            size.map(FormatTool::format_size).unwrap_or_default()
+        ));
+    }
+    PrettyLogger::info(format!(
+        "共 {} 个文件，约 {}",
+        parsed_metas.download_items.len(),
+        FormatTool::format_size(total_size)
+    ));
+    Ok(())
+}
+
+/// 扫码登录并保存账号
+async fn run_account_add(args: &cli::AccountAddArgs) -> Result<()> {
+    let auth_manager = auth::AuthManager::new();
+    log_step!("开始二维码登录流程");
+    let session_id = auth_manager.qr_login_flow().await?;
+    let client = auth_manager.get_authed_client(session_id).await?;
+    let user = client.get_user_info().await?;
+
+    let mut store = AccountStore::open_default()?;
+    let account = store
+        .add(user.mid, args.name.clone(), &user.uname, &client)
+        .await?;
+    if args.make_default {
+        store.set_default(&account.name)?;
+    }
+    PrettyLogger::user_status(&account.uname, account.mid.to_string());
+    PrettyLogger::file_info(
+        "登录信息已保存到",
+        store.cookie_path(&account).display().to_string(),
+    );
+    let is_default = store.default_account().map(|a| a.mid) == Some(account.mid);
+    log_success!(
+        "已添加账号 {}{}",
+        account.name,
+        if is_default { "（默认账号）" } else { "" }
+    );
+    Ok(())
+}
+
+/// 列出已保存的账号，默认账号以 * 标记
+fn run_account_list() -> Result<()> {
+    let store = AccountStore::open_default()?;
+    if store.accounts().is_empty() {
+        log_info!("还没有保存的账号，可使用 bilidl account add 添加");
+        return Ok(());
+    }
+    let default = store.default_account().map(|account| account.mid);
+    for account in store.accounts() {
+        let added_at = chrono::DateTime::from_timestamp(account.added_at, 0)
+            .map(|time| {
+                time.with_timezone(&chrono::Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string()
+            })
+            .unwrap_or_default();
+        PrettyLogger::info(format!(
+            "{} {}  {} (mid {})  添加于 {}",
+            if default == Some(account.mid) {
+                "*"
+            } else {
+                " "
+            },
+            account.name,
+            account.uname,
+            account.mid,
+            added_at
+        ));
+    }
+    Ok(())
+}
+
+/// 删除已保存的账号
+fn run_account_remove(selector: &str) -> Result<()> {
+    let mut store = AccountStore::open_default()?;
+    let account = store.remove(selector)?;
+    log_success!("已删除账号 {} (mid {})", account.name, account.mid);
+    if let Some(default) = store.default_account() {
+        log_info!("默认账号: {}", default.name);
+    }
+    Ok(())
+}
+
+/// 设置默认账号
+fn run_account_default(selector: &str) -> Result<()> {
+    let mut store = AccountStore::open_default()?;
+    let account = store.set_default(selector)?;
+    log_success!("默认账号已设置为 {} (mid {})", account.name, account.mid);
+    Ok(())
+}
+
+/// 导出账号的 Cookie
+fn run_account_export(args: &cli::AccountExportArgs, global: &cli::GlobalArgs) -> Result<()> {
+    let store = AccountStore::open_default()?;
+    let account = match args.account.as_ref().or(global.account.as_ref()) {
+        Some(selector) => store.resolve(selector)?,
+        None => store
+            .default_account()
+            .ok_or("没有默认账号，请指定要导出的账号")?,
+    };
+    let path = store.cookie_path(account);
+    let content = std::fs::read_to_string(&path).map_err(|e| {
+        format!(
+            "读取账号 {} 的登录信息 {:?} 失败: {}",
+            account.name, path, e
+        )
+    })?;
+    let format = match args.format {
+        cli::CookieFormatOption::Netscape => CookieFormat::Netscape,
+        cli::CookieFormatOption::Header => CookieFormat::Header,
+        cli::CookieFormatOption::Json => CookieFormat::Json,
+    };
+    let cookies = auth::cookies::export(&auth::cookies::parse_json(&content)?, format)?;
+
+    match &args.output {
+        Some(output) => {
+            // 导出的 Cookie 可直接登录，只允许当前用户读写
+            write_private(output, cookies)?;
+            log_success!("已导出账号 {} 的 Cookie 到 {:?}", account.name, output);
+        }
+        None => print!("{}", cookies),
+    }
+    Ok(())
+}
+
+/// 显示当前登录的账号
+async fn run_whoami(global: &cli::GlobalArgs) -> Result<()> {
+    let client = create_client(global).await?;
+    let user = client.get_user_info().await?;
+    PrettyLogger::user_status(&user.uname, user.mid.to_string());
+    PrettyLogger::info(format!(
+        "大会员: {}",
+        if user.vip_status == 1 { "是" } else { "否" }
+    ));
+    Ok(())
+}
+
+/// 启动 stdio 模式的 MCP 服务器
+async fn run_mcp(config: config::EffectiveConfig) -> Result<()> {
+    #[cfg(feature = "mcp")]
+    {
+        log_info!("启动MCP服务器模式");
+        let mcp_server = mcp::McpServer::with_config(config);
+        mcp_server.run().await.map_err(|e| e.into())
+    }
+
+    #[cfg(not(feature = "mcp"))]
+    {
+        let _ = config;
+        PrettyLogger::error("MCP功能未启用。请使用 --features mcp 重新编译");
+        Err("MCP功能未启用".into())
+    }
+}
+
+/// 启动 HTTP 模式的 MCP 服务器
+async fn run_serve(args: &cli::ServeArgs, config: config::EffectiveConfig) -> Result<()> {
+    #[cfg(feature = "http")]
+    {
+        log_info!("启动HTTP MCP服务器: http://{}/mcp", args.listen);
+        mcp::http::serve(args.listen, &args.allow_origins, config)
+            .await
+            .map_err(|e| e.into())
+    }
+
+    #[cfg(not(feature = "http"))]
+    {
+        let _ = (args, config);
+        PrettyLogger::error("HTTP服务未启用。请使用 --features http 重新编译");
+        Err("HTTP服务未启用".into())
+    }
+}
+
+/// 将 XML 弹幕转换为 ASS 字幕
+fn run_danmaku_convert(args: &cli::DanmakuConvertArgs) -> Result<()> {
+    let xml = std::fs::read(&args.input)?;
+    let danmakus = post_process::danmaku::parse_xml(&String::from_utf8_lossy(&xml));
+    let options = post_process::danmaku::AssOptions {
+        width: args.width,
+        height: args.height,
+        font: args.font.clone(),
+        font_size: args.font_size,
+        scroll_duration: args.scroll_duration,
+        fixed_duration: args.fixed_duration,
+        opacity: args.opacity,
+        display_area: args.display_area,
+    };
+    let (ass, stats) = post_process::danmaku::to_ass(&danmakus, &options);
+
+    let output = args
+        .output
+        .clone()
+        .unwrap_or_else(|| args.input.with_extension("ass"));
+    std::fs::write(&output, ass)?;
+    PrettyLogger::file_info("弹幕字幕已保存到", output.display().to_string());
+    log_info!(
+        "共 {} 条弹幕，写入 {} 条，屏幕已满丢弃 {} 条，不支持的特殊弹幕 {} 条",
+        danmakus.len(),
+        stats.written,
+        stats.dropped,
+        stats.unsupported
+    );
+    Ok(())
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // 初始化日志
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_writer(common::logger::LogWriter::default)
+        .init();
+
+    // 解析命令行参数，合并配置文件
+    let matches = cli::Cli::command().get_matches();
+    let (args, loaded) = parse_args(&matches)?;
+    // MCP 服务使用的默认设置
+    let effective = || config::EffectiveConfig {
+        source: loaded.as_ref().map(|loaded| loaded.path.clone()),
+        profile: loaded.as_ref().and_then(|loaded| loaded.profile.clone()),
+        global: args.global.clone(),
+        download: args.download.options.clone(),
+    };
+
+    let invocation: Vec<String> = std::env::args().skip(1).collect();
+    match args.subcommand() {
+        cli::Command::Download(download_args) => {
+            run_download(&args.global, &download_args, &invocation).await
+        }
+        cli::Command::Info(info_args) => run_info(&args.global, &info_args).await,
+        cli::Command::Login(add_args) => run_account_add(&add_args).await,
+        cli::Command::Account { command } => match command {
+            cli::AccountCommand::List => run_account_list(),
+            cli::AccountCommand::Add(add_args) => run_account_add(&add_args).await,
+            cli::AccountCommand::Remove { account } => run_account_remove(&account),
+            cli::AccountCommand::Default { account } => run_account_default(&account),
+            cli::AccountCommand::Whoami => run_whoami(&args.global).await,
+            cli::AccountCommand::Export(export_args) => {
+                run_account_export(&export_args, &args.global)
+            }
+        },
+        cli::Command::Resume => run_resume(&invocation).await,
+        cli::Command::Watch(watch_args) => {
+            let client = create_client(&args.global).await?;
+            run_watch(&client, &args.global, &watch_args).await
+        }
+        cli::Command::Mcp => run_mcp(effective()).await,
+        cli::Command::Serve(serve_args) => run_serve(&serve_args, effective()).await,
+        cli::Command::Danmaku {
+            command: cli::DanmakuCommand::Convert(convert_args),
+        } => run_danmaku_convert(&convert_args),
+    }
+}
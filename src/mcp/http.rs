@@ -0,0 +1,75 @@
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    routing::{get, post},
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use super::server::{BiliMcpServer, JsonRpcResponse};
+use crate::config::EffectiveConfig;
+
+#[derive(Clone)]
+struct AppState {
+    server: Arc<BiliMcpServer>,
+    allowed_origins: Arc<Vec<HeaderValue>>,
+}
+
+/// 以 HTTP 方式提供 MCP 服务：`POST /mcp` 接收 JSON-RPC 请求，`GET /health` 用于健康检查
+///
+/// 默认拒绝所有带 `Origin` 的浏览器请求，避免任意网页借用户的登录状态调用；
+/// `allowed_origins` 中的来源可以跨域访问。
+pub async fn serve(
+    addr: SocketAddr,
+    allowed_origins: &[String],
+    config: EffectiveConfig,
+) -> anyhow::Result<()> {
+    let allowed_origins = allowed_origins
+        .iter()
+        .map(|origin| {
+            HeaderValue::from_str(origin.trim_end_matches('/'))
+                .map_err(|e| anyhow::anyhow!("无效的来源 '{}': {}", origin, e))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let mut app = Router::new()
+        .route("/mcp", post(handle_mcp))
+        .route("/health", get(|| async { "ok" }))
+        .with_state(AppState {
+            server: Arc::new(BiliMcpServer::with_config(config)),
+            allowed_origins: Arc::new(allowed_origins.clone()),
+        });
+    if !allowed_origins.is_empty() {
+        app = app.layer(
+            CorsLayer::new()
+                .allow_origin(AllowOrigin::list(allowed_origins))
+                .allow_methods([Method::GET, Method::POST])
+                .allow_headers([header::CONTENT_TYPE]),
+        );
+    }
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    eprintln!(
+        "🚀 BiliDownloader MCP HTTP Server 已启动: http://{}/mcp",
+        addr
+    );
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+async fn handle_mcp(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<JsonRpcResponse>, StatusCode> {
+    // 浏览器的跨域请求都会带 Origin，即使是不触发预检的简单请求
+    if let Some(origin) = headers.get(header::ORIGIN)
+        && !state.allowed_origins.contains(origin)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(Json(state.server.handle_request(&body).await))
+}
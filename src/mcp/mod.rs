@@ -1,5 +1,8 @@
 #[cfg(feature = "mcp")]
 pub mod server;
 
+#[cfg(feature = "http")]
+pub mod http;
+
 #[cfg(feature = "mcp")]
 pub use server::BiliMcpServer as McpServer;
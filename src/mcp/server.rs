@@ -2,14 +2,21 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_util::sync::CancellationToken;
 use serde_json::{json, Value};
 
 use crate::auth::AuthManager;
 use crate::downloader::VideoDownloader;
+use crate::downloader::core::DownloadCore;
+use crate::downloader::rate_limit::{RateLimitConfig, RateSchedule, parse_rate};
+use crate::downloader::events::DownloadEvent;
+use crate::downloader::models::DownloadTask;
 use crate::parser::{VideoParser, models::VideoQuality};
 use crate::parser::detail_parser::models::DownloadConfig;
 use crate::parser::detail_parser::parser_trait::ParserOptions;
 use crate::common::client::client::BiliClient;
+use crate::config::{EffectiveConfig, configure_client};
+use crate::downloader::cdn::CdnPolicy;
 
 #[derive(serde::Deserialize, serde::Serialize)]
 struct JsonRpcRequest {
@@ -36,16 +43,31 @@ struct JsonRpcError {
 
 pub struct BiliMcpServer {
     auth_manager: AuthManager,
+    account_session: Arc<Mutex<Option<uuid::Uuid>>>, // 已保存账号的登录会话
     active_downloads: Arc<Mutex<HashMap<String, String>>>, // task_id -> status
-    download_manager: Arc<Mutex<Option<VideoDownloader>>>,
+    download_tokens: Arc<Mutex<HashMap<String, CancellationToken>>>, // task_id -> 取消令牌
+    download_manager: Arc<Mutex<Option<Arc<VideoDownloader>>>>, // 下载时取出克隆，不持有锁
+    download_core: Arc<Mutex<Option<DownloadCore>>>, // 下载进行中也可访问的核心句柄（限速等）
+    task_progress: Arc<Mutex<HashMap<String, Value>>>, // 文件任务ID -> 进度快照
+    config: EffectiveConfig, // 配置文件与命令行合并后的默认设置
 }
 
 impl BiliMcpServer {
     pub fn new() -> Self {
+        Self::with_config(EffectiveConfig::default())
+    }
+
+    /// 使用配置文件与命令行合并后的设置作为下载默认值
+    pub fn with_config(config: EffectiveConfig) -> Self {
         Self {
             auth_manager: AuthManager::new(),
+            account_session: Arc::new(Mutex::new(None)),
             active_downloads: Arc::new(Mutex::new(HashMap::new())),
+            download_tokens: Arc::new(Mutex::new(HashMap::new())),
             download_manager: Arc::new(Mutex::new(None)),
+            download_core: Arc::new(Mutex::new(None)),
+            task_progress: Arc::new(Mutex::new(HashMap::new())),
+            config,
         }
     }
 
@@ -53,11 +75,103 @@ impl BiliMcpServer {
     async fn init_downloader(&self) -> anyhow::Result<()> {
         let mut manager = self.download_manager.lock().await;
         if manager.is_none() {
-            *manager = Some(VideoDownloader::new(4, "state.json".into(), BiliClient::new()));
+            let options = &self.config.download;
+            let downloader = VideoDownloader::new(
+                options.concurrency,
+                "state.json".into(),
+                self.client().await?,
+            );
+            downloader.core().set_cdn_policy(CdnPolicy {
+                mirror: options.cdn_mirror.clone(),
+                probe: options.cdn_probe,
+            });
+            downloader.core().apply_rate_limits(&RateLimitConfig {
+                global: options.limit_rate.unwrap_or(0),
+                per_task: options.limit_rate_per_task.unwrap_or(0),
+                schedules: options.rate_schedule.clone(),
+            });
+            Self::track_progress(downloader.core().subscribe(), self.task_progress.clone());
+            *self.download_core.lock().await = Some(downloader.core());
+            *manager = Some(Arc::new(downloader));
         }
         Ok(())
     }
 
+    // 获取客户端：使用配置的账号或默认账号登录并复用会话，没有账号时为未登录的客户端
+    async fn client(&self) -> anyhow::Result<BiliClient> {
+        let mut session = self.account_session.lock().await;
+        if session.is_none() {
+            let account = self.config.global.account.as_deref();
+            *session = self
+                .auth_manager
+                .login_account(account)
+                .await?
+                .map(|(session_id, _)| session_id);
+        }
+        let session_id = session.unwrap_or_else(uuid::Uuid::new_v4);
+        let mut client = self.auth_manager.get_authed_client(session_id).await?;
+        configure_client(&mut client, &self.config.global)?;
+        Ok(client)
+    }
+
+    // 订阅下载事件，维护进行中的文件任务的进度快照
+    fn track_progress(
+        mut events: tokio::sync::broadcast::Receiver<DownloadEvent>,
+        task_progress: Arc<Mutex<HashMap<String, Value>>>,
+    ) {
+        use tokio::sync::broadcast::error::RecvError;
+
+        tokio::spawn(async move {
+            loop {
+                let event = match events.recv().await {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => break,
+                };
+                let Some(task_id) = event.task_id().map(str::to_string) else {
+                    continue;
+                };
+
+                let mut progress = task_progress.lock().await;
+                // 文件任务结束后移除快照，只保留进行中的任务，作业结果见 active_downloads
+                if matches!(
+                    event,
+                    DownloadEvent::Completed { .. }
+                        | DownloadEvent::Failed { .. }
+                        | DownloadEvent::Cancelled { .. }
+                        | DownloadEvent::Skipped { .. }
+                ) {
+                    progress.remove(&task_id);
+                    continue;
+                }
+                let entry = progress
+                    .entry(task_id.clone())
+                    .or_insert_with(|| json!({ "task_id": task_id }));
+                match event {
+                    DownloadEvent::TaskAdded { name, total_size, .. } => {
+                        entry["name"] = json!(name);
+                        entry["total_size"] = json!(total_size);
+                        entry["status"] = json!("pending");
+                    }
+                    DownloadEvent::Progress { downloaded, total_size, speed, eta_secs, .. } => {
+                        entry["downloaded"] = json!(downloaded);
+                        entry["total_size"] = json!(total_size);
+                        entry["speed"] = json!(speed as u64);
+                        entry["eta_secs"] = json!(eta_secs);
+                    }
+                    DownloadEvent::Started { .. } | DownloadEvent::Resumed { .. } => {
+                        entry["status"] = json!("downloading");
+                    }
+                    DownloadEvent::Retry { attempt, max_retries, .. } => {
+                        entry["status"] = json!(format!("retrying {}/{}", attempt, max_retries));
+                    }
+                    DownloadEvent::Paused { .. } => entry["status"] = json!("paused"),
+                    _ => {}
+                }
+            }
+        });
+    }
+
     // 运行MCP服务器
     pub async fn run(&self) -> anyhow::Result<()> {
         eprintln!("🚀 BiliDownloader MCP Server 启动中...");
@@ -67,6 +181,7 @@ impl BiliMcpServer {
         eprintln!("   - bili_parse_info: 解析视频信息");
         eprintln!("   - bili_list_downloads: 列出下载任务");
         eprintln!("   - bili_cancel_download: 取消下载任务");
+        eprintln!("   - bili_set_rate_limit: 设置下载限速");
         eprintln!("   - bili_login_status: 检查登录状态");
         eprintln!("   - bili_qr_login: 二维码登录");
 
@@ -181,13 +296,13 @@ impl BiliMcpServer {
                             "quality": {
                                 "type": "string",
                                 "description": "视频清晰度",
-                                "enum": ["360p", "480p", "720p", "720p60", "1080p", "1080p+", "1080p60", "4k", "hdr", "8k"],
-                                "default": "1080p"
+                                "enum": ["360p", "480p", "720p", "720p60", "1080p", "1080p+", "1080p60", "4k", "hdr", "dolby", "8k"],
+                                "default": self.config.quality_name()
                             },
                             "output_dir": {
                                 "type": "string",
                                 "description": "输出目录路径",
-                                "default": "./downloads"
+                                "default": self.config.download.output_dir
                             },
                             "parts": {
                                 "type": "string",
@@ -238,6 +353,28 @@ impl BiliMcpServer {
                         "required": ["task_id"]
                     }
                 },
+                {
+                    "name": "bili_set_rate_limit",
+                    "description": "设置下载带宽限制，对正在进行的下载立即生效；不传参数时返回当前设置",
+                    "inputSchema": {
+                        "type": "object",
+                        "properties": {
+                            "global": {
+                                "type": "string",
+                                "description": "所有下载共享的带宽上限，如 500K、5M，0 表示不限速"
+                            },
+                            "per_task": {
+                                "type": "string",
+                                "description": "单个文件的带宽上限，格式同 global"
+                            },
+                            "schedules": {
+                                "type": "array",
+                                "items": { "type": "string" },
+                                "description": "分时段全局限速，如 [\"01:00-07:00=0\"]，传空数组清除"
+                            }
+                        }
+                    }
+                },
                 {
                     "name": "bili_login_status",
                     "description": "检查当前B站登录状态",
@@ -268,6 +405,7 @@ impl BiliMcpServer {
             "bili_parse_info" => self.handle_parse_info(args.clone()).await?,
             "bili_list_downloads" => self.handle_list_downloads(args.clone()).await?,
             "bili_cancel_download" => self.handle_cancel_download(args.clone()).await?,
+            "bili_set_rate_limit" => self.handle_set_rate_limit(args.clone()).await?,
             "bili_login_status" => self.handle_login_status(args.clone()).await?,
             "bili_qr_login" => self.handle_qr_login(args.clone()).await?,
             _ => return Err(anyhow::anyhow!("未知工具: {}", name)),
@@ -325,9 +463,13 @@ impl BiliMcpServer {
         let url = args["url"].as_str()
             .ok_or_else(|| anyhow::anyhow!("缺少url参数"))?;
 
-        let quality = args["quality"].as_str().unwrap_or("1080p");
-        let output_dir = args["output_dir"].as_str().unwrap_or("./downloads");
-        let parts = args["parts"].as_str();
+        // 未指定的参数使用配置中的默认值
+        let defaults = &self.config.download;
+        let default_quality = self.config.quality_name();
+        let default_output_dir = defaults.output_dir.to_string_lossy();
+        let quality = args["quality"].as_str().unwrap_or(&default_quality);
+        let output_dir = args["output_dir"].as_str().unwrap_or(&default_output_dir);
+        let parts = args["parts"].as_str().or(defaults.parts.as_deref());
 
         // 初始化下载管理器
         self.init_downloader().await?;
@@ -343,6 +485,7 @@ impl BiliMcpServer {
             "1080p60" => VideoQuality::Q1080P60,
             "4k" => VideoQuality::Q4K,
             "hdr" => VideoQuality::QHdr,
+            "dolby" => VideoQuality::QDolby,
             "8k" => VideoQuality::Q8K,
             _ => VideoQuality::Q1080P,
         };
@@ -350,72 +493,68 @@ impl BiliMcpServer {
         // 创建解析选项
         let options = if url.contains("/bangumi/play/") {
             ParserOptions::Bangumi {
-                config: DownloadConfig {
-                    resolution: video_quality,
-                    need_audio: true,
-                    need_video: true,
-                    need_subtitle: false,
-                    need_danmaku: false,
-                    concurrency: 3,
-                    episode_range: parts.map(|s| s.to_string()),
-                    merge: true,
-                    output_dir: output_dir.to_string(),
-                    output_format: "mp4".to_string(),
-                },
+                config: self.download_config(video_quality, output_dir, parts),
             }
         } else {
             ParserOptions::CommonVideo {
-                config: DownloadConfig {
-                    resolution: video_quality,
-                    need_audio: true,
-                    need_video: true,
-                    need_subtitle: false,
-                    need_danmaku: false,
-                    concurrency: 3,
-                    episode_range: parts.map(|s| s.to_string()),
-                    merge: true,
-                    output_dir: output_dir.to_string(),
-                    output_format: "mp4".to_string(),
-                },
+                config: self.download_config(video_quality, output_dir, parts),
             }
         };
 
         // 创建客户端
-        let client = self.auth_manager.get_authed_client(uuid::Uuid::new_v4()).await?;
+        let client = self.client().await?;
 
         // 解析视频信息
         let mut parser = VideoParser::new(client.clone(), true);
         let parsed_metas = parser.parse(url, &options).await?;
 
         // 开始下载
+        let task_id = self.spawn_download(parsed_metas.download_items).await;
+
+        Ok(json!([{
+            "type": "text",
+            "text": format!("开始下载: {} (任务ID: {})", url, task_id)
+        }]))
+    }
+
+    // 在后台执行下载作业，返回作业ID
+    async fn spawn_download(&self, mut tasks: Vec<DownloadTask>) -> String {
         let task_id = uuid::Uuid::new_v4().to_string();
+        let cancel = CancellationToken::new();
         {
             let mut downloads = self.active_downloads.lock().await;
             downloads.insert(task_id.clone(), "downloading".to_string());
+            let mut tokens = self.download_tokens.lock().await;
+            tokens.insert(task_id.clone(), cancel.clone());
         }
 
         // 异步执行下载
         let downloads_clone = self.active_downloads.clone();
+        let tokens_clone = self.download_tokens.clone();
         let task_id_clone = task_id.clone();
-        let download_manager = self.download_manager.clone();
+        // 取出下载器后立即释放锁，下载期间其他请求仍可访问下载管理器
+        let manager = self.download_manager.lock().await.clone();
 
         tokio::spawn(async move {
-            let mut task = parsed_metas.download_items.clone();
-            if let Some(manager) = download_manager.lock().await.as_ref() {
-                let result = manager.download(&mut task).await;
-                let mut downloads = downloads_clone.lock().await;
-                if result.is_ok() {
-                    downloads.insert(task_id_clone, "completed".to_string());
+            if let Some(manager) = manager {
+                let report = manager.download_with_cancel(&mut tasks, &cancel).await;
+                let status = if cancel.is_cancelled() {
+                    "cancelled"
+                } else if report.is_success() {
+                    "completed"
+                } else if report.failed().next().is_none() {
+                    "skipped"
                 } else {
-                    downloads.insert(task_id_clone, "failed".to_string());
-                }
+                    "failed"
+                };
+                downloads_clone
+                    .lock()
+                    .await
+                    .insert(task_id_clone.clone(), status.to_string());
             }
+            tokens_clone.lock().await.remove(&task_id_clone);
         });
-
-        Ok(json!([{
-            "type": "text",
-            "text": format!("开始下载: {} (任务ID: {})", url, task_id)
-        }]))
+        task_id
     }
 
     async fn handle_parse_info(&self, args: Value) -> anyhow::Result<Value> {
@@ -423,7 +562,7 @@ impl BiliMcpServer {
             .ok_or_else(|| anyhow::anyhow!("缺少url参数"))?;
 
         // 创建客户端
-        let client = self.auth_manager.get_authed_client(uuid::Uuid::new_v4()).await?;
+        let client = self.client().await?;
 
         // 解析视频信息
         let mut parser = VideoParser::new(client.clone(), true);
@@ -439,6 +578,7 @@ impl BiliMcpServer {
                 merge: false,
                 output_dir: "./downloads".to_string(),
                 output_format: "mp4".to_string(),
+                ..Default::default()
             },
         };
 
@@ -466,10 +606,59 @@ impl BiliMcpServer {
             }))
             .collect();
 
+        let files: Vec<Value> = self.task_progress.lock().await.values().cloned().collect();
+
         Ok(json!([{
             "type": "text",
             "text": json!({
-                "active_downloads": downloads_list
+                "active_downloads": downloads_list,
+                "files": files
+            }).to_string()
+        }]))
+    }
+
+    async fn handle_set_rate_limit(&self, args: Value) -> anyhow::Result<Value> {
+        // 下载进行中时直接使用已有的核心句柄，只在尚未初始化时创建下载管理器
+        let core = self.download_core.lock().await.clone();
+        let core = match core {
+            Some(core) => core,
+            None => {
+                self.init_downloader().await?;
+                self.download_core
+                    .lock()
+                    .await
+                    .clone()
+                    .ok_or_else(|| anyhow::anyhow!("下载管理器未初始化"))?
+            }
+        };
+
+        let mut config: RateLimitConfig = core.rate_limits();
+        if let Some(global) = args["global"].as_str() {
+            config.global = parse_rate(global).map_err(|e| anyhow::anyhow!(e))?;
+        }
+        if let Some(per_task) = args["per_task"].as_str() {
+            config.per_task = parse_rate(per_task).map_err(|e| anyhow::anyhow!(e))?;
+        }
+        if let Some(schedules) = args["schedules"].as_array() {
+            config.schedules = schedules
+                .iter()
+                .map(|s| {
+                    s.as_str()
+                        .unwrap_or_default()
+                        .parse::<RateSchedule>()
+                        .map_err(|e| anyhow::anyhow!(e))
+                })
+                .collect::<anyhow::Result<_>>()?;
+        }
+        core.apply_rate_limits(&config);
+
+        Ok(json!([{
+            "type": "text",
+            "text": json!({
+                "global": config.global,
+                "per_task": config.per_task,
+                "schedules": config.schedules.iter().map(|s| s.to_string()).collect::<Vec<_>>(),
+                "effective_global": core.effective_rate_limit()
             }).to_string()
         }]))
     }
@@ -478,17 +667,26 @@ impl BiliMcpServer {
         let task_id = args["task_id"].as_str()
             .ok_or_else(|| anyhow::anyhow!("缺少task_id参数"))?;
         
+        // 取消令牌会传递到该作业的所有下载任务，部分文件保留以便之后续传
+        let token = self.download_tokens.lock().await.get(task_id).cloned();
         let mut downloads = self.active_downloads.lock().await;
-        if downloads.remove(task_id).is_some() {
-            Ok(json!([{
+        match (token, downloads.get(task_id)) {
+            (Some(token), _) => {
+                token.cancel();
+                downloads.insert(task_id.to_string(), "cancelled".to_string());
+                Ok(json!([{
+                    "type": "text",
+                    "text": format!("已取消任务: {}", task_id)
+                }]))
+            }
+            (None, Some(status)) => Ok(json!([{
                 "type": "text",
-                "text": format!("已取消任务: {}", task_id)
-            }]))
-        } else {
-            Ok(json!([{
+                "text": format!("任务已结束，无法取消: {} ({})", task_id, status)
+            }])),
+            (None, None) => Ok(json!([{
                 "type": "text",
                 "text": "任务不存在"
-            }]))
+            }])),
         }
     }
 
@@ -517,12 +715,14 @@ impl BiliMcpServer {
 
     async fn get_active_downloads_content(&self) -> String {
         let downloads = self.active_downloads.lock().await;
+        let files: Vec<Value> = self.task_progress.lock().await.values().cloned().collect();
         json!({
             "active_downloads": downloads.len(),
             "tasks": downloads.iter().map(|(id, status)| json!({
                 "task_id": id,
                 "status": status
-            })).collect::<Vec<_>>()
+            })).collect::<Vec<_>>(),
+            "files": files
         }).to_string()
     }
 
@@ -533,12 +733,139 @@ impl BiliMcpServer {
         }).to_string()
     }
 
+    // 工具调用的下载配置：清晰度、目录、集数来自调用参数，其余使用配置中的默认值
+    fn download_config(
+        &self,
+        resolution: VideoQuality,
+        output_dir: &str,
+        parts: Option<&str>,
+    ) -> DownloadConfig {
+        let defaults = &self.config.download;
+        DownloadConfig {
+            resolution,
+            need_audio: defaults.need_audio,
+            need_video: defaults.need_video,
+            need_subtitle: defaults.need_subtitle,
+            need_danmaku: defaults.need_danmaku,
+            concurrency: defaults.concurrency,
+            episode_range: parts.map(|s| s.to_string()),
+            merge: defaults.merge,
+            output_dir: output_dir.to_string(),
+            output_format: "mp4".to_string(),
+            selector: defaults.select.clone(),
+            upgrade: defaults.upgrade,
+            ..Default::default()
+        }
+    }
+
     async fn get_settings_content(&self) -> String {
-        json!({
-            "default_quality": "1080p",
-            "output_directory": "./downloads",
-            "concurrency": 3,
-            "auto_merge": true
-        }).to_string()
+        self.config.to_json().to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::downloader::models::FileType;
+    use crate::downloader::test_server::TestServer;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_progress_pruned_when_task_ends() {
+        let events = crate::downloader::events::event_channel();
+        let task_progress = Arc::new(Mutex::new(HashMap::new()));
+        BiliMcpServer::track_progress(events.subscribe(), task_progress.clone());
+
+        for task_id in ["a", "b"] {
+            events
+                .send(DownloadEvent::TaskAdded {
+                    task_id: task_id.to_string(),
+                    name: task_id.to_string(),
+                    file_type: FileType::Video,
+                    total_size: 1024,
+                })
+                .unwrap();
+        }
+        events
+            .send(DownloadEvent::Completed {
+                task_id: "a".to_string(),
+            })
+            .unwrap();
+        drop(events);
+
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while Arc::strong_count(&task_progress) > 1 {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        let progress = task_progress.lock().await;
+        assert_eq!(progress.keys().collect::<Vec<_>>(), ["b"]);
+    }
+
+    #[tokio::test]
+    async fn test_set_rate_limit_during_download() {
+        // 约 2 秒才能发送完的文件，保证修改限速时下载仍在进行
+        let server = TestServer::start(vec![0; 64 * 1024], 1024, Duration::from_millis(30)).await;
+        let output =
+            std::env::temp_dir().join(format!("bilidl-mcp-{}.m4s", uuid::Uuid::new_v4()));
+        let mcp = BiliMcpServer::new();
+        mcp.init_downloader().await.unwrap();
+        let job = mcp
+            .spawn_download(vec![DownloadTask::new(
+                server.url.clone(),
+                FileType::Video,
+                "video".to_string(),
+                output.to_string_lossy().into_owned(),
+                String::new(),
+                HashMap::new(),
+            )])
+            .await;
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while !mcp
+                .task_progress
+                .lock()
+                .await
+                .values()
+                .any(|file| file["status"] == "downloading")
+            {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("下载未开始");
+
+        // 下载进行中修改限速与取消都应立即返回
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            mcp.handle_set_rate_limit(json!({ "global": "512K" })),
+        )
+        .await
+        .expect("修改限速被进行中的下载阻塞")
+        .unwrap();
+        let core = mcp.download_core.lock().await.clone().unwrap();
+        assert_eq!(core.rate_limits().global, 512 * 1024);
+
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            mcp.handle_cancel_download(json!({ "task_id": job })),
+        )
+        .await
+        .expect("取消下载被进行中的下载阻塞")
+        .unwrap();
+        // 作业结束后释放取消令牌
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while mcp.download_tokens.lock().await.contains_key(&job) {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+            }
+        })
+        .await
+        .expect("取消后作业未结束");
+        assert_eq!(mcp.active_downloads.lock().await[&job], "cancelled");
+
+        crate::downloader::partial::discard(&output).await;
+        let _ = tokio::fs::remove_file(&output).await;
     }
 }
@@ -0,0 +1,142 @@
+use std::io::Read;
+use std::path::Path;
+
+use clap::ValueEnum;
+use url::Url;
+
+use super::errors::ParseError;
+use super::url_parser::UrlParser;
+use crate::cli::QualityOption;
+
+/// 批量输入中的一行
+///
+/// 每行为链接或裸ID（BV/av/ep/ss/cp/cs），后面可以跟 `--parts` 和 `--quality` 覆盖全局设置：
+///
+/// ```text
+/// # 注释和空行会被忽略
+/// BV1xx411c7mD
+/// https://www.bilibili.com/bangumi/play/ss12345 --parts 1-3 --quality 4k
+/// ep67890 --quality=720p
+/// ```
+#[derive(Debug, Clone)]
+pub struct BatchEntry {
+    pub line: usize,   // 行号，从 1 开始
+    pub input: String, // 原始链接或ID
+    pub url: String,   // 裸ID展开后的链接
+    pub parts: Option<String>,
+    pub quality: Option<QualityOption>,
+}
+
+/// 读取批量输入，路径为 `-` 时从标准输入读取
+pub fn read_batch_input(path: &Path) -> Result<String, ParseError> {
+    let result = if path == Path::new("-") {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map(|_| content)
+    } else {
+        std::fs::read_to_string(path)
+    };
+    result.map_err(|e| ParseError::ParseError(format!("读取批量输入 {:?} 失败: {}", path, e)))
+}
+
+/// 解析批量输入，任意一行格式错误时返回带行号的错误
+pub fn parse_batch(content: &str) -> Result<Vec<BatchEntry>, ParseError> {
+    let url_parser = UrlParser::new();
+    let mut entries = Vec::new();
+    for (index, raw) in content.lines().enumerate() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let entry = parse_line(&url_parser, index + 1, line)
+            .map_err(|e| ParseError::ParseError(format!("批量输入第 {} 行: {}", index + 1, e)))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+fn parse_line(url_parser: &UrlParser, line: usize, text: &str) -> Result<BatchEntry, String> {
+    let mut tokens = text.split_whitespace();
+    let input = tokens.next().unwrap_or_default().to_string();
+    let url = if Url::parse(&input).is_ok() {
+        input.clone()
+    } else {
+        url_parser
+            .handle_raw_id(&input)
+            .map_err(|_| format!("无法识别的链接或ID {:?}", input))?
+    };
+
+    let mut entry = BatchEntry {
+        line,
+        input,
+        url,
+        parts: None,
+        quality: None,
+    };
+    while let Some(token) = tokens.next() {
+        // 同时支持 `--parts 1-3` 和 `--parts=1-3`
+        let (flag, inline_value) = match token.split_once('=') {
+            Some((flag, value)) => (flag, Some(value.to_string())),
+            None => (token, None),
+        };
+        let value = match inline_value {
+            Some(value) => value,
+            None => tokens
+                .next()
+                .map(str::to_string)
+                .ok_or_else(|| format!("{} 缺少参数值", flag))?,
+        };
+        match flag {
+            "--parts" => entry.parts = Some(value),
+            "--quality" => {
+                entry.quality = Some(
+                    QualityOption::from_str(&value, true)
+                        .map_err(|_| format!("无效的清晰度 {:?}", value))?,
+                )
+            }
+            _ => {
+                return Err(format!(
+                    "不支持的选项 {:?}，每行只能指定 --parts 和 --quality",
+                    flag
+                ));
+            }
+        }
+    }
+    Ok(entry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_batch() {
+        let entries = parse_batch(
+            r#"
+# 收藏的视频
+BV1xx411c7mD
+https://www.bilibili.com/bangumi/play/ss12345 --parts 1-3 --quality 4k
+ep67890 --quality=720p
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 3);
+        assert_eq!(
+            entries[0].url,
+            "https://www.bilibili.com/video/BV1xx411c7mD"
+        );
+        assert_eq!(entries[0].line, 3);
+        assert_eq!(entries[1].parts.as_deref(), Some("1-3"));
+        assert!(matches!(entries[1].quality, Some(QualityOption::Q4K)));
+        assert_eq!(
+            entries[2].url,
+            "https://www.bilibili.com/bangumi/play/ep67890"
+        );
+        assert!(matches!(entries[2].quality, Some(QualityOption::Q720P)));
+
+        assert!(parse_batch("BV1xx411c7mD --output ./a").is_err());
+        assert!(parse_batch("not-an-id").is_err());
+    }
+}
@@ -6,12 +6,20 @@ use tracing::debug;
 use crate::common::client::client::BiliClient;
 use crate::common::client::models::common::CommonResponse;
 use crate::common::models::DownloadType;
+use crate::downloader::archive::ArchiveKey;
 use crate::downloader::models::DownloadTask;
-use crate::parser::detail_parser::stream_utils::{select_audio_stream, select_video_stream};
-use crate::parser::detail_parser::task_utils::{create_audio_task, create_video_task};
+use crate::parser::detail_parser::stream_utils::{
+    select_audio_stream, select_durl_segments, select_video_stream,
+};
+use crate::parser::detail_parser::task_utils::{
+    create_audio_metadata, create_audio_only_tasks, create_audio_task, create_durl_tasks,
+    create_video_task, skip_archived, with_archive_key, with_quality_metadata,
+};
 use crate::parser::detail_parser::Parser;
 use crate::parser::detail_parser::models::{DownloadConfig, PlayUrlData};
-use crate::parser::detail_parser::parser_trait::{ParserOptions, parse_episode_range};
+use crate::parser::detail_parser::parser_trait::{
+    ParserOptions, parse_episode_range, request_play_url,
+};
 use crate::parser::models::UrlType;
 use crate::parser::detail_parser::error_utils::handle_api_error;
 use crate::parser::{ParsedMeta, errors::ParseError};
@@ -86,6 +94,12 @@ pub struct BangumiInfo {
     media_info: Option<MediaInfo>, // 媒体信息
     episodes: Vec<Episode>, // 集数列表
     total: u32,    // 总集数
+    up_info: Option<UpInfo>, // 出品方/UP主信息
+}
+
+#[derive(Debug, Deserialize)]
+struct UpInfo {
+    uname: String,
 }
 
 // 整季查询策略
@@ -163,18 +177,38 @@ impl<'a> BangumiParser<'a> {
     }
 
     // 获取播放地址
-    async fn get_play_url(&self, ep_id: &str, cid: i64) -> Result<PlayUrlData, ParseError> {
+    async fn get_play_url(
+        &self,
+        ep_id: &str,
+        cid: i64,
+        config: &DownloadConfig,
+    ) -> Result<PlayUrlData, ParseError> {
+        request_play_url(config.play_stream_type(), |fnval| {
+            self.fetch_play_url(ep_id, cid, config.request_qn(), fnval)
+        })
+        .await
+    }
+
+    async fn fetch_play_url(
+        &self,
+        ep_id: &str,
+        cid: i64,
+        qn: i32,
+        fnval: u32,
+    ) -> Result<PlayUrlData, ParseError> {
         let params = HashMap::from([
             ("ep_id".to_string(), ep_id.to_string()),
             ("cid".to_string(), cid.to_string()),
-            // ("qn".to_string(), (quality as i32).to_string()),
-            ("fnval".to_string(), "976".to_string()),
+            ("qn".to_string(), qn.to_string()),
+            ("fnval".to_string(), fnval.to_string()),
             ("fnver".to_string(), "0".to_string()),
             ("fourk".to_string(), "1".to_string()),
         ]);
 
+        // 播放地址可单独走地区代理，解除番剧的地区限制
         let resp = self
             .client
+            .playurl_client()
             .get_auto::<CommonResponse<PlayUrlData>>(
                 "https://api.bilibili.com/pgc/player/web/playurl",
                 params,
@@ -194,57 +228,129 @@ impl<'a> BangumiParser<'a> {
     // 创建单集视频的元数据
     async fn create_episode_meta(
         &self,
-        title: &str,
+        bangumi_info: &BangumiInfo,
         episode: &Episode,
         config: &DownloadConfig,
     ) -> Result<Vec<DownloadTask>, ParseError> {
+        let title = bangumi_info.title.as_str();
+        let archive_key = ArchiveKey::new("bangumi", episode.id, episode.cid, config.audio_only);
+        let archive_name = format!("{} - {}", title, episode.title);
+        if skip_archived(config, &archive_key, &archive_name) {
+            return Ok(Vec::new());
+        }
+
         let play_info = self
-            .get_play_url(&episode.id.to_string(), episode.cid)
+            .get_play_url(&episode.id.to_string(), episode.cid, config)
             .await
             .map_err(|e| ParseError::ParseError(e.to_string()))?;
 
         let mut download_task_vec: Vec<DownloadTask> = Vec::new();
 
         // --------------------------------------------------------------------
-        let video_stream_task = if config.need_video && play_info.dash.is_some() {
-            select_video_stream(&play_info.dash.as_ref().unwrap().video, config.resolution)?
-                .map(|video_url| {
+        if config.audio_only {
+            let dash = play_info
+                .dash
+                .as_ref()
+                .ok_or_else(|| ParseError::ParseError("仅音频模式需要 DASH 音频流".to_string()))?;
+            let track_title = if episode.long_title.is_empty() {
+                &episode.title
+            } else {
+                &episode.long_title
+            };
+            let artist = bangumi_info
+                .up_info
+                .as_ref()
+                .map(|up| up.uname.as_str())
+                .unwrap_or_default();
+            let track = bangumi_info
+                .episodes
+                .iter()
+                .position(|ep| ep.id == episode.id)
+                .map(|index| index + 1);
+            download_task_vec.extend(create_audio_only_tasks(
+                dash,
+                play_info.timelength,
+                title,
+                Some(&episode.title),
+                &config.output_dir,
+                Some(&episode.cover),
+                create_audio_metadata(track_title, artist, title, track),
+            )?);
+            return Ok(with_archive_key(download_task_vec, config, &archive_key, &archive_name));
+        }
+
+        // --------------------------------------------------------------------
+        let video_stream_task = if config.wants_video() && play_info.dash.is_some() {
+            select_video_stream(&play_info, &config.video_selector())?
+                .map(|video| {
                     create_video_task(
-                        video_url,
+                        video.base_url.clone(),
                         title,
                         Some(&episode.title),
                         &config.output_dir,
-                        HashMap::new(),
+                        with_quality_metadata(
+                            HashMap::new(),
+                            &play_info,
+                            video.id,
+                            &video.codecs,
+                        ),
                     )
+                    .with_mirrors(video.backup_urls())
+                    .with_estimated_size(video.estimated_size(play_info.timelength))
                 })
         } else {
             None
         };
 
         // --------------------------------------------------------------------
-        let audio_stream_task = if config.need_audio && play_info.dash.is_some() {
+        let audio_stream_task = if config.wants_audio() && play_info.dash.is_some() {
             select_audio_stream(&play_info.dash.as_ref().unwrap().audio)?
-                .map(|audio_url| {
+                .map(|audio| {
                     create_audio_task(
-                        audio_url,
+                        audio.base_url.clone(),
                         title,
                         Some(&episode.title),
                         &config.output_dir,
                         HashMap::new(),
                     )
+                    .with_mirrors(audio.backup_urls())
+                    .with_estimated_size(audio.estimated_size(play_info.timelength))
                 })
         } else {
             None
         };
 
+        // --------------------------------------------------------------------
+        let durl_tasks = if config.wants_video() && play_info.dash.is_none() {
+            select_durl_segments(&play_info, &config.video_selector())
+                .map(|selected| {
+                    create_durl_tasks(
+                        &selected.durl,
+                        &play_info.format,
+                        title,
+                        Some(&episode.title),
+                        with_quality_metadata(
+                            HashMap::new(),
+                            &play_info,
+                            selected.quality,
+                            "",
+                        ),
+                    )
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
         if let Some(video_task) = video_stream_task {
             download_task_vec.push(video_task);
         }
+        download_task_vec.extend(durl_tasks);
         if let Some(audio_task) = audio_stream_task {
             download_task_vec.push(audio_task);
         }
 
-        Ok(download_task_vec)
+        Ok(with_archive_key(download_task_vec, config, &archive_key, &archive_name))
     }
 }
 
@@ -268,7 +374,7 @@ impl<'a> Parser for BangumiParser<'a> {
                 debug!("番剧 {} 共有 {} 集", bangumi_info.title, bangumi_info.total);
 
                 let download_items = self
-                    .create_episode_meta(&bangumi_info.title, &episode, &config)
+                    .create_episode_meta(&bangumi_info, &episode, &config)
                     .await
                     .map_err(|e| ParseError::ParseError(e.to_string()))?;
 
@@ -317,7 +423,7 @@ impl<'a> Parser for BangumiParser<'a> {
                 for episode in episodes_to_download {
                     debug!("处理集数: {} - {}", episode.id, episode.title);
                     let episode_tasks = self
-                        .create_episode_meta(&bangumi_info.title, episode, &config)
+                        .create_episode_meta(&bangumi_info, episode, &config)
                         .await?;
                     debug!("获取到集数 {} 成功", episode.id);
                     season_download_tasks.extend(episode_tasks);
@@ -1,14 +1,20 @@
 use crate::common::client::client::BiliClient;
 use crate::common::client::models::common::CommonResponse;
 use crate::common::models::{DownloadType, ParsedMeta};
-use crate::downloader::models::{DownloadTask, FileType};
+use crate::downloader::archive::ArchiveKey;
+use crate::downloader::models::DownloadTask;
 use crate::parser::detail_parser::Parser;
 use crate::parser::detail_parser::danmaku_handler::DanmakuHandler;
 use crate::parser::detail_parser::error_utils::handle_api_error;
 use crate::parser::detail_parser::models::{DashItem, DownloadConfig, PlayUrlData};
-use crate::parser::detail_parser::parser_trait::ParserOptions;
-use crate::parser::detail_parser::stream_utils::{select_audio_stream, select_video_stream};
-use crate::parser::detail_parser::task_utils::{create_audio_task, create_danmaku_task, create_video_task};
+use crate::parser::detail_parser::parser_trait::{ParserOptions, request_play_url};
+use crate::parser::detail_parser::stream_utils::{
+    select_audio_stream, select_durl_segments, select_video_stream,
+};
+use crate::parser::detail_parser::task_utils::{
+    create_audio_metadata, create_audio_only_tasks, create_audio_task, create_danmaku_task,
+    create_durl_tasks, create_video_task, skip_archived, with_archive_key, with_quality_metadata,
+};
 use crate::parser::errors::ParseError;
 use crate::parser::models::{UrlType, VideoQuality};
 
@@ -94,25 +100,35 @@ impl<'a> CommonVideoParser<'a> {
             .ok_or_else(|| ParseError::ParseError("API响应中未找到视频信息".to_string()))
     }
 
+    // 获取播放地址，按首选流格式请求并在不可用时回退
     async fn get_play_url(
         &self,
         video_info: &CommonVideoInfo,
         config: &DownloadConfig,
+    ) -> Result<PlayUrlData, ParseError> {
+        request_play_url(config.play_stream_type(), |fnval| {
+            self.fetch_play_url(video_info, config, fnval)
+        })
+        .await
+    }
+
+    async fn fetch_play_url(
+        &self,
+        video_info: &CommonVideoInfo,
+        config: &DownloadConfig,
+        fnval: u32,
     ) -> Result<PlayUrlData, ParseError> {
         let params = HashMap::from([
             ("bvid".to_string(), video_info.bvid.clone()),
             ("cid".to_string(), video_info.cid.to_string()),
-            ("qn".to_string(), (config.resolution as i32).to_string()), // 设置清晰度
-            ("fnval".to_string(), "16".to_string()),                    // 16表示需要音视频分离
-            ("fourk".to_string(), "1".to_string()),                     // 1表示需要4K视频
-            ("fnver".to_string(), "0".to_string()),                     // 0表示使用最新版本
+            ("qn".to_string(), config.request_qn().to_string()), // 设置清晰度
+            ("fnval".to_string(), fnval.to_string()),            // 流格式位掩码
+            ("fourk".to_string(), "1".to_string()),              // 1表示需要4K视频
+            ("fnver".to_string(), "0".to_string()),              // 0表示使用最新版本
         ]);
 
         debug!("请求播放地址参数: {:?}", params);
-        debug!(
-            "目标清晰度: {:?} ({})",
-            config.resolution, config.resolution as i32
-        );
+        debug!("目标清晰度: {}", config.request_qn());
 
         let resp = self
             .client
@@ -130,13 +146,6 @@ impl<'a> CommonVideoParser<'a> {
 
         resp.data
             .ok_or_else(|| ParseError::ParseError("未找到播放地址信息".to_string()))
-            .and_then(|data| {
-                if data.dash.is_none() && data.durl.is_none() {
-                    Err(ParseError::ParseError("未解析出播放地址".to_string()))
-                } else {
-                    Ok(data)
-                }
-            })
     }
 
     async fn create_video_meta(
@@ -144,6 +153,11 @@ impl<'a> CommonVideoParser<'a> {
         video_info: &CommonVideoInfo,
         config: &DownloadConfig,
     ) -> Result<Vec<DownloadTask>, ParseError> {
+        let archive_key = ArchiveKey::new("video", &video_info.bvid, video_info.cid, config.audio_only);
+        if skip_archived(config, &archive_key, &video_info.title) {
+            return Ok(Vec::new());
+        }
+
         let play_info = self.get_play_url(video_info, config).await?;
         debug!("获取到播放地址信息: {:?}", play_info);
 
@@ -168,16 +182,46 @@ impl<'a> CommonVideoParser<'a> {
         }
 
         // --------------------------------------------------------------------
-        let video_stream_task = if config.need_video && play_info.dash.is_some() {
-            select_video_stream(&play_info.dash.as_ref().unwrap().video, config.resolution)?
-                .map(|video_url| {
+        if config.audio_only {
+            let dash = play_info
+                .dash
+                .as_ref()
+                .ok_or_else(|| ParseError::ParseError("仅音频模式需要 DASH 音频流".to_string()))?;
+            let album = video_info
+                .ugc_season
+                .as_ref()
+                .map(|season| season.title.as_str())
+                .unwrap_or(&video_info.title);
+            download_task_vec.extend(create_audio_only_tasks(
+                dash,
+                play_info.timelength,
+                &video_info.title,
+                None,
+                &config.output_dir,
+                Some(&video_info.pic),
+                create_audio_metadata(&video_info.title, &video_info.owner.name, album, Some(1)),
+            )?);
+            return Ok(with_archive_key(download_task_vec, config, &archive_key, &video_info.title));
+        }
+
+        // --------------------------------------------------------------------
+        let video_stream_task = if config.wants_video() && play_info.dash.is_some() {
+            select_video_stream(&play_info, &config.video_selector())?
+                .map(|video| {
                     create_video_task(
-                        video_url,
+                        video.base_url.clone(),
                         &video_info.title,
                         None,
                         &config.output_dir,
-                        HashMap::from([("desc".to_string(), video_info.desc.clone())]),
+                        with_quality_metadata(
+                            HashMap::from([("desc".to_string(), video_info.desc.clone())]),
+                            &play_info,
+                            video.id,
+                            &video.codecs,
+                        ),
                     )
+                    .with_mirrors(video.backup_urls())
+                    .with_estimated_size(video.estimated_size(play_info.timelength))
                 })
         } else {
             None
@@ -188,16 +232,18 @@ impl<'a> CommonVideoParser<'a> {
         }
 
         // --------------------------------------------------------------------
-        let audio_stream_task = if config.need_audio && play_info.dash.is_some() {
+        let audio_stream_task = if config.wants_audio() && play_info.dash.is_some() {
             select_audio_stream(&play_info.dash.as_ref().unwrap().audio)?
-                .map(|audio_url| {
+                .map(|audio| {
                     create_audio_task(
-                        audio_url,
+                        audio.base_url.clone(),
                         &video_info.title,
                         None,
                         &config.output_dir,
                         HashMap::from([("desc".to_string(), video_info.desc.clone())]),
                     )
+                    .with_mirrors(audio.backup_urls())
+                    .with_estimated_size(audio.estimated_size(play_info.timelength))
                 })
         } else {
             None
@@ -208,34 +254,28 @@ impl<'a> CommonVideoParser<'a> {
         }
 
         // --------------------------------------------------------------------
-        let mp4_stream_task: Option<DownloadTask> = if config.need_video && play_info.durl.is_some()
-        {
-            let play_info = play_info
-                .durl
-                .as_ref()
-                .unwrap()
-                .first()
+        if config.wants_video() && play_info.dash.is_none() {
+            let selected = select_durl_segments(&play_info, &config.video_selector())
+                .filter(|selected| !selected.durl.is_empty())
                 .ok_or_else(|| ParseError::ParseError("未找到 MP4 流信息".to_string()))?;
-            debug!("MP4流信息: {:?}", play_info);
-
-            Some(DownloadTask::new(
-                play_info.url.clone(),
-                FileType::Video,
-                video_info.title.clone() + ".mp4",
-                format!("./tmp/{}-durl-video.mp4", video_info.title),
-                format!("./tmp/{}-durl-video.mp4", video_info.title),
-                HashMap::from([("desc".to_string(), video_info.desc.clone())]),
-            ))
-        } else {
-            None
-        };
+            debug!("MP4流信息: {:?}", selected.durl);
 
-        for task in mp4_stream_task {
-            download_task_vec.push(task);
+            download_task_vec.extend(create_durl_tasks(
+                &selected.durl,
+                &play_info.format,
+                &video_info.title,
+                None,
+                with_quality_metadata(
+                    HashMap::from([("desc".to_string(), video_info.desc.clone())]),
+                    &play_info,
+                    selected.quality,
+                    "",
+                ),
+            ));
         }
 
         // --------------------------------------------------------------------
-        Ok(download_task_vec)
+        Ok(with_archive_key(download_task_vec, config, &archive_key, &video_info.title))
     }
 }
 
@@ -274,6 +314,12 @@ pub struct CommonVideoInfo {
     owner: OwnerInfo,
     cid: i64,
     bvid: String,
+    ugc_season: Option<UgcSeasonInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UgcSeasonInfo {
+    title: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -4,12 +4,20 @@ use tracing::debug;
 
 use crate::common::models::{DownloadType, ParsedMeta};
 use crate::common::{client::client::BiliClient, client::models::common::CommonResponse};
+use crate::downloader::archive::ArchiveKey;
 use crate::downloader::models::DownloadTask;
 use crate::parser::detail_parser::error_utils::handle_api_error;
 use crate::parser::detail_parser::models::{CourseEpisode, CourseInfo, DownloadConfig};
-use crate::parser::detail_parser::parser_trait::{ParserOptions, parse_episode_range};
-use crate::parser::detail_parser::stream_utils::{select_audio_stream, select_video_stream};
-use crate::parser::detail_parser::task_utils::{create_audio_task, create_video_task};
+use crate::parser::detail_parser::parser_trait::{
+    ParserOptions, parse_episode_range, request_play_url,
+};
+use crate::parser::detail_parser::stream_utils::{
+    select_audio_stream, select_durl_segments, select_video_stream,
+};
+use crate::parser::detail_parser::task_utils::{
+    create_audio_metadata, create_audio_only_tasks, create_audio_task, create_durl_tasks,
+    create_video_task, skip_archived, with_archive_key, with_quality_metadata,
+};
 use crate::parser::models::UrlType;
 use crate::parser::{
     detail_parser::{Parser, models::PlayUrlData},
@@ -61,20 +69,35 @@ impl<'a> CourseParser<'a> {
             .ok_or_else(|| ParseError::ParseError("API响应中未找到课程信息".to_string()))
     }
 
-    // 获取播放地址
+    // 获取播放地址，按首选流格式请求并在不可用时回退
     async fn get_play_url(
         &self,
         ep_id: i64,
         aid: i64,
         cid: i64,
+        config: &DownloadConfig,
+    ) -> Result<PlayUrlData, ParseError> {
+        request_play_url(config.play_stream_type(), |fnval| {
+            self.fetch_play_url(ep_id, aid, cid, config.request_qn(), fnval)
+        })
+        .await
+    }
+
+    async fn fetch_play_url(
+        &self,
+        ep_id: i64,
+        aid: i64,
+        cid: i64,
+        qn: i32,
+        fnval: u32,
     ) -> Result<PlayUrlData, ParseError> {
         let params = HashMap::from([
             (String::from("avid"), aid.to_string()),
             (String::from("cid"), cid.to_string()),
             (String::from("ep_id"), ep_id.to_string()),
-            (String::from("qn"), String::from("116")), // 画质参数
+            (String::from("qn"), qn.to_string()), // 画质参数
             (String::from("fnver"), String::from("0")), // 固定值
-            (String::from("fnval"), String::from("976")), // 固定值
+            (String::from("fnval"), fnval.to_string()), // 流格式位掩码
             (String::from("fourk"), String::from("1")),
         ]);
 
@@ -99,57 +122,125 @@ impl<'a> CourseParser<'a> {
     // 根据单集课程信息创建视频元数据
     async fn create_video_meta(
         &self,
-        title: &str,
+        course_info: &CourseInfo,
         episode: &CourseEpisode,
         config: &DownloadConfig,
     ) -> Result<Vec<DownloadTask>, ParseError> {
+        let title = course_info.title.as_str();
+        let archive_key = ArchiveKey::new("course", episode.id, episode.cid, config.audio_only);
+        let archive_name = format!("{} - {}", title, episode.title);
+        if skip_archived(config, &archive_key, &archive_name) {
+            return Ok(Vec::new());
+        }
+
         let play_info = self
-            .get_play_url(episode.id, episode.aid, episode.cid)
+            .get_play_url(episode.id, episode.aid, episode.cid, config)
             .await?;
 
         let mut download_task_vec: Vec<DownloadTask> = Vec::new();
 
         // --------------------------------------------------------------------
-        let video_stream_task = if config.need_video && play_info.dash.is_some() {
-            select_video_stream(&play_info.dash.as_ref().unwrap().video, config.resolution)?
-                .map(|video_url| {
+        if config.audio_only {
+            let dash = play_info
+                .dash
+                .as_ref()
+                .ok_or_else(|| ParseError::ParseError("仅音频模式需要 DASH 音频流".to_string()))?;
+            let artist = course_info
+                .up_info
+                .as_ref()
+                .map(|up| up.uname.as_str())
+                .unwrap_or_default();
+            let track = course_info
+                .episodes
+                .iter()
+                .position(|ep| ep.id == episode.id)
+                .map(|index| index + 1);
+            let cover = episode.cover.as_deref().unwrap_or(&course_info.cover);
+            download_task_vec.extend(create_audio_only_tasks(
+                dash,
+                play_info.timelength,
+                title,
+                Some(&episode.title),
+                &config.output_dir,
+                Some(cover),
+                create_audio_metadata(&episode.title, artist, title, track),
+            )?);
+            return Ok(with_archive_key(download_task_vec, config, &archive_key, &archive_name));
+        }
+
+        // --------------------------------------------------------------------
+        let video_stream_task = if config.wants_video() && play_info.dash.is_some() {
+            select_video_stream(&play_info, &config.video_selector())?
+                .map(|video| {
                     create_video_task(
-                        video_url,
+                        video.base_url.clone(),
                         title,
                         Some(&episode.title),
                         &config.output_dir,
-                        HashMap::new(),
+                        with_quality_metadata(
+                            HashMap::new(),
+                            &play_info,
+                            video.id,
+                            &video.codecs,
+                        ),
                     )
+                    .with_mirrors(video.backup_urls())
+                    .with_estimated_size(video.estimated_size(play_info.timelength))
                 })
         } else {
             None
         };
 
         // --------------------------------------------------------------------
-        let audio_stream_task = if config.need_audio && play_info.dash.is_some() {
+        let audio_stream_task = if config.wants_audio() && play_info.dash.is_some() {
             select_audio_stream(&play_info.dash.as_ref().unwrap().audio)?
-                .map(|audio_url| {
+                .map(|audio| {
                     create_audio_task(
-                        audio_url,
+                        audio.base_url.clone(),
                         title,
                         Some(&episode.title),
                         &config.output_dir,
                         HashMap::new(),
                     )
+                    .with_mirrors(audio.backup_urls())
+                    .with_estimated_size(audio.estimated_size(play_info.timelength))
                 })
         } else {
             None
         };
 
+        // --------------------------------------------------------------------
+        let durl_tasks = if config.wants_video() && play_info.dash.is_none() {
+            select_durl_segments(&play_info, &config.video_selector())
+                .map(|selected| {
+                    create_durl_tasks(
+                        &selected.durl,
+                        &play_info.format,
+                        title,
+                        Some(&episode.title),
+                        with_quality_metadata(
+                            HashMap::new(),
+                            &play_info,
+                            selected.quality,
+                            "",
+                        ),
+                    )
+                })
+                .unwrap_or_default()
+        } else {
+            Vec::new()
+        };
+
         if let Some(video_task) = &video_stream_task {
             download_task_vec.push(video_task.clone());
         }
+        download_task_vec.extend(durl_tasks);
 
         if let Some(audio_task) = &audio_stream_task {
             download_task_vec.push(audio_task.clone());
         }
 
-        Ok(download_task_vec)
+        Ok(with_archive_key(download_task_vec, config, &archive_key, &archive_name))
     }
 }
 
@@ -185,7 +276,7 @@ impl<'a> Parser for CourseParser<'a> {
                     .ok_or_else(|| ParseError::ParseError("未找到章节信息".to_string()))?;
 
                 let download_itmes = self
-                    .create_video_meta(&course_info.title, &episode, &config)
+                    .create_video_meta(&course_info, &episode, &config)
                     .await?;
 
                 Ok(ParsedMeta {
@@ -232,7 +323,7 @@ impl<'a> Parser for CourseParser<'a> {
                 let mut download_items: Vec<DownloadTask> = Vec::new();
                 for episode in episodes_to_download {
                     let tasks = self
-                        .create_video_meta(&course_info.title, &episode, &config)
+                        .create_video_meta(&course_info, &episode, &config)
                         .await?;
                     download_items.extend(tasks);
                 }
@@ -20,7 +20,7 @@ pub fn handle_api_error(code: i32, message: &str, context: &str) -> ParseError {
             context, message
         )),
         6001 => ParseError::ParseError(format!(
-            "{}地区限制（6001）: {}。此内容在当前地区不可观看",
+            "{}地区限制（6001）: {}。此内容在当前地区不可观看，可通过 --playurl-proxy 指定对应地区的代理获取播放地址",
             context, message
         )),
         62002 => ParseError::ParseError(format!(
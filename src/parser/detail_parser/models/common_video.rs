@@ -1,6 +1,9 @@
 use serde_derive::Deserialize;
 
-use crate::parser::models::VideoQuality;
+use crate::downloader::archive::{ArchiveEntry, ArchiveKey, DownloadArchive};
+use crate::parser::detail_parser::parser_trait::StreamType;
+use crate::parser::models::{AudioFormat, VideoQuality};
+use crate::parser::stream_selector::StreamSelector;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct DownloadConfig {
@@ -14,6 +17,57 @@ pub struct DownloadConfig {
     pub output_dir: String,            // 输出目录
     pub concurrency: usize,            // 并发数
     pub episode_range: Option<String>, // 集数范围
+    pub audio_only: bool,              // 仅提取音频
+    pub audio_format: AudioFormat,     // 仅音频模式的输出格式
+    pub audio_template: String,        // 仅音频模式的文件名模板
+    pub stream_type: StreamType,       // 首选流格式
+    pub selector: Option<StreamSelector>, // 流选择表达式，未指定时按分辨率选择
+    #[serde(skip)]
+    pub archive: Option<DownloadArchive>, // 下载存档，已存档的内容跳过
+    pub upgrade: bool,                    // 有更高清晰度时重新下载已存档的内容
+}
+
+impl DownloadConfig {
+    // 是否需要下载视频流（仅音频模式下忽略视频）
+    pub fn wants_video(&self) -> bool {
+        self.need_video && !self.audio_only
+    }
+
+    // 是否需要下载音频流
+    pub fn wants_audio(&self) -> bool {
+        self.need_audio || self.audio_only
+    }
+
+    // 请求播放地址时的首选格式：仅音频模式只能使用 DASH 音频流，忽略指定的 MP4/FLV
+    pub fn play_stream_type(&self) -> StreamType {
+        if self.audio_only {
+            StreamType::Dash
+        } else {
+            self.stream_type
+        }
+    }
+
+    // 请求播放地址时使用的 qn：选择表达式指定了清晰度时使用该清晰度，否则请求最高清晰度
+    pub fn request_qn(&self) -> i32 {
+        match &self.selector {
+            Some(selector) => selector
+                .requested_quality(&[])
+                .unwrap_or(VideoQuality::Q8K as i32),
+            None => self.resolution as i32,
+        }
+    }
+
+    // 下载存档中的已有记录
+    pub fn archived(&self, key: &ArchiveKey) -> Option<ArchiveEntry> {
+        self.archive.as_ref()?.get(key)
+    }
+
+    // 视频流选择器：优先使用选择表达式，否则按分辨率构造
+    pub fn video_selector(&self) -> StreamSelector {
+        self.selector
+            .clone()
+            .unwrap_or_else(|| StreamSelector::from_quality(self.resolution))
+    }
 }
 
 impl Default for DownloadConfig {
@@ -29,6 +83,13 @@ impl Default for DownloadConfig {
             output_dir: "./downloads".to_string(),
             concurrency: 4,
             episode_range: None,
+            audio_only: false,
+            audio_format: AudioFormat::default(),
+            audio_template: "{title}".to_string(),
+            stream_type: StreamType::default(),
+            selector: None,
+            archive: None,
+            upgrade: false,
         }
     }
 }
@@ -13,6 +13,12 @@ pub struct CourseInfo {
     pub cover: String,
     pub paid_view: bool,
     pub episodes: Vec<CourseEpisode>,
+    pub up_info: Option<CourseUpInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CourseUpInfo {
+    pub uname: String,
 }
 
 #[derive(Debug, Deserialize)]
@@ -23,4 +29,5 @@ pub struct CourseEpisode {
     pub duration: i32,
     pub title: String,
     pub release_date: i64,
+    pub cover: Option<String>,
 }
@@ -1,13 +1,53 @@
 use serde::Deserialize;
 
+use crate::parser::models::quality_label;
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct PlayUrlData {
     pub format: String,                  // 流格式
-    pub timelength: i64,                 // 时长，单位为秒
+    pub timelength: i64,                 // 时长，单位为毫秒
     pub quality: Option<i32>,            // 当前选择的分辨率ID
     pub dash: Option<DashInfo>,          // DASH流信息
     pub durl: Option<Vec<Mp4Info>>,           // MP4流信息
     pub durls: Option<Vec<DurlInfo>>,    // MP4流信息
+    #[serde(default)]
+    pub accept_quality: Vec<i32>, // 可用的分辨率ID列表
+    #[serde(default)]
+    pub accept_description: Vec<String>, // 可用分辨率的描述
+    #[serde(default)]
+    pub support_formats: Vec<SupportFormat>, // 可用格式的详细信息
+}
+
+impl PlayUrlData {
+    // 清晰度描述，优先使用接口返回的 support_formats / accept_description
+    pub fn quality_description(&self, quality: i32) -> String {
+        self.support_formats
+            .iter()
+            .find(|f| f.quality == quality && !f.new_description.is_empty())
+            .map(|f| f.new_description.clone())
+            .or_else(|| {
+                self.accept_quality
+                    .iter()
+                    .position(|q| *q == quality)
+                    .and_then(|i| self.accept_description.get(i).cloned())
+            })
+            .unwrap_or_else(|| quality_label(quality))
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SupportFormat {
+    pub quality: i32, // 分辨率ID
+    #[serde(default)]
+    pub format: String, // 格式名称
+    #[serde(default)]
+    pub new_description: String, // 描述，如 "1080P 60帧"
+    #[serde(default)]
+    pub display_desc: String, // 简短描述，如 "1080P"
+    #[serde(default)]
+    pub superscript: String, // 角标，如 "60帧"、"高码率"
+    #[serde(default)]
+    pub codecs: Option<Vec<String>>, // 可用编码
 }
 
 // ------------------------------------------------------------------------------------------
@@ -16,6 +56,13 @@ pub struct DashInfo {
     pub duration: i64,        // 时长，单位为秒
     pub audio: Vec<DashItem>, // 音频流信息
     pub video: Vec<DashItem>, // 视频流信息
+    pub flac: Option<FlacInfo>, // Hi-Res无损音频
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlacInfo {
+    pub display: bool,           // 是否展示无损音频
+    pub audio: Option<DashItem>, // 无损音频流
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -31,6 +78,17 @@ pub struct DashItem {
     pub frame_rate: Option<String>,      // 帧率
 }
 
+impl DashItem {
+    pub fn backup_urls(&self) -> Vec<String> {
+        self.backup_url.clone().unwrap_or_default()
+    }
+
+    /// 按平均码率和时长（毫秒）估算文件大小
+    pub fn estimated_size(&self, timelength: i64) -> u64 {
+        (self.bandwidth.max(0) as u64).saturating_mul(timelength.max(0) as u64) / 8000
+    }
+}
+
 // ------------------------------------------------------------------------------------------
 #[derive(Debug, Clone, Deserialize)]
 pub struct DurlInfo {
@@ -47,3 +105,9 @@ pub struct Mp4Info {
     pub backup_url: Option<Vec<String>>, // 备用URL列表
     pub quality: Option<i32>,            // 分辨率ID
 }
+
+impl Mp4Info {
+    pub fn backup_urls(&self) -> Vec<String> {
+        self.backup_url.clone().unwrap_or_default()
+    }
+}
@@ -1,10 +1,12 @@
 use async_trait::async_trait;
 use serde_derive::Deserialize;
+use std::future::Future;
+use tracing::{debug, warn};
 
 use crate::{
     common::models::ParsedMeta,
     parser::{
-        detail_parser::models::DownloadConfig,
+        detail_parser::models::{DownloadConfig, PlayUrlData},
         errors::ParseError,
         models::UrlType,
     },
@@ -29,6 +31,17 @@ pub enum ParserOptions {
     },
 }
 
+impl ParserOptions {
+    // 获取下载配置
+    pub fn config(&self) -> &DownloadConfig {
+        match self {
+            Self::CommonVideo { config } | Self::Bangumi { config } | Self::Course { config } => {
+                config
+            }
+        }
+    }
+}
+
 impl Default for ParserOptions {
     fn default() -> Self {
         Self::CommonVideo {
@@ -37,11 +50,99 @@ impl Default for ParserOptions {
     }
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
 pub enum StreamType {
     #[default]
+    Auto, // 优先 DASH，不可用时自动回退
     Dash, // DASH流
-    MP4, // MP4流
+    MP4,  // MP4流
+    Flv,  // FLV流（旧格式）
+}
+
+// playurl 接口 fnval 参数的各个标志位
+const FNVAL_FLV: u32 = 0;
+const FNVAL_MP4: u32 = 1;
+const FNVAL_DASH: u32 = 16;
+const FNVAL_HDR: u32 = 64;
+const FNVAL_4K: u32 = 128;
+const FNVAL_DOLBY_AUDIO: u32 = 256;
+const FNVAL_DOLBY_VISION: u32 = 512;
+const FNVAL_8K: u32 = 1024;
+const FNVAL_AV1: u32 = 2048;
+
+impl StreamType {
+    // 构造请求该格式时使用的 fnval 位掩码
+    pub fn fnval(&self) -> u32 {
+        match self {
+            Self::Auto | Self::Dash => {
+                FNVAL_DASH
+                    | FNVAL_HDR
+                    | FNVAL_4K
+                    | FNVAL_DOLBY_AUDIO
+                    | FNVAL_DOLBY_VISION
+                    | FNVAL_8K
+                    | FNVAL_AV1
+            }
+            Self::MP4 => FNVAL_MP4,
+            Self::Flv => FNVAL_FLV,
+        }
+    }
+
+    // 按优先级排列的候选格式，首选格式不可用时依次回退
+    pub fn candidates(&self) -> [StreamType; 3] {
+        match self {
+            Self::Auto | Self::Dash => [Self::Dash, Self::MP4, Self::Flv],
+            Self::MP4 => [Self::MP4, Self::Flv, Self::Dash],
+            Self::Flv => [Self::Flv, Self::MP4, Self::Dash],
+        }
+    }
+
+    // 判断接口响应中是否提供了该格式的流
+    pub fn is_offered_by(&self, data: &PlayUrlData) -> bool {
+        let has_durl = data.durl.as_ref().is_some_and(|d| !d.is_empty())
+            || data.durls.as_ref().is_some_and(|d| !d.is_empty());
+        match self {
+            Self::Auto => data.dash.is_some() || has_durl,
+            Self::Dash => data.dash.is_some(),
+            Self::MP4 => data.dash.is_none() && has_durl && data.format.starts_with("mp4"),
+            Self::Flv => data.dash.is_none() && has_durl && data.format.starts_with("flv"),
+        }
+    }
+}
+
+/// 按格式优先级请求播放地址，首选格式不可用时自动回退到其它格式
+///
+/// `fetch` 接收 fnval 参数并返回接口响应，API 错误会直接向上传递。
+pub async fn request_play_url<F, Fut>(
+    stream_type: StreamType,
+    mut fetch: F,
+) -> Result<PlayUrlData, ParseError>
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<PlayUrlData, ParseError>>,
+{
+    let mut fallback: Option<PlayUrlData> = None;
+
+    for (index, candidate) in stream_type.candidates().into_iter().enumerate() {
+        let data = fetch(candidate.fnval()).await?;
+        if candidate.is_offered_by(&data) {
+            if index > 0 && stream_type != StreamType::Auto {
+                warn!(
+                    "首选格式 {:?} 不可用，已回退到 {:?} (format={})",
+                    stream_type, candidate, data.format
+                );
+            }
+            debug!("使用 {:?} 格式, fnval={}", candidate, candidate.fnval());
+            return Ok(data);
+        }
+
+        debug!("{:?} 格式不可用 (format={})，尝试下一个格式", candidate, data.format);
+        if fallback.is_none() && StreamType::Auto.is_offered_by(&data) {
+            fallback = Some(data);
+        }
+    }
+
+    fallback.ok_or_else(|| ParseError::ParseError("未解析出播放地址".to_string()))
 }
 
 // 定义一个trait，用于解析视频信息，然后返回元数据
@@ -98,3 +199,91 @@ pub fn parse_episode_range(range_str: &str) -> Result<Vec<i64>, ParseError> {
 
     Ok(episodes)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    // 模拟接口响应：dash 为 true 时返回 DASH 流，否则返回指定格式的 durl
+    fn play_url(format: &str, dash: bool) -> PlayUrlData {
+        let mut data = json!({ "format": format, "timelength": 1000 });
+        if dash {
+            data["dash"] = json!({ "duration": 1, "audio": [], "video": [], "flac": null });
+        } else if !format.is_empty() {
+            data["durl"] = json!([{ "order": 1, "length": 1, "size": 1, "url": "u" }]);
+        }
+        serde_json::from_value(data).unwrap()
+    }
+
+    // 按 fnval 返回响应，并记录请求过的 fnval
+    async fn request(
+        stream_type: StreamType,
+        respond: impl Fn(u32) -> PlayUrlData,
+    ) -> (Result<PlayUrlData, ParseError>, Vec<u32>) {
+        let mut calls = Vec::new();
+        let result = request_play_url(stream_type, |fnval| {
+            calls.push(fnval);
+            let data = respond(fnval);
+            async move { Ok(data) }
+        })
+        .await;
+        (result, calls)
+    }
+
+    #[tokio::test]
+    async fn test_play_url_fallback_order() {
+        let dash = StreamType::Dash.fnval();
+
+        // 首选格式可用时只请求一次
+        let (result, calls) = request(StreamType::MP4, |_| play_url("mp4720", false)).await;
+        assert!(result.unwrap().durl.is_some());
+        assert_eq!(calls, [FNVAL_MP4]);
+
+        // FLV 不可用时回退到 MP4
+        let (result, calls) = request(StreamType::Flv, |_| play_url("mp4720", false)).await;
+        assert_eq!(result.unwrap().format, "mp4720");
+        assert_eq!(calls, [FNVAL_FLV, FNVAL_MP4]);
+
+        // 只有 DASH 时，MP4 -> FLV -> DASH 依次尝试
+        let (result, calls) = request(StreamType::MP4, |_| play_url("dash", true)).await;
+        assert!(result.unwrap().dash.is_some());
+        assert_eq!(calls, [FNVAL_MP4, FNVAL_FLV, dash]);
+
+        // DASH 不可用时回退到 MP4
+        let (result, calls) = request(StreamType::Auto, |fnval| match fnval {
+            FNVAL_FLV => play_url("flv", false),
+            _ => play_url("mp4720", false),
+        })
+        .await;
+        assert_eq!(result.unwrap().format, "mp4720");
+        assert_eq!(calls, [dash, FNVAL_MP4]);
+
+        // 都不匹配时使用第一个有流的响应，没有任何流时报错
+        let (result, calls) = request(StreamType::Dash, |fnval| match fnval {
+            FNVAL_MP4 => play_url("flv720", false),
+            FNVAL_FLV => play_url("", false),
+            _ => play_url("mp4720", false),
+        })
+        .await;
+        assert_eq!(result.unwrap().format, "mp4720");
+        assert_eq!(calls, [dash, FNVAL_MP4, FNVAL_FLV]);
+        let (result, calls) = request(StreamType::Dash, |_| play_url("", false)).await;
+        assert!(result.is_err());
+        assert_eq!(calls.len(), 3);
+    }
+
+    #[test]
+    fn test_audio_only_requests_dash() {
+        let config = DownloadConfig {
+            stream_type: StreamType::MP4,
+            ..Default::default()
+        };
+        assert_eq!(config.play_stream_type(), StreamType::MP4);
+        let config = DownloadConfig {
+            audio_only: true,
+            ..config
+        };
+        assert_eq!(config.play_stream_type(), StreamType::Dash);
+    }
+}
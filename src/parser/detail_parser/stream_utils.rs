@@ -1,76 +1,101 @@
-use crate::parser::detail_parser::models::DashItem;
+use crate::parser::detail_parser::models::{DashInfo, DashItem, DurlInfo, PlayUrlData};
 use crate::parser::errors::ParseError;
-use crate::parser::models::VideoQuality;
-use tracing::{debug, warn};
+use crate::parser::stream_selector::{Selection, StreamCandidate, StreamSelector};
+use tracing::{debug, info, warn};
 
-/// 选择最佳的视频流
+/// 按选择表达式选择视频流，并报告实际选择与请求的差异
 pub fn select_video_stream(
-    streams: &[DashItem],
-    resolution: VideoQuality,
-) -> Result<Option<String>, ParseError> {
-    if streams.is_empty() {
+    play_info: &PlayUrlData,
+    selector: &StreamSelector,
+) -> Result<Option<DashItem>, ParseError> {
+    let Some(dash) = play_info.dash.as_ref().filter(|d| !d.video.is_empty()) else {
         return Err(ParseError::ParseError(
             "没有可用的视频流。可能原因：1. 视频需要大会员权限 2. 当前清晰度不可用 3. Cookie已过期，请重新登录".to_string()
         ));
-    }
+    };
+    let streams = &dash.video;
 
     debug!("可用的视频流数量: {}", streams.len());
     for (i, stream) in streams.iter().enumerate() {
         debug!(
-            "流 {}: 清晰度ID={}, width={:?}, height={:?}",
-            i, stream.id, stream.width, stream.height
+            "流 {}: 清晰度ID={}, width={:?}, height={:?}, 编码={}",
+            i, stream.id, stream.width, stream.height, stream.codecs
         );
     }
+    debug!("可用清晰度: {:?}", play_info.accept_quality);
 
-    let target_quality_id = resolution as i32;
-    debug!("目标清晰度ID: {}", target_quality_id);
-
-    // 首先尝试精确匹配清晰度ID
-    if let Some(stream) = streams.iter().find(|s| s.id == target_quality_id) {
-        debug!("找到精确匹配的清晰度: ID={}", stream.id);
-        return Ok(Some(stream.base_url.clone()));
-    }
-
-    // 如果没有精确匹配，选择最接近且不超过目标清晰度的流
-    let mut suitable_streams: Vec<_> = streams
+    let candidates: Vec<StreamCandidate> = streams
         .iter()
-        .filter(|s| s.id <= target_quality_id)
+        .map(|s| StreamCandidate::from_dash(s, dash.duration))
         .collect();
 
-    if !suitable_streams.is_empty() {
-        // 按清晰度ID降序排序，选择最高的
-        suitable_streams.sort_by(|a, b| b.id.cmp(&a.id));
-        let selected = suitable_streams[0];
-        debug!(
-            "选择最接近的清晰度: ID={} (目标: {})",
-            selected.id, target_quality_id
-        );
-        return Ok(Some(selected.base_url.clone()));
-    }
+    let Some(selection) = selector.select(&candidates, &play_info.support_formats) else {
+        let available: Vec<String> = streams
+            .iter()
+            .map(|s| describe_quality(play_info, s.id))
+            .collect();
+        return Err(ParseError::ParseError(format!(
+            "没有符合选择表达式 `{}` 的视频流，可用清晰度: {}",
+            selector,
+            available.join(", ")
+        )));
+    };
 
-    // 如果所有流的清晰度都高于目标，选择最低的
-    let mut all_streams = streams.to_vec();
-    all_streams.sort_by(|a, b| a.id.cmp(&b.id));
-    let fallback = &all_streams[0];
-
-    // 检查是否是高质量视频权限问题
-    let highest_available_quality = all_streams.last().map(|s| s.id).unwrap_or(0);
-    if target_quality_id >= 112 && highest_available_quality < target_quality_id {
-        // 112是1080P+
-        warn!(
-            "目标清晰度 {} 可能需要大会员权限，最高可用清晰度: {}",
-            target_quality_id, highest_available_quality
-        );
-        warn!("💡 提示：1080P+、4K等高清晰度通常需要大会员权限，请确保已登录大会员账号");
+    let selected = &streams[selection.index];
+    report_selection(play_info, selector, selection, selected.id);
+    debug!(
+        "选择视频流: ID={}, {}x{}, 帧率={}, 编码={}",
+        selected.id,
+        selected.width.unwrap_or_default(),
+        selected.height.unwrap_or_default(),
+        selected.frame_rate.as_deref().unwrap_or("-"),
+        selected.codecs
+    );
+
+    Ok(Some(selected.clone()))
+}
+
+// 输出实际选择的清晰度；未命中首选条件时给出提示
+fn report_selection(
+    play_info: &PlayUrlData,
+    selector: &StreamSelector,
+    selection: Selection,
+    selected_id: i32,
+) {
+    let description = describe_quality(play_info, selected_id);
+    if selection.alternative == 0 {
+        info!("🎞️ 已选择清晰度: {} (表达式: {})", description, selector);
+        return;
     }
 
-    debug!("目标清晰度过低，降级到最低可用清晰度: ID={}", fallback.id);
+    warn!(
+        "首选条件 `{}` 无匹配的流，已按 `{}` 选择清晰度: {}",
+        selector.alternative_text(0),
+        selector.alternative_text(selection.alternative),
+        description
+    );
+
+    // 请求的清晰度在可用列表中但未返回，通常是权限不足
+    if let Some(requested) = selector.requested_quality(&play_info.support_formats) {
+        let highest = play_info.accept_quality.iter().max().copied().unwrap_or(0);
+        if requested > selected_id && (requested >= 112 || highest >= requested) {
+            warn!(
+                "目标清晰度 {} 可能需要大会员权限，实际获取: {}",
+                describe_quality(play_info, requested),
+                description
+            );
+            warn!("💡 提示：1080P+、4K等高清晰度通常需要大会员权限，请确保已登录大会员账号");
+        }
+    }
+}
 
-    Ok(Some(fallback.base_url.clone()))
+// 清晰度描述，附带清晰度ID
+fn describe_quality(play_info: &PlayUrlData, quality: i32) -> String {
+    format!("{} ({})", play_info.quality_description(quality), quality)
 }
 
 /// 选择最佳的音频流
-pub fn select_audio_stream(streams: &[DashItem]) -> Result<Option<String>, ParseError> {
+pub fn select_audio_stream(streams: &[DashItem]) -> Result<Option<DashItem>, ParseError> {
     if streams.is_empty() {
         return Err(ParseError::ParseError(
             "没有可用的音频流。可能原因：1. 视频源异常 2. 网络问题 3. Cookie已过期".to_string(),
@@ -95,5 +120,81 @@ pub fn select_audio_stream(streams: &[DashItem]) -> Result<Option<String>, Parse
         selected.id, selected.bandwidth
     );
 
-    Ok(Some(selected.base_url.clone()))
-}
\ No newline at end of file
+    Ok(Some(selected.clone()))
+}
+/// 为仅音频模式选择最佳音轨，优先使用 Hi-Res 无损音轨
+pub fn select_best_audio_stream(dash: &DashInfo) -> Result<Option<DashItem>, ParseError> {
+    if let Some(flac) = dash.flac.as_ref().and_then(|f| f.audio.as_ref()) {
+        debug!("选择 Hi-Res 无损音频流: ID={}, 编码={}", flac.id, flac.codecs);
+        return Ok(Some(flac.clone()));
+    }
+
+    if dash.audio.is_empty() {
+        return Err(ParseError::ParseError(
+            "没有可用的音频流。可能原因：1. 视频源异常 2. 网络问题 3. Cookie已过期".to_string(),
+        ));
+    }
+
+    let selected = dash
+        .audio
+        .iter()
+        .max_by_key(|s| s.bandwidth)
+        .cloned();
+    if let Some(stream) = &selected {
+        debug!(
+            "选择最高质量音频流: ID={}, 编码={}, 带宽={}",
+            stream.id, stream.codecs, stream.bandwidth
+        );
+    }
+    Ok(selected)
+}
+
+/// 选择 DURL（FLV/MP4 分段）流的全部分段及其清晰度，分段按 `order` 排序
+///
+/// 优先使用 `durl`；若接口只返回了 `durls`，则按选择表达式选择条目，
+/// 无匹配时选择与实际清晰度一致的条目。
+pub fn select_durl_segments(
+    play_info: &PlayUrlData,
+    selector: &StreamSelector,
+) -> Option<DurlInfo> {
+    let (quality, mut segments) = match (&play_info.durl, &play_info.durls) {
+        (Some(durl), _) if !durl.is_empty() => {
+            let quality = play_info
+                .quality
+                .or_else(|| durl.iter().find_map(|s| s.quality))
+                .unwrap_or_default();
+            info!("🎞️ 已获取清晰度: {}", describe_quality(play_info, quality));
+            (quality, durl.clone())
+        }
+        (_, Some(durls)) if !durls.is_empty() => {
+            let candidates: Vec<StreamCandidate> = durls
+                .iter()
+                .map(|d| StreamCandidate::from_durl(d.quality, &d.durl))
+                .collect();
+            let selected = match selector.select(&candidates, &play_info.support_formats) {
+                Some(selection) => {
+                    let selected = &durls[selection.index];
+                    report_selection(play_info, selector, selection, selected.quality);
+                    selected
+                }
+                None => {
+                    let target = play_info.quality.unwrap_or_default();
+                    durls
+                        .iter()
+                        .find(|d| d.quality == target)
+                        .or_else(|| durls.iter().max_by_key(|d| d.quality))?
+                }
+            };
+            debug!("从 durls 中选择清晰度: {}", selected.quality);
+            (selected.quality, selected.durl.clone())
+        }
+        _ => return None,
+    };
+
+    segments.sort_by_key(|s| s.order);
+    debug!("DURL 分段数量: {}", segments.len());
+    Some(DurlInfo {
+        quality,
+        durl: segments,
+    })
+}
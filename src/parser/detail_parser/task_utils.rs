@@ -1,5 +1,10 @@
+use crate::downloader::archive::ArchiveKey;
 use crate::downloader::models::{DownloadTask, FileType};
+use crate::parser::detail_parser::models::{DashInfo, DownloadConfig, Mp4Info, PlayUrlData};
+use crate::parser::detail_parser::stream_utils::select_best_audio_stream;
+use crate::parser::errors::ParseError;
 use std::collections::HashMap;
+use tracing::info;
 
 /// 创建视频下载任务的辅助函数
 pub fn create_video_task(
@@ -72,4 +77,191 @@ pub fn create_danmaku_task(
         cid.to_string(),
         metadata,
     )
-}
\ No newline at end of file
+}
+/// 创建封面图片下载任务的辅助函数（仅音频模式写入封面）
+pub fn create_cover_task(
+    url: String,
+    title: &str,
+    episode_title: Option<&str>,
+    output_dir: &str,
+    metadata: HashMap<String, String>,
+) -> DownloadTask {
+    let filename = if let Some(ep_title) = episode_title {
+        format!("{} - {}.jpg", title, ep_title)
+    } else {
+        format!("{}.jpg", title)
+    };
+
+    let output_path = format!("{}/{}", output_dir, filename);
+
+    DownloadTask::new(
+        url,
+        FileType::Image,
+        filename,
+        output_path,
+        output_dir.to_string(),
+        metadata,
+    )
+}
+
+/// 构造音频标签元数据，供仅音频模式写入标签和渲染文件名模板
+pub fn create_audio_metadata(
+    title: &str,
+    artist: &str,
+    album: &str,
+    track: Option<usize>,
+) -> HashMap<String, String> {
+    let mut metadata = HashMap::from([
+        ("title".to_string(), title.to_string()),
+        ("artist".to_string(), artist.to_string()),
+        ("album".to_string(), album.to_string()),
+    ]);
+    if let Some(track) = track {
+        metadata.insert("track".to_string(), track.to_string());
+    }
+    metadata
+}
+
+/// 在元数据中记录实际获取的清晰度、编码和时长，后处理时写入输出文件名、校验输出并记录下载存档
+pub fn with_quality_metadata(
+    mut metadata: HashMap<String, String>,
+    play_info: &PlayUrlData,
+    quality: i32,
+    codecs: &str,
+) -> HashMap<String, String> {
+    metadata.insert("quality".to_string(), quality.to_string());
+    if !codecs.is_empty() {
+        metadata.insert("codecs".to_string(), codecs.to_string());
+    }
+    metadata.insert(
+        "quality_desc".to_string(),
+        play_info.quality_description(quality),
+    );
+    // 合并后校验输出时长
+    metadata.insert("timelength".to_string(), play_info.timelength.to_string());
+    metadata
+}
+
+/// 下载存档中已有记录且未指定 `--upgrade` 时返回 true，调用方应跳过，不再请求播放地址
+pub fn skip_archived(config: &DownloadConfig, key: &ArchiveKey, name: &str) -> bool {
+    match config.archived(key) {
+        Some(entry) if !config.upgrade => {
+            info!("⏭️ 已在下载存档中，跳过: {} ({})", name, entry.output);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// 为同一分P的所有任务写入存档标识
+///
+/// 指定 `--upgrade` 且已有存档时，只有本次选到的清晰度高于存档记录才重新下载，否则返回空列表。
+pub fn with_archive_key(
+    mut tasks: Vec<DownloadTask>,
+    config: &DownloadConfig,
+    key: &ArchiveKey,
+    name: &str,
+) -> Vec<DownloadTask> {
+    if let Some(entry) = config.archived(key) {
+        let quality = tasks
+            .iter()
+            .filter_map(|task| task.metadata.get("quality")?.parse::<i32>().ok())
+            .max()
+            .unwrap_or(0);
+        if quality <= entry.quality {
+            info!("⏭️ 没有比存档更高的清晰度，跳过: {} (qn {})", name, entry.quality);
+            return Vec::new();
+        }
+        info!(
+            "⬆️ 发现更高的清晰度，重新下载: {} (qn {} -> {})",
+            name, entry.quality, quality
+        );
+    }
+    for task in &mut tasks {
+        key.insert_into(&mut task.metadata);
+    }
+    tasks
+}
+
+/// 仅音频模式：选择最佳音轨，创建音频及封面下载任务
+pub fn create_audio_only_tasks(
+    dash: &DashInfo,
+    timelength: i64,
+    title: &str,
+    episode_title: Option<&str>,
+    output_dir: &str,
+    cover_url: Option<&str>,
+    mut metadata: HashMap<String, String>,
+) -> Result<Vec<DownloadTask>, ParseError> {
+    let mut tasks = Vec::new();
+
+    if let Some(stream) = select_best_audio_stream(dash)? {
+        metadata.insert("codecs".to_string(), stream.codecs.clone());
+        metadata.insert("timelength".to_string(), timelength.to_string());
+        let mirrors = stream.backup_urls();
+        let estimated_size = stream.estimated_size(timelength);
+        tasks.push(
+            create_audio_task(
+                stream.base_url,
+                title,
+                episode_title,
+                output_dir,
+                metadata.clone(),
+            )
+            .with_mirrors(mirrors)
+            .with_estimated_size(estimated_size),
+        );
+    }
+
+    if let Some(cover_url) = cover_url.filter(|url| !url.is_empty()) {
+        tasks.push(create_cover_task(
+            cover_url.to_string(),
+            title,
+            episode_title,
+            output_dir,
+            metadata,
+        ));
+    }
+
+    Ok(tasks)
+}
+
+/// 创建 DURL 分段下载任务
+///
+/// 所有分段共享同一个任务名，后处理时会被归为一组并按顺序拼接。
+pub fn create_durl_tasks(
+    segments: &[Mp4Info],
+    format: &str,
+    title: &str,
+    episode_title: Option<&str>,
+    metadata: HashMap<String, String>,
+) -> Vec<DownloadTask> {
+    let stem = if let Some(ep_title) = episode_title {
+        format!("{} - {}", title, ep_title)
+    } else {
+        title.to_string()
+    };
+    let filename = format!("{}.mp4", stem);
+    let extension = if format.starts_with("flv") { "flv" } else { "mp4" };
+
+    segments
+        .iter()
+        .map(|segment| {
+            let mut metadata = metadata.clone();
+            metadata.insert("segment_order".to_string(), segment.order.to_string());
+            metadata.insert("segment_count".to_string(), segments.len().to_string());
+            metadata.insert("expected_size".to_string(), segment.size.to_string());
+
+            let output_path = format!("./tmp/{}-durl-{:02}.{}", stem, segment.order, extension);
+            DownloadTask::new(
+                segment.url.clone(),
+                FileType::Video,
+                filename.clone(),
+                output_path.clone(),
+                output_path,
+                metadata,
+            )
+            .with_mirrors(segment.backup_urls())
+        })
+        .collect()
+}
@@ -36,6 +36,9 @@ pub enum ParseError {
 
     #[error("需要付费")]
     PaymentRequired,
+
+    #[error("触发B站风控: {0}，请降低请求频率 (--api-rate)、稍后重试或登录后再试")]
+    RiskControl(String),
 }
 
 impl From<ApiError> for ParseError {
@@ -44,6 +47,7 @@ impl From<ApiError> for ParseError {
             ApiError::InvalidResponse(msg) => ParseError::ApiError(msg),
             ApiError::Reqwest(e) => ParseError::ApiError(e.to_string()),
             ApiError::ApiError(_, msg) => ParseError::ApiError(msg),
+            ApiError::RiskControl(code, msg) => ParseError::RiskControl(format!("{} ({})", msg, code)),
             ApiError::Unknown(msg) => ParseError::ApiError(msg),
             _ => ParseError::ApiError(err.to_string()),
         }
@@ -6,9 +6,11 @@ use errors::ParseError;
 use models::UrlType;
 use tracing::debug;
 
+pub mod batch;
 pub mod detail_parser;
 pub mod errors;
 pub mod models;
+pub mod stream_selector;
 pub mod url_parser;
 
 /// 主视频解析器，负责协调整个解析过程
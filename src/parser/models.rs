@@ -99,9 +99,31 @@ pub enum VideoQuality {
     Q1080P60 = 116, // 高清 1080P60
     Q4K = 120,      // 超清 4K
     QHdr = 125,     // HDR 真彩色
+    QDolby = 126,   // 杜比视界
     Q8K = 127,      // 超高清 8K
 }
 
+// 清晰度ID的默认描述，接口未返回描述时使用；未知ID原样保留
+pub fn quality_label(id: i32) -> String {
+    let label = match id {
+        6 => "240P 极速",
+        16 => "360P 流畅",
+        32 => "480P 清晰",
+        64 => "720P 高清",
+        74 => "720P60 高帧率",
+        80 => "1080P 高清",
+        100 => "智能修复",
+        112 => "1080P+ 高码率",
+        116 => "1080P60 高帧率",
+        120 => "4K 超清",
+        125 => "HDR 真彩色",
+        126 => "杜比视界",
+        127 => "8K 超高清",
+        _ => return format!("未知清晰度({})", id),
+    };
+    label.to_string()
+}
+
 
 pub enum AudioQuality {
     Quality64k = 30216,
@@ -111,4 +133,30 @@ pub enum AudioQuality {
     Quality192k = 30280,
 }
 
+// 仅音频模式的输出格式
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub enum AudioFormat {
+    #[default]
+    M4a, // 直接封装，不重新编码
+    Mp3,
+    Flac,
+    Opus,
+}
+
+impl AudioFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M4a => "m4a",
+            Self::Mp3 => "mp3",
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+        }
+    }
+
+    // 容器是否支持内嵌封面
+    pub fn supports_cover(&self) -> bool {
+        !matches!(self, Self::Opus)
+    }
+}
+
 // --------------------------------------------------------
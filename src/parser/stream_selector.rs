@@ -1,18 +1,457 @@
+use serde_derive::Deserialize;
+use std::fmt;
+use std::str::FromStr;
 
+use crate::parser::detail_parser::models::{DashItem, Mp4Info, SupportFormat};
+use crate::parser::models::VideoQuality;
 
-pub struct StreamSelector;
+/// 流选择表达式
+///
+/// 由 `/` 分隔的多个备选项组成，按顺序尝试，第一个有匹配流的备选项生效。
+/// 每个备选项由 `,` 分隔的条件组成，例如：
+///
+/// - `best` / `worst`：最高 / 最低清晰度
+/// - `1080p60`、`4k`、`hdr`、`116`：指定清晰度
+/// - `height<=1080`、`fps>=60`、`filesize<2G`、`codec=hevc`：按属性过滤
+///
+/// 示例：`1080p60/1080p/best`、`height<=1080,fps>=60/best`
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct StreamSelector {
+    expression: String,
+    alternatives: Vec<Alternative>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Alternative {
+    filters: Vec<Filter>,
+    prefer_worst: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Filter {
+    Quality(QualityLabel),
+    Compare(Field, Op, f64),
+    Codec(String, bool), // 编码前缀，是否取反
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum QualityLabel {
+    Id(i32),
+    Description(String), // 与 support_formats 中的描述匹配
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Id,
+    Width,
+    Height,
+    Fps,
+    Bitrate,
+    Filesize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Eq,
+    Ne,
+}
+
+/// 参与选择的候选流
+#[derive(Debug, Clone, Default)]
+pub struct StreamCandidate {
+    pub id: i32,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub fps: Option<f64>,
+    pub bandwidth: Option<i64>, // bps
+    pub filesize: Option<i64>,  // 字节，DASH 流按码率和时长估算
+    pub codecs: Option<String>,
+}
+
+impl StreamCandidate {
+    /// 由 DASH 视频流构造候选，`duration` 为视频时长（秒）
+    pub fn from_dash(item: &DashItem, duration: i64) -> Self {
+        Self {
+            id: item.id,
+            width: item.width,
+            height: item.height,
+            fps: item.frame_rate.as_deref().and_then(parse_frame_rate),
+            bandwidth: Some(item.bandwidth),
+            filesize: (duration > 0).then(|| item.bandwidth * duration / 8),
+            codecs: Some(item.codecs.clone()),
+        }
+    }
+
+    /// 由 DURL 分段构造候选，尺寸和帧率按清晰度ID推断
+    pub fn from_durl(quality: i32, segments: &[Mp4Info]) -> Self {
+        let (height, fps) = nominal_resolution(quality);
+        Self {
+            id: quality,
+            width: None,
+            height,
+            fps,
+            bandwidth: None,
+            filesize: Some(segments.iter().map(|s| s.size).sum()),
+            codecs: None,
+        }
+    }
+}
+
+/// 选择结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub index: usize,       // 选中的候选下标
+    pub alternative: usize, // 命中的备选项下标，0 表示首选
+}
 
 impl StreamSelector {
-    /// 根据流类型选择合适的流
-    pub fn select_stream(
-        &self,
-        dash_info: &DashInfo,
-        config: &DownloadConfig,
-    ) -> Option<ParsedMeta> {
-        match stream_type {
-            "DASH" => streams.iter().find(|s| s.stream_type == "DASH").cloned(),
-            "MP4" => streams.iter().find(|s| s.stream_type == "MP4").cloned(),
+    /// 由清晰度选项构造默认选择器：不超过目标的最高清晰度，否则最低清晰度
+    pub fn from_quality(quality: VideoQuality) -> Self {
+        let expression = format!("id<={}/worst", quality as i32);
+        expression.parse().expect("默认选择表达式必须合法")
+    }
+
+    /// 第 `index` 个备选项的表达式文本
+    pub fn alternative_text(&self, index: usize) -> &str {
+        self.expression
+            .split('/')
+            .nth(index)
+            .unwrap_or_default()
+            .trim()
+    }
+
+    /// 首选备选项中明确指定的清晰度ID，用于提示权限问题
+    pub fn requested_quality(&self, formats: &[SupportFormat]) -> Option<i32> {
+        let first = self.alternatives.first()?;
+        first.filters.iter().find_map(|filter| match filter {
+            Filter::Quality(label) => label.resolve(formats),
+            Filter::Compare(Field::Id, Op::Eq | Op::Le, value) => Some(*value as i32),
             _ => None,
+        })
+    }
+
+    /// 在候选流中按表达式选择
+    pub fn select(
+        &self,
+        candidates: &[StreamCandidate],
+        formats: &[SupportFormat],
+    ) -> Option<Selection> {
+        for (alternative, alt) in self.alternatives.iter().enumerate() {
+            let matched = candidates
+                .iter()
+                .enumerate()
+                .filter(|(_, c)| alt.filters.iter().all(|f| f.matches(c, formats)));
+
+            // 清晰度ID优先，同清晰度下码率优先
+            let rank = |(_, c): &(usize, &StreamCandidate)| (c.id, c.bandwidth.unwrap_or(0));
+            let chosen = if alt.prefer_worst {
+                matched.min_by_key(rank)
+            } else {
+                matched.max_by_key(rank)
+            };
+
+            if let Some((index, _)) = chosen {
+                return Some(Selection { index, alternative });
+            }
+        }
+        None
+    }
+}
+
+impl Filter {
+    fn matches(&self, candidate: &StreamCandidate, formats: &[SupportFormat]) -> bool {
+        match self {
+            Filter::Quality(label) => label.resolve(formats) == Some(candidate.id),
+            Filter::Compare(field, op, expected) => {
+                let actual = match field {
+                    Field::Id => Some(candidate.id as f64),
+                    Field::Width => candidate.width.map(f64::from),
+                    Field::Height => candidate.height.map(f64::from),
+                    Field::Fps => candidate.fps,
+                    Field::Bitrate => candidate.bandwidth.map(|b| b as f64),
+                    Field::Filesize => candidate.filesize.map(|s| s as f64),
+                };
+                actual.is_some_and(|actual| op.apply(actual, *expected))
+            }
+            Filter::Codec(prefix, negate) => {
+                let aliases = codec_aliases(prefix);
+                let matched = candidate.codecs.as_deref().is_some_and(|c| {
+                    if aliases.is_empty() {
+                        c.starts_with(prefix.as_str())
+                    } else {
+                        aliases.iter().any(|a| c.starts_with(a))
+                    }
+                });
+                matched != *negate
+            }
+        }
+    }
+}
+
+impl QualityLabel {
+    fn resolve(&self, formats: &[SupportFormat]) -> Option<i32> {
+        match self {
+            QualityLabel::Id(id) => Some(*id),
+            QualityLabel::Description(desc) => formats
+                .iter()
+                .find(|f| {
+                    f.new_description.eq_ignore_ascii_case(desc)
+                        || f.display_desc.eq_ignore_ascii_case(desc)
+                })
+                .map(|f| f.quality),
         }
     }
-}
\ No newline at end of file
+}
+
+impl Op {
+    fn apply(&self, actual: f64, expected: f64) -> bool {
+        match self {
+            Op::Lt => actual < expected,
+            Op::Le => actual <= expected,
+            Op::Gt => actual > expected,
+            Op::Ge => actual >= expected,
+            Op::Eq => (actual - expected).abs() < f64::EPSILON,
+            Op::Ne => (actual - expected).abs() >= f64::EPSILON,
+        }
+    }
+}
+
+impl FromStr for StreamSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = s.trim();
+        if expression.is_empty() {
+            return Err("选择表达式不能为空".to_string());
+        }
+
+        let alternatives = expression
+            .split('/')
+            .map(parse_alternative)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            expression: expression.to_string(),
+            alternatives,
+        })
+    }
+}
+
+impl TryFrom<String> for StreamSelector {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl fmt::Display for StreamSelector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+fn parse_alternative(text: &str) -> Result<Alternative, String> {
+    let mut alternative = Alternative {
+        filters: Vec::new(),
+        prefer_worst: false,
+    };
+
+    for term in text.split(',').map(str::trim) {
+        match term.to_lowercase().as_str() {
+            "" => return Err(format!("选择表达式中存在空条件: `{}`", text)),
+            "best" => {}
+            "worst" => alternative.prefer_worst = true,
+            _ => alternative.filters.push(parse_filter(term)?),
+        }
+    }
+
+    Ok(alternative)
+}
+
+fn parse_filter(term: &str) -> Result<Filter, String> {
+    const OPS: [(&str, Op); 6] = [
+        ("<=", Op::Le),
+        (">=", Op::Ge),
+        ("!=", Op::Ne),
+        ("<", Op::Lt),
+        (">", Op::Gt),
+        ("=", Op::Eq),
+    ];
+
+    let Some((pos, token, op)) = OPS
+        .iter()
+        .filter_map(|(token, op)| term.find(token).map(|pos| (pos, *token, *op)))
+        .min_by_key(|(pos, token, _)| (*pos, usize::MAX - token.len()))
+    else {
+        return Ok(Filter::Quality(parse_quality_label(term)));
+    };
+
+    let field = term[..pos].trim().to_lowercase();
+    let value = term[pos + token.len()..].trim();
+
+    if field == "codec" || field == "vcodec" {
+        return match op {
+            Op::Eq => Ok(Filter::Codec(value.to_lowercase(), false)),
+            Op::Ne => Ok(Filter::Codec(value.to_lowercase(), true)),
+            _ => Err(format!("编码条件只支持 = 或 !=: `{}`", term)),
+        };
+    }
+
+    let field = match field.as_str() {
+        "id" | "qn" | "quality" => Field::Id,
+        "width" => Field::Width,
+        "height" | "res" => Field::Height,
+        "fps" => Field::Fps,
+        "bitrate" | "tbr" => Field::Bitrate,
+        "filesize" | "size" => Field::Filesize,
+        _ => return Err(format!("未知的选择字段: `{}`", field)),
+    };
+
+    let value = if field == Field::Id {
+        match parse_quality_label(value) {
+            QualityLabel::Id(id) => id as f64,
+            QualityLabel::Description(_) => return Err(format!("无效的清晰度: `{}`", value)),
+        }
+    } else {
+        parse_number(value).ok_or_else(|| format!("无效的数值: `{}`", value))?
+    };
+
+    Ok(Filter::Compare(field, op, value))
+}
+
+// 解析带单位的数值，K/M/G/T 按 1024 进制
+fn parse_number(value: &str) -> Option<f64> {
+    let value = value.trim().trim_end_matches(['b', 'B']);
+    let (number, multiplier) = match value.chars().last()?.to_ascii_uppercase() {
+        'K' => (&value[..value.len() - 1], 1024f64),
+        'M' => (&value[..value.len() - 1], 1024f64.powi(2)),
+        'G' => (&value[..value.len() - 1], 1024f64.powi(3)),
+        'T' => (&value[..value.len() - 1], 1024f64.powi(4)),
+        _ => (value, 1f64),
+    };
+    number.trim().parse::<f64>().ok().map(|n| n * multiplier)
+}
+
+// 常用清晰度名称对应的清晰度ID，未知名称交由 support_formats 匹配
+fn parse_quality_label(label: &str) -> QualityLabel {
+    let normalized = label.trim().to_lowercase();
+    let id = match normalized.as_str() {
+        "240p" => 6,
+        "360p" => 16,
+        "480p" => 32,
+        "720p" => 64,
+        "720p60" => 74,
+        "1080p" => 80,
+        "1080p+" => 112,
+        "1080p60" => 116,
+        "4k" => 120,
+        "hdr" => 125,
+        "dolby" => 126,
+        "8k" => 127,
+        _ => match normalized.parse::<i32>() {
+            Ok(id) => id,
+            Err(_) => return QualityLabel::Description(label.trim().to_string()),
+        },
+    };
+    QualityLabel::Id(id)
+}
+
+fn parse_frame_rate(rate: &str) -> Option<f64> {
+    match rate.split_once('/') {
+        Some((num, den)) => {
+            let den = den.trim().parse::<f64>().ok().filter(|d| *d > 0.0)?;
+            Some(num.trim().parse::<f64>().ok()? / den)
+        }
+        None => rate.trim().parse().ok(),
+    }
+}
+
+fn codec_aliases(name: &str) -> &[&str] {
+    match name {
+        "avc" | "h264" | "avc1" => &["avc1", "avc"],
+        "hevc" | "h265" | "hev1" | "hvc1" => &["hev1", "hvc1", "hevc"],
+        "av1" | "av01" => &["av01", "av1"],
+        _ => &[],
+    }
+}
+
+// 按清晰度ID推断标称高度和帧率
+fn nominal_resolution(quality: i32) -> (Option<i32>, Option<f64>) {
+    match quality {
+        6 => (Some(240), None),
+        16 => (Some(360), None),
+        32 => (Some(480), None),
+        64 => (Some(720), None),
+        74 => (Some(720), Some(60.0)),
+        80 | 112 => (Some(1080), None),
+        116 => (Some(1080), Some(60.0)),
+        120 | 125 | 126 => (Some(2160), None),
+        127 => (Some(4320), None),
+        _ => (None, None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(id: i32, height: i32, fps: f64, bandwidth: i64, codecs: &str) -> StreamCandidate {
+        StreamCandidate {
+            id,
+            width: Some(height * 16 / 9),
+            height: Some(height),
+            fps: Some(fps),
+            bandwidth: Some(bandwidth),
+            filesize: Some(bandwidth * 600 / 8),
+            codecs: Some(codecs.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_select_stream() {
+        let candidates = vec![
+            candidate(120, 2160, 30.0, 12_000_000, "hev1.1.6.L153.90"),
+            candidate(116, 1080, 60.0, 4_000_000, "avc1.640032"),
+            candidate(116, 1080, 60.0, 2_000_000, "hev1.1.6.L150.90"),
+            candidate(80, 1080, 30.0, 2_500_000, "avc1.640032"),
+            candidate(32, 480, 30.0, 800_000, "avc1.64001F"),
+        ];
+        let select = |expr: &str| {
+            expr.parse::<StreamSelector>()
+                .unwrap()
+                .select(&candidates, &[])
+        };
+
+        assert_eq!(
+            select("best"),
+            Some(Selection {
+                index: 0,
+                alternative: 0
+            })
+        );
+        assert_eq!(select("worst").map(|s| s.index), Some(4));
+        assert_eq!(select("height<=1080,fps>=60").map(|s| s.index), Some(1));
+        assert_eq!(select("1080p60,codec=hevc").map(|s| s.index), Some(2));
+        assert_eq!(select("filesize<200M").map(|s| s.index), Some(2));
+        assert_eq!(
+            select("8k/1080p/best"),
+            Some(Selection {
+                index: 3,
+                alternative: 1
+            })
+        );
+        assert_eq!(select("8k"), None);
+
+        let default = StreamSelector::from_quality(VideoQuality::Q1080P);
+        assert_eq!(default.select(&candidates, &[]).map(|s| s.index), Some(3));
+
+        assert!("height<=abc".parse::<StreamSelector>().is_err());
+        assert!("color=red".parse::<StreamSelector>().is_err());
+    }
+}
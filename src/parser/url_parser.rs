@@ -59,7 +59,8 @@ impl UrlParser {
         }
     }
 
-    fn handle_raw_id(&self, id: &str) -> Result<String, ParseError> {
+    /// 将裸ID（BV/av/ep/ss/cp/cs）展开为完整链接
+    pub fn handle_raw_id(&self, id: &str) -> Result<String, ParseError> {
         lazy_static! {
             static ref BV_PATTERN: Regex = Regex::new(r"^BV[0-9A-Za-z]{10}$").unwrap();
             static ref AV_PATTERN: Regex = Regex::new(r"^av(\d+)$").unwrap();
@@ -0,0 +1,302 @@
+use std::fmt::Write;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+
+/// 一条弹幕
+#[derive(Debug, Clone, PartialEq)]
+pub struct Danmaku {
+    pub time: f64, // 出现时间（秒）
+    pub mode: u8,  // 1-3 滚动，4 底部，5 顶部，6 逆向，7 高级，8 代码
+    pub size: u32, // 字号，25 为标准
+    pub color: u32,
+    pub text: String,
+}
+
+/// ASS 字幕生成参数
+#[derive(Debug, Clone)]
+pub struct AssOptions {
+    pub width: u32,
+    pub height: u32,
+    pub font: String,
+    pub font_size: u32,       // 标准弹幕（字号 25）对应的字号
+    pub scroll_duration: f64, // 滚动弹幕经过屏幕的时间（秒）
+    pub fixed_duration: f64,  // 顶部/底部弹幕的显示时间（秒）
+    pub opacity: f32,         // 0-1
+    pub display_area: f32,    // 滚动弹幕可使用的屏幕高度比例
+}
+
+impl Default for AssOptions {
+    fn default() -> Self {
+        Self {
+            width: 1920,
+            height: 1080,
+            font: "Microsoft YaHei".to_string(),
+            font_size: 48,
+            scroll_duration: 10.0,
+            fixed_duration: 5.0,
+            opacity: 0.8,
+            display_area: 1.0,
+        }
+    }
+}
+
+/// 转换结果统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AssStats {
+    pub written: usize,
+    pub dropped: usize,     // 没有空闲轨道而丢弃
+    pub unsupported: usize, // 高级弹幕、代码弹幕
+}
+
+/// 解析 B 站 XML 弹幕，结果按出现时间排序
+pub fn parse_xml(xml: &str) -> Vec<Danmaku> {
+    lazy_static! {
+        static ref ITEM: Regex = Regex::new(r#"<d p="([^"]*)">([^<]*)</d>"#).unwrap();
+    }
+
+    let mut danmakus: Vec<Danmaku> = ITEM
+        .captures_iter(xml)
+        .filter_map(|caps| {
+            // p 属性: 时间,模式,字号,颜色,发送时间,弹幕池,用户哈希,弹幕ID
+            let attrs: Vec<&str> = caps[1].split(',').collect();
+            Some(Danmaku {
+                time: attrs.first()?.parse().ok()?,
+                mode: attrs.get(1)?.parse().ok()?,
+                size: attrs.get(2)?.parse().ok()?,
+                color: attrs.get(3)?.parse().ok()?,
+                text: unescape_xml(&caps[2]),
+            })
+        })
+        .collect();
+    danmakus.sort_by(|a, b| a.time.total_cmp(&b.time));
+    danmakus
+}
+
+/// 生成 ASS 字幕
+///
+/// 滚动弹幕按轨道排布，新弹幕不会与同一轨道的前一条重叠或追尾；没有空闲轨道时丢弃。
+pub fn to_ass(danmakus: &[Danmaku], options: &AssOptions) -> (String, AssStats) {
+    let width = options.width as f64;
+    let height = options.height as f64;
+    let line_height = options.font_size as f64 + 4.0;
+    let scroll_lanes = ((height * options.display_area.clamp(0.0, 1.0) as f64) / line_height)
+        .floor()
+        .max(1.0) as usize;
+    let fixed_lanes = (height / line_height).floor().max(1.0) as usize;
+
+    let mut scroll = vec![ScrollLane::default(); scroll_lanes];
+    let mut top = vec![0.0; fixed_lanes];
+    let mut bottom = vec![0.0; fixed_lanes];
+    let mut stats = AssStats::default();
+    let mut events = String::new();
+
+    for danmaku in danmakus {
+        let size = options.font_size as f64 * danmaku.size as f64 / 25.0;
+        let text_width = text_width(&danmaku.text, size);
+        let start = danmaku.time;
+
+        let placement = match danmaku.mode {
+            1..=3 | 6 => {
+                let speed = (width + text_width) / options.scroll_duration;
+                let end = start + options.scroll_duration;
+                let lane = scroll
+                    .iter()
+                    .position(|lane| lane.accepts(start, speed, width));
+                lane.map(|index| {
+                    scroll[index] = ScrollLane {
+                        enter_at: start + text_width / speed,
+                        leave_at: end,
+                    };
+                    let y = index as f64 * line_height;
+                    let (from, to) = if danmaku.mode == 6 {
+                        (-text_width, width)
+                    } else {
+                        (width, -text_width)
+                    };
+                    (
+                        end,
+                        format!("\\move({:.0},{:.0},{:.0},{:.0})", from, y, to, y),
+                    )
+                })
+            }
+            4 | 5 => {
+                let lanes = if danmaku.mode == 5 {
+                    &mut top
+                } else {
+                    &mut bottom
+                };
+                let end = start + options.fixed_duration;
+                lanes
+                    .iter()
+                    .position(|free_at| *free_at <= start)
+                    .map(|index| {
+                        lanes[index] = end;
+                        let offset = index as f64 * line_height;
+                        let tag = if danmaku.mode == 5 {
+                            format!("\\an8\\pos({:.0},{:.0})", width / 2.0, offset)
+                        } else {
+                            format!("\\an2\\pos({:.0},{:.0})", width / 2.0, height - offset)
+                        };
+                        (end, tag)
+                    })
+            }
+            _ => {
+                stats.unsupported += 1;
+                continue;
+            }
+        };
+
+        let Some((end, position)) = placement else {
+            stats.dropped += 1;
+            continue;
+        };
+        let mut tags = position;
+        if danmaku.size != 25 {
+            let _ = write!(tags, "\\fs{:.0}", size);
+        }
+        if danmaku.color & 0xFFFFFF != 0xFFFFFF {
+            let _ = write!(tags, "\\c{}", ass_color(danmaku.color));
+        }
+        let _ = writeln!(
+            events,
+            "Dialogue: 0,{},{},Danmaku,,0,0,0,,{{{}}}{}",
+            ass_time(start),
+            ass_time(end),
+            tags,
+            escape_ass(&danmaku.text)
+        );
+        stats.written += 1;
+    }
+
+    // ASS 的透明度 00 为不透明，FF 为全透明
+    let alpha = ((1.0 - options.opacity.clamp(0.0, 1.0)) * 255.0).round() as u8;
+    let mut ass = String::new();
+    let _ = write!(
+        ass,
+        "[Script Info]\n\
+         ScriptType: v4.00+\n\
+         PlayResX: {width}\n\
+         PlayResY: {height}\n\
+         WrapStyle: 2\n\
+         ScaledBorderAndShadow: yes\n\
+         \n\
+         [V4+ Styles]\n\
+         Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+         Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, \
+         Shadow, Alignment, MarginL, MarginR, MarginV, Encoding\n\
+         Style: Danmaku,{font},{size},&H{alpha:02X}FFFFFF,&H{alpha:02X}FFFFFF,&H{alpha:02X}000000,\
+         &H{alpha:02X}000000,0,0,0,0,100,100,0,0,1,1,0,7,0,0,0,1\n\
+         \n\
+         [Events]\n\
+         Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\n",
+        width = options.width,
+        height = options.height,
+        font = options.font,
+        size = options.font_size,
+        alpha = alpha,
+    );
+    ass.push_str(&events);
+    (ass, stats)
+}
+
+// 滚动弹幕轨道的占用情况
+#[derive(Debug, Clone, Copy, Default)]
+struct ScrollLane {
+    enter_at: f64, // 前一条弹幕尾部完全进入屏幕的时间
+    leave_at: f64, // 前一条弹幕完全离开屏幕的时间
+}
+
+impl ScrollLane {
+    // 新弹幕在前一条完全进入屏幕后出现，且在前一条离开前追不上它
+    fn accepts(&self, start: f64, speed: f64, width: f64) -> bool {
+        start >= self.enter_at && start + width / speed >= self.leave_at
+    }
+}
+
+// 估算文字宽度：ASCII 字符按半角计算
+fn text_width(text: &str, size: f64) -> f64 {
+    text.chars()
+        .map(|c| if c.is_ascii() { size * 0.5 } else { size })
+        .sum()
+}
+
+fn ass_time(seconds: f64) -> String {
+    let centis = (seconds.max(0.0) * 100.0).round() as u64;
+    format!(
+        "{}:{:02}:{:02}.{:02}",
+        centis / 360_000,
+        centis / 6_000 % 60,
+        centis / 100 % 60,
+        centis % 100
+    )
+}
+
+// 十进制 RGB 转为 ASS 的 &HBBGGRR&
+fn ass_color(rgb: u32) -> String {
+    let (r, g, b) = ((rgb >> 16) & 0xFF, (rgb >> 8) & 0xFF, rgb & 0xFF);
+    format!("&H{:02X}{:02X}{:02X}&", b, g, r)
+}
+
+// 花括号会被当作样式标签，换行符替换为空格
+fn escape_ass(text: &str) -> String {
+    text.replace('{', "｛")
+        .replace('}', "｝")
+        .replace(['\r', '\n'], " ")
+}
+
+fn unescape_xml(text: &str) -> String {
+    lazy_static! {
+        static ref ENTITY: Regex = Regex::new(r"&(#x[0-9a-fA-F]+|#[0-9]+|[a-z]+);").unwrap();
+    }
+    ENTITY
+        .replace_all(text, |caps: &regex::Captures| {
+            let entity = &caps[1];
+            let decoded = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ if entity.starts_with("#x") => u32::from_str_radix(&entity[2..], 16)
+                    .ok()
+                    .and_then(char::from_u32),
+                _ => entity[1..].parse().ok().and_then(char::from_u32),
+            };
+            decoded.map_or_else(|| caps[0].to_string(), String::from)
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_danmaku() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?><i>
+<d p="12.5,1,25,16777215,1700000000,0,abc,1">第二条</d>
+<d p="3.0,5,25,16711680,1700000000,0,abc,2">顶部 &amp; 红色</d>
+<d p="1.0,1,25,16777215,1700000000,0,abc,3">第一条{滚动}</d>
+<d p="2.0,7,25,16777215,1700000000,0,abc,4">[0,0,1]</d>
+</i>"#;
+        let danmakus = parse_xml(xml);
+        assert_eq!(danmakus.len(), 4);
+        assert_eq!(danmakus[0].text, "第一条{滚动}");
+        assert_eq!(danmakus[2].text, "顶部 & 红色");
+
+        let (ass, stats) = to_ass(&danmakus, &AssOptions::default());
+        assert_eq!(
+            stats,
+            AssStats {
+                written: 3,
+                dropped: 0,
+                unsupported: 1
+            }
+        );
+        assert!(ass.contains("PlayResX: 1920"));
+        assert!(ass.contains("Dialogue: 0,0:00:01.00,0:00:11.00,Danmaku,,0,0,0,,{\\move(1920,0,"));
+        assert!(ass.contains("第一条｛滚动｝"));
+        assert!(ass.contains("{\\an8\\pos(960,0)\\c&H0000FF&}顶部 & 红色"));
+    }
+}
@@ -1,12 +1,22 @@
+use std::collections::HashMap;
 use std::path::Path;
 use std::process::Stdio;
 use tokio::process::Command;
 use tracing::{debug, error, info};
 
 use crate::downloader::error::DownloadError;
+use crate::parser::models::AudioFormat;
 
 pub struct MediaMerger;
 
+/// 输出文件的时长与音视频轨信息
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MediaProbe {
+    pub duration: Option<f64>, // 秒
+    pub has_video: bool,
+    pub has_audio: bool,
+}
+
 impl MediaMerger {
     pub async fn merge_av(
         video_path: &Path,
@@ -74,6 +84,167 @@ impl MediaMerger {
         Ok(())
     }
 
+    /// 使用 ffmpeg concat 分离器按顺序无损拼接多个分段
+    pub async fn concat_segments(
+        segments: &[&Path],
+        output_path: &Path,
+    ) -> Result<(), DownloadError> {
+        for segment in segments {
+            if !segment.exists() {
+                return Err(DownloadError::FileNotFound(segment.to_path_buf()));
+            }
+        }
+
+        let ffmpeg_cmd = Self::find_ffmpeg_path().await?;
+
+        // concat 分离器需要一个列表文件，路径使用绝对路径并转义单引号
+        let mut list = String::new();
+        for segment in segments {
+            let absolute = std::fs::canonicalize(segment)?;
+            let escaped = absolute.to_string_lossy().replace('\'', "'\\''");
+            list.push_str(&format!("file '{}'\n", escaped));
+        }
+        let list_path = output_path.with_extension("concat.txt");
+        tokio::fs::write(&list_path, list).await?;
+
+        debug!("开始拼接 {} 个分段 -> 输出路径: {:?}", segments.len(), output_path);
+
+        let output = Command::new(&ffmpeg_cmd)
+            .arg("-f")
+            .arg("concat")
+            .arg("-safe")
+            .arg("0")
+            .arg("-i")
+            .arg(&list_path)
+            .arg("-c")
+            .arg("copy")
+            .arg("-y")
+            .arg(output_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .await;
+
+        let _ = tokio::fs::remove_file(&list_path).await;
+        let output = output?;
+
+        if !output.status.success() {
+            let err_msg = String::from_utf8_lossy(&output.stderr);
+            error!("❌ ffmpeg 分段拼接失败，错误日志如下：\n{}", err_msg);
+            return Err(DownloadError::FfmpegError(err_msg.to_string()));
+        }
+
+        info!("✅ 分段拼接成功，输出文件: {:?}", output_path);
+        Ok(())
+    }
+
+    /// 提取音频：封装（或转码）为目标格式，并写入标签和封面
+    pub async fn extract_audio(
+        audio_path: &Path,
+        cover_path: Option<&Path>,
+        output_path: &Path,
+        format: AudioFormat,
+        tags: &HashMap<String, String>,
+    ) -> Result<(), DownloadError> {
+        if !audio_path.exists() {
+            return Err(DownloadError::FileNotFound(audio_path.to_path_buf()));
+        }
+        debug!("✅ 音频文件存在: {:?}", audio_path);
+
+        let ffmpeg_cmd = Self::find_ffmpeg_path().await?;
+
+        let cover_path = cover_path.filter(|p| p.exists() && format.supports_cover());
+
+        let mut command = Command::new(&ffmpeg_cmd);
+        command.arg("-i").arg(audio_path);
+        if let Some(cover) = cover_path {
+            command.arg("-i").arg(cover);
+        }
+        command.arg("-map").arg("0:a");
+        if cover_path.is_some() {
+            command
+                .arg("-map")
+                .arg("1:v")
+                .arg("-c:v")
+                .arg("copy")
+                .arg("-disposition:v")
+                .arg("attached_pic");
+        }
+
+        // m4a 直接封装；无损音轨无法放入 m4a 时转为 ALAC
+        let is_lossless = tags
+            .get("codecs")
+            .is_some_and(|c| c.to_lowercase().contains("flac"));
+        let codec_args: &[&str] = match format {
+            AudioFormat::M4a if is_lossless => &["-c:a", "alac"],
+            AudioFormat::M4a => &["-c:a", "copy"],
+            AudioFormat::Mp3 => &["-c:a", "libmp3lame", "-q:a", "0", "-id3v2_version", "3"],
+            AudioFormat::Flac => &["-c:a", "flac"],
+            AudioFormat::Opus => &["-c:a", "libopus", "-b:a", "192k"],
+        };
+        command.args(codec_args);
+
+        for key in ["title", "artist", "album", "track"] {
+            if let Some(value) = tags.get(key).filter(|v| !v.is_empty()) {
+                command.arg("-metadata").arg(format!("{}={}", key, value));
+            }
+        }
+
+        let output = command
+            .arg("-y")
+            .arg(output_path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .await?;
+
+        if !output.status.success() {
+            let err_msg = String::from_utf8_lossy(&output.stderr);
+            error!("❌ ffmpeg 音频提取失败，错误日志如下：\n{}", err_msg);
+            return Err(DownloadError::FfmpegError(err_msg.to_string()));
+        }
+
+        info!("✅ 音频提取成功，输出文件: {:?}", output_path);
+        Ok(())
+    }
+
+    /// 读取媒体文件的时长和音视频轨，用于校验合并结果
+    ///
+    /// 使用 `ffmpeg -i` 输出的文件信息，不依赖 ffprobe。
+    pub async fn probe_media(path: &Path) -> Result<MediaProbe, DownloadError> {
+        if !path.exists() {
+            return Err(DownloadError::FileNotFound(path.to_path_buf()));
+        }
+        let ffmpeg_cmd = Self::find_ffmpeg_path().await?;
+
+        // 未指定输出文件时 ffmpeg 总是以非零状态退出，只解析 stderr
+        let output = Command::new(&ffmpeg_cmd)
+            .arg("-hide_banner")
+            .arg("-i")
+            .arg(path)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .output()
+            .await?;
+
+        let info = String::from_utf8_lossy(&output.stderr);
+        let mut probe = MediaProbe::default();
+        for line in info.lines().map(str::trim) {
+            if let Some(rest) = line.strip_prefix("Duration: ") {
+                probe.duration = rest.split(',').next().and_then(parse_timestamp);
+            } else if line.starts_with("Stream #") {
+                // 封面图也显示为视频轨，仅音频模式只检查音轨
+                probe.has_video |= line.contains(": Video:");
+                probe.has_audio |= line.contains(": Audio:");
+            }
+        }
+        debug!("媒体信息 {:?}: {:?}", path, probe);
+        Ok(probe)
+    }
+
     async fn find_ffmpeg_path() -> Result<String, DownloadError> {
         // 首先检查环境变量
         if let Ok(path) = std::env::var("FFMPEG_PATH") {
@@ -142,3 +313,12 @@ impl MediaMerger {
             .unwrap_or(false)
     }
 }
+
+// 解析 `HH:MM:SS.xx` 格式的时间
+fn parse_timestamp(value: &str) -> Option<f64> {
+    let mut parts = value.trim().split(':');
+    let hours: f64 = parts.next()?.parse().ok()?;
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(hours * 3600.0 + minutes * 60.0 + seconds)
+}
@@ -1,16 +1,26 @@
+pub mod danmaku;
 pub mod merger;
+pub mod template;
 
-use tracing::debug;
+use tracing::{debug, warn};
 
 use crate::{
-    downloader::models::{DownloadTask, FileType},
+    downloader::{
+        archive::{ArchiveEntry, ArchiveKey, DownloadArchive},
+        events::{DownloadEvent, EventSender, emit},
+        models::{DownloadTask, FileType},
+        partial,
+        report::DownloadReport,
+    },
     parser::{detail_parser::parser_trait::ParserOptions, errors::ParseError},
 };
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 pub async fn post_process(
     task: &Vec<DownloadTask>,
     parser_options: &ParserOptions,
+    report: &DownloadReport,
+    events: &EventSender,
 ) -> Result<(), ParseError> {
     debug!("开始后处理，总任务数: {}", task.len());
     
@@ -20,7 +30,32 @@ pub async fn post_process(
     
     for (episode_key, episode_tasks) in task_groups {
         debug!("处理集数/组: {}", episode_key);
-        process_single_episode(&episode_tasks, parser_options).await?;
+        // 组内有任务未下载完成时跳过，避免用残缺的文件合并
+        if let Some(missing) = episode_tasks
+            .iter()
+            .find(|task| !report.is_completed(&task.output_path))
+        {
+            warn!("⏭️ 跳过后处理: {}，{} 未下载完成", episode_key, missing.name);
+            continue;
+        }
+        emit(
+            events,
+            DownloadEvent::MergeStarted {
+                name: episode_key.clone(),
+            },
+        );
+        let result = process_single_episode(&episode_tasks, parser_options).await;
+        emit(
+            events,
+            DownloadEvent::MergeFinished {
+                name: episode_key,
+                error: result.as_ref().err().map(|e| e.to_string()),
+            },
+        );
+        let output = result?;
+        if let (Some(output), Some(archive)) = (output, &parser_options.config().archive) {
+            record_archive(archive, &episode_tasks, &output);
+        }
     }
     
     debug!("所有集数后处理完成");
@@ -93,7 +128,8 @@ fn extract_episode_key(name: &str) -> String {
         .replace("_audio", "")
         .replace(".mp4", "")
         .replace(".m4s", "")
-        .replace(".xml", "");
+        .replace(".xml", "")
+        .replace(".jpg", "");
     
     debug!("清理后的名称: '{}'", clean_name);
     
@@ -161,11 +197,38 @@ fn extract_episode_key(name: &str) -> String {
     clean_name
 }
 
-/// 处理单个集数的任务
+/// 后处理成功后写入下载存档，优先使用视频任务的清晰度和编码
+fn record_archive(archive: &DownloadArchive, episode_tasks: &[&DownloadTask], output: &Path) {
+    let Some((task, key)) = episode_tasks
+        .iter()
+        .filter(|t| matches!(t.file_type, FileType::Video | FileType::Audio))
+        .filter_map(|t| Some((*t, ArchiveKey::from_metadata(&t.metadata)?)))
+        .min_by_key(|(t, _)| t.file_type != FileType::Video)
+    else {
+        return;
+    };
+
+    let entry = ArchiveEntry {
+        key,
+        quality: task
+            .metadata
+            .get("quality")
+            .and_then(|q| q.parse().ok())
+            .unwrap_or(0),
+        codec: task.metadata.get("codecs").cloned().unwrap_or_default(),
+        output: output.display().to_string(),
+        archived_at: chrono::Utc::now().timestamp(),
+    };
+    if let Err(e) = archive.record(entry) {
+        warn!("写入下载存档失败: {}", e);
+    }
+}
+
+/// 处理单个集数的任务，返回最终输出文件
 async fn process_single_episode(
     episode_tasks: &[&DownloadTask],
     parser_options: &ParserOptions,
-) -> Result<(), ParseError> {
+) -> Result<Option<PathBuf>, ParseError> {
     debug!("\n========== 处理单集任务 ==========");
     
     let video_tasks: Vec<&DownloadTask> = episode_tasks.iter().filter(|t| t.file_type == FileType::Video).copied().collect();
@@ -193,7 +256,34 @@ async fn process_single_episode(
 
     debug!("格式确认: DASH={}, DURL={}", is_dash_format, is_durl_format);
 
-    match parser_options {
+    if parser_options.config().audio_only {
+        let cover_task = other_tasks
+            .iter()
+            .find(|t| t.file_type == FileType::Image)
+            .copied();
+        let output =
+            handle_audio_extraction(audio_tasks.first().copied(), cover_task, parser_options.config())
+                .await?;
+        debug!("================================\n");
+        return Ok(Some(output));
+    }
+
+    // DURL 分段流：校验大小后按顺序拼接
+    let segment_tasks: Vec<&DownloadTask> = video_tasks
+        .iter()
+        .filter(|t| t.metadata.contains_key("segment_order"))
+        .copied()
+        .collect();
+    if !segment_tasks.is_empty() {
+        let output = handle_segment_concat(&segment_tasks, parser_options.config()).await?;
+        for other_task in &other_tasks {
+            move_single_file(other_task, &format!("{:?}", other_task.file_type)).await?;
+        }
+        debug!("================================\n");
+        return Ok(Some(output));
+    }
+
+    let output = match parser_options {
         ParserOptions::CommonVideo { config } => {
             debug!("使用普通视频配置处理");
             handle_media_processing(
@@ -202,7 +292,7 @@ async fn process_single_episode(
                 config, 
                 is_dash_format, 
                 is_durl_format
-            ).await?;
+            ).await?
         }
         ParserOptions::Bangumi { config } => {
             debug!("使用番剧配置处理");
@@ -212,7 +302,7 @@ async fn process_single_episode(
                 config, 
                 is_dash_format, 
                 is_durl_format
-            ).await?;
+            ).await?
         }
         ParserOptions::Course { config } => {
             debug!("使用课程配置处理");
@@ -223,10 +313,10 @@ async fn process_single_episode(
                 config, 
                 is_dash_format, 
                 is_durl_format
-            ).await?;
+            ).await?
         }
-    }
-    
+    };
+
     // 处理其它类型的文件（如弹幕）
     for other_task in &other_tasks {
         debug!("移动其它文件: {} ({:?})", other_task.name, other_task.file_type);
@@ -234,7 +324,7 @@ async fn process_single_episode(
     }
     
     debug!("================================\n");
-    Ok(())
+    Ok(output)
 }
 
 /// 处理媒体文件的合并或移动
@@ -244,7 +334,7 @@ async fn handle_media_processing(
     config: &crate::parser::detail_parser::models::DownloadConfig,
     is_dash_format: bool,
     is_durl_format: bool,
-) -> Result<(), ParseError> {
+) -> Result<Option<PathBuf>, ParseError> {
     debug!("\n========== 媒体处理 ==========");
     debug!("配置: merge={}, need_video={}, need_audio={}", config.merge, config.need_video, config.need_audio);
     debug!("格式: DASH={}, DURL={}", is_dash_format, is_durl_format);
@@ -261,7 +351,7 @@ async fn handle_media_processing(
         debug!("  🎵 音频: {}", audio_task.output_path);
         
         // 构造输出文件名 - 使用视频任务的名称，但去除后缀
-        let output_name = clean_filename_for_output(&video_task.name);
+        let output_name = output_name_for(video_task);
         let output_path = Path::new(&config.output_dir)
             .join(&output_name)
             .with_extension("mp4");
@@ -275,24 +365,212 @@ async fn handle_media_processing(
         )
         .await
         .map_err(|e| ParseError::ParseError(format!("合并失败: {}", e)))?;
+        verify_output(&output_path, video_task, true, true).await?;
         
         debug!("✅ DASH格式合并完成");
+        Ok(Some(output_path))
     } else if is_durl_format {
         // DURL格式：已经是合并的流，只需要移动到目标位置
         debug!("执行DURL格式文件移动");
-        if let Some(video_task) = video {
-            move_file_to_output(video_task, config, "DURL视频").await?;
+        let output = if let Some(video_task) = video {
+            Some(move_file_to_output(video_task, config, "DURL视频").await?)
         } else if let Some(audio_task) = audio {
-            move_file_to_output(audio_task, config, "DURL音频").await?;
-        }
+            Some(move_file_to_output(audio_task, config, "DURL音频").await?)
+        } else {
+            None
+        };
         debug!("✅ DURL格式文件移动完成");
+        Ok(output)
     } else {
         debug!("⏭️  跳过后处理：不满足处理条件");
         debug!("   条件检查: DASH需要merge=true + need_video=true + need_audio=true");
         debug!("   条件检查: DURL需要单独的视频或音频流");
+        Ok(None)
     }
-    debug!("==========================\n");
-    Ok(())
+}
+
+/// 校验 DURL 分段大小，并按 `order` 拼接为一个完整文件
+async fn handle_segment_concat(
+    segment_tasks: &[&DownloadTask],
+    config: &crate::parser::detail_parser::models::DownloadConfig,
+) -> Result<PathBuf, ParseError> {
+    debug!("\n========== 分段拼接 ==========");
+    let segments = ordered_segments(segment_tasks).await?;
+
+    if segments.len() == 1 {
+        let output = move_file_to_output(segments[0], config, "DURL视频").await?;
+        debug!("✅ 单分段文件移动完成");
+        return Ok(output);
+    }
+
+    let output_path = output_file(&config.output_dir, &output_name_for(segments[0]), "mp4");
+    let segment_paths: Vec<&Path> = segments.iter().map(|t| Path::new(&t.output_path)).collect();
+
+    merger::MediaMerger::concat_segments(&segment_paths, &output_path)
+        .await
+        .map_err(|e| ParseError::ParseError(format!("分段拼接失败: {}", e)))?;
+
+    verify_output(&output_path, segments[0], true, true).await?;
+
+    // 拼接并校验成功后清理分段文件
+    for path in segment_paths {
+        let _ = tokio::fs::remove_file(path).await;
+        partial::forget(path).await;
+    }
+
+    debug!("✅ {} 个分段拼接完成: {:?}", segments.len(), output_path);
+    Ok(output_path)
+}
+
+/// 按 `segment_order` 排序分段，并校验分段数量与每个分段的大小
+async fn ordered_segments<'a>(
+    segment_tasks: &[&'a DownloadTask],
+) -> Result<Vec<&'a DownloadTask>, ParseError> {
+    let metadata_number = |task: &DownloadTask, key: &str| -> u64 {
+        task.metadata
+            .get(key)
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0)
+    };
+
+    let mut segments = segment_tasks.to_vec();
+    segments.sort_by_key(|t| metadata_number(t, "segment_order"));
+
+    let expected_count = metadata_number(segments[0], "segment_count") as usize;
+    if expected_count > 0 && segments.len() != expected_count {
+        return Err(ParseError::ParseError(format!(
+            "分段缺失: 期望 {} 个分段，实际 {} 个",
+            expected_count,
+            segments.len()
+        )));
+    }
+
+    // 校验每个分段的大小与接口返回的 size 一致
+    for task in &segments {
+        let expected_size = metadata_number(task, "expected_size");
+        let actual_size = tokio::fs::metadata(&task.output_path)
+            .await
+            .map_err(|e| ParseError::ParseError(format!("分段文件不存在: {} ({})", task.output_path, e)))?
+            .len();
+        if expected_size > 0 && actual_size != expected_size {
+            return Err(ParseError::ParseError(format!(
+                "分段大小不一致: {} (期望 {} 字节，实际 {} 字节)",
+                task.output_path, expected_size, actual_size
+            )));
+        }
+    }
+    Ok(segments)
+}
+
+/// 仅音频模式：封装/转码音频并写入标签，输出文件名由音频模板决定
+async fn handle_audio_extraction(
+    audio: Option<&DownloadTask>,
+    cover: Option<&DownloadTask>,
+    config: &crate::parser::detail_parser::models::DownloadConfig,
+) -> Result<PathBuf, ParseError> {
+    debug!("\n========== 音频提取 ==========");
+    let audio_task = audio.ok_or(ParseError::ParseError("音频文件未找到".to_string()))?;
+
+    let mut output_name = template::render_template(&config.audio_template, &audio_task.metadata);
+    if output_name.is_empty() {
+        output_name = clean_filename_for_output(&audio_task.name);
+    }
+    let output_path = Path::new(&config.output_dir)
+        .join(format!("{}.{}", output_name, config.audio_format.extension()));
+
+    debug!("  🎵 音频: {}", audio_task.output_path);
+    debug!("  🎬 输出: {:?}", output_path);
+
+    let cover_path = cover.map(|c| Path::new(&c.output_path));
+    merger::MediaMerger::extract_audio(
+        Path::new(&audio_task.output_path),
+        cover_path,
+        &output_path,
+        config.audio_format,
+        &audio_task.metadata,
+    )
+    .await
+    .map_err(|e| ParseError::ParseError(format!("音频提取失败: {}", e)))?;
+
+    verify_output(&output_path, audio_task, false, true).await?;
+
+    // 提取并校验成功后清理中间文件
+    let _ = tokio::fs::remove_file(&audio_task.output_path).await;
+    partial::forget(Path::new(&audio_task.output_path)).await;
+    if let Some(cover_path) = cover_path {
+        let _ = tokio::fs::remove_file(cover_path).await;
+    }
+
+    debug!("✅ 音频提取完成");
+    Ok(output_path)
+}
+
+/// 校验输出文件的时长与接口返回的 `timelength` 一致，且包含需要的音视频轨
+///
+/// 校验失败时返回错误，调用方不会清理中间文件，可检查后重新合并。
+async fn verify_output(
+    output_path: &Path,
+    task: &DownloadTask,
+    need_video: bool,
+    need_audio: bool,
+) -> Result<(), ParseError> {
+    let probe = match merger::MediaMerger::probe_media(output_path).await {
+        Ok(probe) => probe,
+        Err(e) => {
+            warn!("无法读取输出文件信息，跳过校验: {}", e);
+            return Ok(());
+        }
+    };
+
+    let mut problems = Vec::new();
+    if need_video && !probe.has_video {
+        problems.push("缺少视频轨".to_string());
+    }
+    if need_audio && !probe.has_audio {
+        problems.push("缺少音频轨".to_string());
+    }
+    let expected = task
+        .metadata
+        .get("timelength")
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|&ms| ms > 0.0)
+        .map(|ms| ms / 1000.0);
+    match (expected, probe.duration) {
+        (Some(expected), Some(actual)) if !duration_matches(expected, actual) => {
+            problems.push(format!("时长 {:.1} 秒，预期 {:.1} 秒", actual, expected));
+        }
+        (Some(_), None) => problems.push("无法读取时长".to_string()),
+        _ => {}
+    }
+
+    if problems.is_empty() {
+        debug!("✅ 输出校验通过: {:?}", output_path);
+        return Ok(());
+    }
+    Err(ParseError::ParseError(format!(
+        "输出校验失败: {:?}（{}），已保留中间文件",
+        output_path,
+        problems.join("，")
+    )))
+}
+
+/// 时长误差在 1.5 秒或 1% 以内视为一致
+fn duration_matches(expected: f64, actual: f64) -> bool {
+    (expected - actual).abs() <= (expected * 0.01).max(1.5)
+}
+
+/// 输出文件名：清理后的任务名称，附带实际获取的清晰度
+fn output_name_for(task: &DownloadTask) -> String {
+    let name = clean_filename_for_output(&task.name);
+    match task.metadata.get("quality_desc").filter(|d| !d.is_empty()) {
+        Some(desc) => format!("{} [{}]", name, template::sanitize_filename(desc)),
+        None => name,
+    }
+}
+
+/// 输出目录中的文件路径；标题中可能含有 "."，不能用 `with_extension` 添加扩展名
+fn output_file(output_dir: &str, name: &str, extension: &str) -> PathBuf {
+    Path::new(output_dir).join(format!("{}.{}", name, extension))
 }
 
 /// 清理文件名以用于输出
@@ -307,16 +585,17 @@ fn clean_filename_for_output(name: &str) -> String {
         .replace(".mp4", "")
         .replace(".m4s", "")
         .replace(".xml", "")
+        .replace(".jpg", "")
         .trim()
         .to_string()
 }
 
-/// 移动文件到输出目录
+/// 移动文件到输出目录，返回最终路径
 async fn move_file_to_output(
     task: &DownloadTask,
     config: &crate::parser::detail_parser::models::DownloadConfig,
     file_type: &str,
-) -> Result<(), ParseError> {
+) -> Result<PathBuf, ParseError> {
     use tokio::fs;
     use std::path::Path;
     
@@ -327,7 +606,7 @@ async fn move_file_to_output(
     }
     
     // 构造目标文件名
-    let output_name = clean_filename_for_output(&task.name);
+    let output_name = output_name_for(task);
     let extension = source_path.extension().and_then(|ext| ext.to_str()).unwrap_or("mp4");
     let target_path = Path::new(&config.output_dir)
         .join(&output_name)
@@ -367,9 +646,10 @@ async fn move_file_to_output(
     // 执行文件移动
     fs::rename(source_path, &final_target).await
         .map_err(|e| ParseError::ParseError(format!("文件移动失败: {}", e)))?;
+    partial::forget(source_path).await;
     
     debug!("✅ {}文件移动成功: {:?}", file_type, final_target);
-    Ok(())
+    Ok(final_target)
 }
 
 /// 移动单个文件到目标位置（通用版本，无需配置）
@@ -387,3 +667,62 @@ async fn move_single_file(task: &DownloadTask, file_type: &str) -> Result<(), Pa
     debug!("✅ {}文件保持原位置: {:?}", file_type, source_path);
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn segment(dir: &Path, order: u32, count: u32, size: u64) -> DownloadTask {
+        let output = dir.join(format!("Vol.2 第1.5话-{}.flv", order));
+        let metadata = HashMap::from([
+            ("segment_order".to_string(), order.to_string()),
+            ("segment_count".to_string(), count.to_string()),
+            ("expected_size".to_string(), size.to_string()),
+        ]);
+        DownloadTask::new(
+            String::new(),
+            FileType::Video,
+            "Vol.2 第1.5话".to_string(),
+            output.to_string_lossy().into_owned(),
+            String::new(),
+            metadata,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_ordered_segments() {
+        let dir = std::env::temp_dir().join(format!("bilidl-segments-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let tasks: Vec<DownloadTask> = [2, 0, 1].map(|order| segment(&dir, order, 3, 4)).into();
+        for task in &tasks {
+            std::fs::write(&task.output_path, b"data").unwrap();
+        }
+
+        // 按 segment_order 排序
+        let refs: Vec<&DownloadTask> = tasks.iter().collect();
+        let ordered = ordered_segments(&refs).await.unwrap();
+        let orders: Vec<&str> = ordered
+            .iter()
+            .map(|t| t.metadata["segment_order"].as_str())
+            .collect();
+        assert_eq!(orders, ["0", "1", "2"]);
+
+        // 分段缺失
+        assert!(ordered_segments(&refs[..2]).await.is_err());
+
+        // 分段大小与接口返回的 size 不一致
+        std::fs::write(&tasks[1].output_path, b"truncated").unwrap();
+        assert!(ordered_segments(&refs).await.is_err());
+
+        // 分段文件不存在
+        std::fs::remove_file(&tasks[1].output_path).unwrap();
+        assert!(ordered_segments(&refs).await.is_err());
+
+        // 标题中的 "." 不会被当作扩展名截断
+        let output = output_file("out", &output_name_for(&tasks[0]), "mp4");
+        assert_eq!(output, Path::new("out").join("Vol.2 第1.5话.mp4"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+/// 根据任务元数据渲染文件名模板
+///
+/// 模板中的 `{key}` 会被替换为元数据中对应的值，未知的占位符替换为空字符串。
+/// 渲染结果会去除文件系统不允许的字符。
+pub fn render_template(template: &str, metadata: &HashMap<String, String>) -> String {
+    let mut rendered = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        match rest[start..].find('}') {
+            Some(end) => {
+                let key = &rest[start + 1..start + end];
+                if let Some(value) = metadata.get(key) {
+                    rendered.push_str(value);
+                }
+                rest = &rest[start + end + 1..];
+            }
+            None => {
+                rendered.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    sanitize_filename(&rendered)
+}
+
+/// 去除文件名中不允许出现的字符
+pub fn sanitize_filename(name: &str) -> String {
+    let cleaned: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    cleaned.trim().trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let metadata = HashMap::from([
+            ("title".to_string(), "第1话".to_string()),
+            ("album".to_string(), "番剧/名".to_string()),
+            ("track".to_string(), "1".to_string()),
+        ]);
+
+        assert_eq!(
+            render_template("{album} - {track} - {title}", &metadata),
+            "番剧_名 - 1 - 第1话"
+        );
+        assert_eq!(render_template("{artist}{title}", &metadata), "第1话");
+        assert_eq!(render_template("{title", &metadata), "{title");
+    }
+}
@@ -0,0 +1,100 @@
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::utils::write_private;
+
+/// 记录未完成下载命令的文件，与状态文件一样位于当前目录
+pub const RESUME_FILE: &str = "resume.json";
+
+// 携带登录凭据的选项，不写入文件
+const SECRET_OPTIONS: [&str; 2] = ["--cookie", "--header"];
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ResumePoint {
+    pub args: Vec<String>, // 不含程序名的命令行参数，不含凭据选项
+    #[serde(default)]
+    pub secrets_removed: bool, // 原命令中的 --cookie/--header 未保存
+    saved_at: i64,         // unix 时间戳（秒）
+}
+
+/// 记录本次下载命令，下载全部完成后应调用 [`clear_resume_point`]
+///
+/// `--cookie` 和 `--header` 及其值不会写入文件。
+pub fn save_resume_point(args: &[String]) -> io::Result<()> {
+    let (args, secrets) = split_secrets(args);
+    let point = ResumePoint {
+        args,
+        secrets_removed: !secrets.is_empty(),
+        saved_at: chrono::Utc::now().timestamp(),
+    };
+    let content = serde_json::to_vec_pretty(&point).map_err(io::Error::other)?;
+    write_private(Path::new(RESUME_FILE), content)
+}
+
+/// 读取上次未完成的下载命令
+pub fn load_resume_point() -> io::Result<Option<ResumePoint>> {
+    match std::fs::read(RESUME_FILE) {
+        Ok(content) => {
+            let point: ResumePoint = serde_json::from_slice(&content)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            Ok(Some(point))
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn clear_resume_point() {
+    if Path::new(RESUME_FILE).exists() {
+        let _ = std::fs::remove_file(RESUME_FILE);
+    }
+}
+
+/// 将命令行参数分为普通参数和凭据选项（`--cookie`/`--header` 及其值）
+pub fn split_secrets(args: &[String]) -> (Vec<String>, Vec<String>) {
+    let mut rest = Vec::new();
+    let mut secrets = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--" {
+            rest.push(arg.clone());
+            rest.extend(iter.by_ref().cloned());
+        } else if SECRET_OPTIONS.contains(&arg.as_str()) {
+            secrets.push(arg.clone());
+            secrets.extend(iter.next().cloned());
+        } else if SECRET_OPTIONS
+            .iter()
+            .any(|option| arg.starts_with(&format!("{}=", option)))
+        {
+            secrets.push(arg.clone());
+        } else {
+            rest.push(arg.clone());
+        }
+    }
+    (rest, secrets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_secrets() {
+        let args: Vec<String> = [
+            "--cookie",
+            "SESSDATA=abc",
+            "download",
+            "BV1xx411c7mD",
+            "--header=X-Token: 1",
+            "--quality",
+            "4k",
+        ]
+        .map(String::from)
+        .to_vec();
+        let (rest, secrets) = split_secrets(&args);
+        assert_eq!(rest, ["download", "BV1xx411c7mD", "--quality", "4k"]);
+        assert_eq!(secrets, ["--cookie", "SESSDATA=abc", "--header=X-Token: 1"]);
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::parser::errors::ParseError;
+use crate::post_process::template::render_template;
+
+/// 默认检查间隔（分钟）
+pub const DEFAULT_INTERVAL_MINUTES: u64 = 30;
+
+/// 订阅配置文件
+///
+/// ```toml
+/// interval_minutes = 30
+/// output = "./mirror/{source}"
+///
+/// [[source]]
+/// name = "稍后再看"
+/// type = "favorites"
+/// id = 123456
+/// quality = "4k"
+///
+/// [[source]]
+/// type = "uploader"
+/// mid = 2
+/// output = "./mirror/up/{owner}"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchConfig {
+    #[serde(default = "default_interval")]
+    pub interval_minutes: u64, // 默认检查间隔
+    pub output: Option<String>, // 默认输出目录模板，未设置时使用 --output-dir
+    pub quality: Option<String>, // 默认清晰度，未设置时使用 --quality
+    pub archive: Option<PathBuf>, // 下载存档，未设置时与 download 命令相同，默认为配置目录下的 archive.jsonl
+    #[serde(rename = "source", default)]
+    pub sources: Vec<WatchSource>,
+}
+
+fn default_interval() -> u64 {
+    DEFAULT_INTERVAL_MINUTES
+}
+
+/// 一个订阅源
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchSource {
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub kind: SourceKind,
+    pub output: Option<String>,        // 输出目录模板，覆盖全局设置
+    pub quality: Option<String>,       // 清晰度，覆盖全局设置
+    pub interval_minutes: Option<u64>, // 检查间隔，覆盖全局设置
+}
+
+/// 订阅源类型
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SourceKind {
+    /// 收藏夹，`id` 为收藏夹的 media_id
+    Favorites { id: u64 },
+    /// UP主的全部投稿
+    Uploader { mid: u64 },
+    /// UP主的合集，`id` 为合集的 season_id
+    Collection { mid: u64, id: u64 },
+    /// 当前登录账号的追番列表
+    FollowedBangumi,
+}
+
+impl WatchConfig {
+    /// 读取配置文件，相对路径的存档文件以配置文件所在目录为基准
+    pub fn load(path: &Path) -> Result<Self, ParseError> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| ParseError::ParseError(format!("读取订阅配置 {:?} 失败: {}", path, e)))?;
+        let mut config: WatchConfig = toml::from_str(&content)
+            .map_err(|e| ParseError::ParseError(format!("订阅配置 {:?} 格式错误: {}", path, e)))?;
+
+        if config.sources.is_empty() {
+            return Err(ParseError::ParseError(format!(
+                "订阅配置 {:?} 中没有 [[source]]",
+                path
+            )));
+        }
+        if config.interval_minutes == 0
+            || config.sources.iter().any(|s| s.interval_minutes == Some(0))
+        {
+            return Err(ParseError::ParseError(
+                "interval_minutes 必须大于 0".to_string(),
+            ));
+        }
+
+        let base = path.parent().unwrap_or(Path::new("."));
+        // 相对路径相对于配置文件所在目录
+        if let Some(archive) = config.archive.as_mut()
+            && archive.is_relative()
+        {
+            *archive = base.join(&*archive);
+        }
+        Ok(config)
+    }
+}
+
+impl WatchSource {
+    /// 日志中显示的名称
+    pub fn display_name(&self) -> String {
+        if let Some(name) = &self.name {
+            return name.clone();
+        }
+        match &self.kind {
+            SourceKind::Favorites { id } => format!("收藏夹 {}", id),
+            SourceKind::Uploader { mid } => format!("UP主 {}", mid),
+            SourceKind::Collection { id, .. } => format!("合集 {}", id),
+            SourceKind::FollowedBangumi => "追番列表".to_string(),
+        }
+    }
+
+    pub fn interval(&self, config: &WatchConfig) -> Duration {
+        let minutes = self.interval_minutes.unwrap_or(config.interval_minutes);
+        Duration::from_secs(minutes * 60)
+    }
+
+    pub fn output_template<'a>(&'a self, config: &'a WatchConfig) -> Option<&'a str> {
+        self.output.as_deref().or(config.output.as_deref())
+    }
+
+    pub fn quality<'a>(&'a self, config: &'a WatchConfig) -> Option<&'a str> {
+        self.quality.as_deref().or(config.quality.as_deref())
+    }
+}
+
+/// 渲染输出目录模板
+///
+/// 按路径分隔符逐段渲染，占位符的值中的 `/` 会被替换，模板本身的目录层级保留。
+pub fn render_output_dir(template: &str, vars: &HashMap<String, String>) -> PathBuf {
+    let mut path = PathBuf::new();
+    for (index, segment) in template.split(['/', '\\']).enumerate() {
+        if index == 0 && segment.is_empty() {
+            path.push("/");
+        } else if segment.contains('{') {
+            path.push(render_template(segment, vars));
+        } else if !segment.is_empty() {
+            path.push(segment);
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_watch_config() {
+        let config: WatchConfig = toml::from_str(
+            r#"
+output = "./mirror/{source}"
+
+[[source]]
+name = "音乐"
+type = "favorites"
+id = 123456
+quality = "4k"
+
+[[source]]
+type = "collection"
+mid = 2
+id = 789
+interval_minutes = 5
+
+[[source]]
+type = "followed_bangumi"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(config.interval_minutes, DEFAULT_INTERVAL_MINUTES);
+        assert_eq!(config.sources[0].kind, SourceKind::Favorites { id: 123456 });
+        assert_eq!(config.sources[0].quality(&config), Some("4k"));
+        assert_eq!(
+            config.sources[1].kind,
+            SourceKind::Collection { mid: 2, id: 789 }
+        );
+        assert_eq!(
+            config.sources[1].interval(&config),
+            Duration::from_secs(300)
+        );
+        assert_eq!(config.sources[2].kind, SourceKind::FollowedBangumi);
+
+        let vars = HashMap::from([("source".to_string(), "AC/DC".to_string())]);
+        assert_eq!(
+            render_output_dir(config.sources[0].output_template(&config).unwrap(), &vars),
+            PathBuf::from("./mirror/AC_DC")
+        );
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+use tracing::debug;
+
+use super::config::SourceKind;
+use crate::common::client::client::BiliClient;
+use crate::common::client::error::ApiError;
+use crate::common::client::models::common::CommonResponse;
+use crate::downloader::archive::DownloadArchive;
+use crate::parser::detail_parser::error_utils::handle_api_error;
+use crate::parser::errors::ParseError;
+
+/// 追番时的集数范围：覆盖整季，已存档的剧集由解析器跳过
+const ALL_EPISODES: &str = "1-9999";
+/// 单个订阅源最多翻页数，避免异常情况下无限请求
+const MAX_PAGES: u32 = 200;
+
+/// 订阅源中的一项待下载内容
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchItem {
+    pub url: String,
+    pub title: String,
+    pub owner: String,                 // UP主或番剧名，用于输出目录模板
+    pub episode_range: Option<String>, // 番剧整季下载时的集数范围
+}
+
+/// 列出订阅源中尚未存档的内容
+///
+/// 列表按时间从新到旧翻页，某一页的内容全部已存档时停止，之后的内容视为已下载过。
+/// `archive_kind` 为普通视频在存档中的类型（仅音频模式下为 `video-audio`）。
+pub async fn list_new_items(
+    client: &BiliClient,
+    kind: &SourceKind,
+    archive: &DownloadArchive,
+    archive_kind: &str,
+) -> Result<Vec<WatchItem>, ParseError> {
+    if *kind == SourceKind::FollowedBangumi {
+        return list_followed_bangumi(client).await;
+    }
+
+    let mut items = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let (videos, has_more) = match kind {
+            SourceKind::Favorites { id } => favorites_page(client, *id, page).await?,
+            SourceKind::Uploader { mid } => uploader_page(client, *mid, page).await?,
+            SourceKind::Collection { mid, id } => collection_page(client, *mid, *id, page).await?,
+            SourceKind::FollowedBangumi => unreachable!(),
+        };
+        let page_len = videos.len();
+        let new_items: Vec<WatchItem> = videos
+            .into_iter()
+            .filter(|video| !archive.has_item(archive_kind, &video.bvid))
+            .map(|video| WatchItem {
+                url: format!("https://www.bilibili.com/video/{}", video.bvid),
+                title: video.title,
+                owner: video.owner,
+                episode_range: None,
+            })
+            .collect();
+        debug!(
+            "第 {} 页: {} 项，其中 {} 项未存档",
+            page,
+            page_len,
+            new_items.len()
+        );
+
+        let all_archived = page_len > 0 && new_items.is_empty();
+        items.extend(new_items);
+        if !has_more || all_archived {
+            break;
+        }
+    }
+    Ok(items)
+}
+
+// 列表中的一个视频
+struct ListedVideo {
+    bvid: String,
+    title: String,
+    owner: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FavoritesPage {
+    medias: Option<Vec<FavoriteMedia>>,
+    has_more: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct FavoriteMedia {
+    bvid: String,
+    title: String,
+    #[serde(rename = "type")]
+    media_type: i32, // 2 为视频
+    upper: Option<Upper>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Upper {
+    name: String,
+}
+
+async fn favorites_page(
+    client: &BiliClient,
+    media_id: u64,
+    page: u32,
+) -> Result<(Vec<ListedVideo>, bool), ParseError> {
+    let params = HashMap::from([
+        ("media_id".to_string(), media_id.to_string()),
+        ("pn".to_string(), page.to_string()),
+        ("ps".to_string(), "20".to_string()),
+        ("platform".to_string(), "web".to_string()),
+    ]);
+    let data: FavoritesPage = fetch(
+        client,
+        "https://api.bilibili.com/x/v3/fav/resource/list",
+        params,
+        "收藏夹",
+    )
+    .await?;
+
+    let videos = data
+        .medias
+        .unwrap_or_default()
+        .into_iter()
+        // 失效视频无法下载
+        .filter(|media| media.media_type == 2 && media.title != "已失效视频")
+        .map(|media| ListedVideo {
+            bvid: media.bvid,
+            title: media.title,
+            owner: media.upper.map(|upper| upper.name).unwrap_or_default(),
+        })
+        .collect();
+    Ok((videos, data.has_more))
+}
+
+#[derive(Debug, Deserialize)]
+struct UploaderPage {
+    list: UploaderList,
+    page: PageInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploaderList {
+    vlist: Vec<UploaderVideo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploaderVideo {
+    bvid: String,
+    title: String,
+    author: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageInfo {
+    #[serde(alias = "page_num")]
+    pn: u32,
+    #[serde(alias = "page_size")]
+    ps: u32,
+    #[serde(alias = "total")]
+    count: u32,
+}
+
+impl PageInfo {
+    fn has_more(&self) -> bool {
+        self.pn * self.ps < self.count
+    }
+}
+
+async fn uploader_page(
+    client: &BiliClient,
+    mid: u64,
+    page: u32,
+) -> Result<(Vec<ListedVideo>, bool), ParseError> {
+    let params = HashMap::from([
+        ("mid".to_string(), mid.to_string()),
+        ("pn".to_string(), page.to_string()),
+        ("ps".to_string(), "30".to_string()),
+        ("order".to_string(), "pubdate".to_string()),
+    ]);
+    let data: UploaderPage = fetch(
+        client,
+        "https://api.bilibili.com/x/space/wbi/arc/search",
+        params,
+        "UP主投稿",
+    )
+    .await?;
+
+    let has_more = data.page.has_more();
+    let videos = data
+        .list
+        .vlist
+        .into_iter()
+        .map(|video| ListedVideo {
+            bvid: video.bvid,
+            title: video.title,
+            owner: video.author,
+        })
+        .collect();
+    Ok((videos, has_more))
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectionPage {
+    archives: Vec<CollectionArchive>,
+    meta: Option<CollectionMeta>,
+    page: PageInfo,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectionArchive {
+    bvid: String,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct CollectionMeta {
+    name: String,
+}
+
+async fn collection_page(
+    client: &BiliClient,
+    mid: u64,
+    season_id: u64,
+    page: u32,
+) -> Result<(Vec<ListedVideo>, bool), ParseError> {
+    let params = HashMap::from([
+        ("mid".to_string(), mid.to_string()),
+        ("season_id".to_string(), season_id.to_string()),
+        ("page_num".to_string(), page.to_string()),
+        ("page_size".to_string(), "30".to_string()),
+        // 新投稿排在前面
+        ("sort_reverse".to_string(), "true".to_string()),
+    ]);
+    let data: CollectionPage = fetch(
+        client,
+        "https://api.bilibili.com/x/polymer/web-space/seasons_archives_list",
+        params,
+        "合集",
+    )
+    .await?;
+
+    let has_more = data.page.has_more();
+    let owner = data.meta.map(|meta| meta.name).unwrap_or_default();
+    let videos = data
+        .archives
+        .into_iter()
+        .map(|video| ListedVideo {
+            bvid: video.bvid,
+            title: video.title,
+            owner: owner.clone(),
+        })
+        .collect();
+    Ok((videos, has_more))
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowedBangumiPage {
+    list: Option<Vec<FollowedSeason>>,
+    total: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct FollowedSeason {
+    season_id: u64,
+    title: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct NavInfo {
+    #[serde(rename = "isLogin")]
+    is_login: bool,
+    mid: Option<u64>,
+}
+
+// 追番列表：每部番剧整季交给番剧解析器，由下载存档跳过已下载的剧集
+async fn list_followed_bangumi(client: &BiliClient) -> Result<Vec<WatchItem>, ParseError> {
+    let nav: NavInfo = fetch(
+        client,
+        "https://api.bilibili.com/x/web-interface/nav",
+        HashMap::new(),
+        "账号信息",
+    )
+    .await
+    .or_else(|e| match e {
+        // 未登录时 nav 接口返回 -101
+        ParseError::LoginRequired => Ok(NavInfo {
+            is_login: false,
+            mid: None,
+        }),
+        e => Err(e),
+    })?;
+    let mid = match (nav.is_login, nav.mid) {
+        (true, Some(mid)) => mid,
+        _ => return Err(ParseError::LoginRequired),
+    };
+
+    let mut items = Vec::new();
+    for page in 1..=MAX_PAGES {
+        let params = HashMap::from([
+            ("type".to_string(), "1".to_string()),
+            ("follow_status".to_string(), "0".to_string()),
+            ("pn".to_string(), page.to_string()),
+            ("ps".to_string(), "30".to_string()),
+            ("vmid".to_string(), mid.to_string()),
+        ]);
+        let data: FollowedBangumiPage = fetch(
+            client,
+            "https://api.bilibili.com/x/space/bangumi/follow/list",
+            params,
+            "追番列表",
+        )
+        .await?;
+
+        let seasons = data.list.unwrap_or_default();
+        let page_len = seasons.len() as u32;
+        items.extend(seasons.into_iter().map(|season| WatchItem {
+            url: format!(
+                "https://www.bilibili.com/bangumi/play/ss{}",
+                season.season_id
+            ),
+            owner: season.title.clone(),
+            title: season.title,
+            episode_range: Some(ALL_EPISODES.to_string()),
+        }));
+        if page_len == 0 || page * 30 >= data.total {
+            break;
+        }
+    }
+    Ok(items)
+}
+
+// 请求列表接口并检查错误码
+async fn fetch<T: serde::de::DeserializeOwned>(
+    client: &BiliClient,
+    url: &str,
+    params: HashMap<String, String>,
+    context: &str,
+) -> Result<T, ParseError> {
+    // code 不为 0 时 BiliClient 返回 ApiError::ApiError，在这里转换为解析错误
+    let resp = client
+        .get_auto::<CommonResponse<T>>(url, params)
+        .await
+        .map_err(|e| match e {
+            ApiError::ApiError(-101, _) => ParseError::LoginRequired,
+            ApiError::ApiError(code, message) => handle_api_error(code as i32, &message, context),
+            e => ParseError::NetworkError(e.to_string()),
+        })?;
+    resp.data
+        .ok_or_else(|| ParseError::ParseError(format!("{}接口未返回数据", context)))
+}